@@ -1,3 +1,5 @@
+use crate::auth::jwt_keys::JwtKeys;
+use crate::auth::tokens::TokenPolicy;
 use crate::auth::traits::{CooldownError, CooldownManager, CooldownType, EmailService};
 use async_trait::async_trait;
 use sea_orm::DatabaseConnection;
@@ -7,6 +9,7 @@ use std::pin::Pin;
 use std::sync::Arc;
 
 type EmailFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+type MagicLinkSender = Arc<dyn Fn(String, String, String, i64) -> EmailFuture + Send + Sync>;
 type CooldownFuture = Pin<Box<dyn Future<Output = Result<(), CooldownError>> + Send + 'static>>;
 
 #[async_trait]
@@ -20,6 +23,19 @@ pub trait EmailTaskDispatcher: Send + Sync + Clone {
         reset_url: String,
         expiry_hours: i64,
     );
+
+    async fn send_magic_link_email(
+        &self,
+        email: String,
+        name: String,
+        login_url: String,
+        expiry_minutes: i64,
+    ) {
+        let _ = (email, name, login_url, expiry_minutes);
+        tracing::warn!(
+            "EmailTaskDispatcher does not implement send_magic_link_email; email dropped"
+        );
+    }
 }
 
 #[derive(Clone)]
@@ -61,6 +77,18 @@ where
             .send_password_reset_email(email, name, reset_url, expiry_hours)
             .await;
     }
+
+    async fn send_magic_link_email(
+        &self,
+        email: String,
+        name: String,
+        login_url: String,
+        expiry_minutes: i64,
+    ) {
+        self.dispatcher
+            .send_magic_link_email(email, name, login_url, expiry_minutes)
+            .await;
+    }
 }
 
 #[async_trait]
@@ -260,6 +288,7 @@ where
 pub struct ClosureEmailService {
     send_registration: Arc<dyn Fn(String, String, String) -> EmailFuture + Send + Sync>,
     send_password_reset: Arc<dyn Fn(String, String, String, i64) -> EmailFuture + Send + Sync>,
+    send_magic_link: Option<MagicLinkSender>,
 }
 
 impl ClosureEmailService {
@@ -280,8 +309,21 @@ impl ClosureEmailService {
             send_password_reset: Arc::new(move |email, name, reset_url, expiry_hours| {
                 Box::pin(send_password_reset(email, name, reset_url, expiry_hours))
             }),
+            send_magic_link: None,
         }
     }
+
+    /// Handle magic-link login emails; without this they are dropped
+    pub fn with_magic_link<FLink, FutLink>(mut self, send_magic_link: FLink) -> Self
+    where
+        FLink: Fn(String, String, String, i64) -> FutLink + Send + Sync + 'static,
+        FutLink: Future<Output = ()> + Send + 'static,
+    {
+        self.send_magic_link = Some(Arc::new(move |email, name, login_url, expiry_minutes| {
+            Box::pin(send_magic_link(email, name, login_url, expiry_minutes))
+        }));
+        self
+    }
 }
 
 #[async_trait]
@@ -299,6 +341,19 @@ impl EmailService for ClosureEmailService {
     ) {
         (self.send_password_reset)(email, name, reset_url, expiry_hours).await
     }
+
+    async fn send_magic_link_email(
+        &self,
+        email: String,
+        name: String,
+        login_url: String,
+        expiry_minutes: i64,
+    ) {
+        match &self.send_magic_link {
+            Some(send) => send(email, name, login_url, expiry_minutes).await,
+            None => tracing::warn!("ClosureEmailService has no magic link handler; email dropped"),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -398,6 +453,8 @@ impl SeaOrmCooldownBackend {
             crate::auth::traits::CooldownType::Login => "login",
             crate::auth::traits::CooldownType::EmailResend => "email_resend",
             crate::auth::traits::CooldownType::EmailForgotPassword => "email_forgot_password",
+            crate::auth::traits::CooldownType::MfaVerify => "mfa_verify",
+            crate::auth::traits::CooldownType::EmailMagicLink => "email_magic_link",
         }
     }
 }
@@ -559,12 +616,54 @@ impl crate::auth::traits::AuditLogger for SeaOrmAuditLogger {
             crate::auth::traits::AuthEventType::TokenRefreshFailed => {
                 crate::auth::entities::auth_events::EventType::TokenRefreshFailed
             }
+            crate::auth::traits::AuthEventType::TokenReuseDetected => {
+                crate::auth::entities::auth_events::EventType::TokenReuseDetected
+            }
             crate::auth::traits::AuthEventType::PasswordResetRequest => {
                 crate::auth::entities::auth_events::EventType::PasswordResetRequest
             }
             crate::auth::traits::AuthEventType::PasswordReset => {
                 crate::auth::entities::auth_events::EventType::PasswordReset
             }
+            crate::auth::traits::AuthEventType::MfaChallengeIssued => {
+                crate::auth::entities::auth_events::EventType::MfaChallengeIssued
+            }
+            crate::auth::traits::AuthEventType::MfaVerified => {
+                crate::auth::entities::auth_events::EventType::MfaVerified
+            }
+            crate::auth::traits::AuthEventType::MfaFailed => {
+                crate::auth::entities::auth_events::EventType::MfaFailed
+            }
+            crate::auth::traits::AuthEventType::MfaEnabled => {
+                crate::auth::entities::auth_events::EventType::MfaEnabled
+            }
+            crate::auth::traits::AuthEventType::MfaDisabled => {
+                crate::auth::entities::auth_events::EventType::MfaDisabled
+            }
+            crate::auth::traits::AuthEventType::RecoveryCodeUsed => {
+                crate::auth::entities::auth_events::EventType::RecoveryCodeUsed
+            }
+            crate::auth::traits::AuthEventType::RecoveryCodesRegenerated => {
+                crate::auth::entities::auth_events::EventType::RecoveryCodesRegenerated
+            }
+            crate::auth::traits::AuthEventType::PasskeyRegistered => {
+                crate::auth::entities::auth_events::EventType::PasskeyRegistered
+            }
+            crate::auth::traits::AuthEventType::PasskeyRemoved => {
+                crate::auth::entities::auth_events::EventType::PasskeyRemoved
+            }
+            crate::auth::traits::AuthEventType::MagicLinkRequest => {
+                crate::auth::entities::auth_events::EventType::MagicLinkRequest
+            }
+            crate::auth::traits::AuthEventType::SessionRevoked => {
+                crate::auth::entities::auth_events::EventType::SessionRevoked
+            }
+            crate::auth::traits::AuthEventType::IdentityLinked => {
+                crate::auth::entities::auth_events::EventType::IdentityLinked
+            }
+            crate::auth::traits::AuthEventType::IdentityUnlinked => {
+                crate::auth::entities::auth_events::EventType::IdentityUnlinked
+            }
         };
 
         let shared_payload = crate::auth::entities::auth_events::AuthEventPayload {
@@ -635,16 +734,52 @@ impl crate::auth::traits::MetricsRecorder for FnMetricsRecorder {
 #[derive(Clone)]
 pub struct StaticConfigProvider {
     frontend_url: Arc<str>,
-    jwt_secret: Arc<str>,
+    jwt_keys: JwtKeys,
+    refresh_token_key: Arc<str>,
+    mfa_secret_key: Option<Arc<str>>,
+    token_policy: TokenPolicy,
+    refresh_reuse_grace_seconds: i64,
 }
 
 impl StaticConfigProvider {
+    /// HS256 access tokens signed with `jwt_secret`, which also keys the
+    /// refresh token hash
     pub fn new(frontend_url: impl Into<String>, jwt_secret: impl Into<String>) -> Self {
+        let jwt_secret = jwt_secret.into();
         Self {
             frontend_url: Arc::<str>::from(frontend_url.into()),
-            jwt_secret: Arc::<str>::from(jwt_secret.into()),
+            jwt_keys: JwtKeys::hs256(&jwt_secret),
+            refresh_token_key: Arc::<str>::from(jwt_secret),
+            mfa_secret_key: None,
+            token_policy: TokenPolicy::default(),
+            refresh_reuse_grace_seconds: 0,
         }
     }
+
+    pub fn with_jwt_keys(mut self, keys: JwtKeys) -> Self {
+        self.jwt_keys = keys;
+        self
+    }
+
+    pub fn with_refresh_token_key(mut self, key: impl Into<String>) -> Self {
+        self.refresh_token_key = Arc::from(key.into());
+        self
+    }
+
+    pub fn with_mfa_secret_key(mut self, key: impl Into<String>) -> Self {
+        self.mfa_secret_key = Some(Arc::from(key.into()));
+        self
+    }
+
+    pub fn with_token_policy(mut self, policy: TokenPolicy) -> Self {
+        self.token_policy = policy;
+        self
+    }
+
+    pub fn with_refresh_reuse_grace_seconds(mut self, seconds: i64) -> Self {
+        self.refresh_reuse_grace_seconds = seconds;
+        self
+    }
 }
 
 impl crate::auth::traits::ConfigProvider for StaticConfigProvider {
@@ -652,8 +787,26 @@ impl crate::auth::traits::ConfigProvider for StaticConfigProvider {
         &self.frontend_url
     }
 
-    fn jwt_secret(&self) -> &str {
-        &self.jwt_secret
+    fn jwt_keys(&self) -> &JwtKeys {
+        &self.jwt_keys
+    }
+
+    fn refresh_token_key(&self) -> &str {
+        &self.refresh_token_key
+    }
+
+    fn mfa_secret_key(&self) -> &str {
+        self.mfa_secret_key
+            .as_deref()
+            .unwrap_or(&self.refresh_token_key)
+    }
+
+    fn token_policy(&self) -> &TokenPolicy {
+        &self.token_policy
+    }
+
+    fn refresh_reuse_grace_seconds(&self) -> i64 {
+        self.refresh_reuse_grace_seconds
     }
 }
 
@@ -675,6 +828,29 @@ impl AuthTaskQueue {
         }
     }
 
+    /// Wrap an existing queue, e.g. one whose storage publishes to a
+    /// `TaskEventBus`.
+    pub fn from_queue(
+        inner: crate::background_jobs::TaskQueue<crate::background_jobs::DurableStorage>,
+    ) -> Self {
+        Self { inner }
+    }
+
+    /// Encrypt auth email payloads at rest; they carry live verification and
+    /// reset tokens. Workers need the same cipher via `TaskWorker::with_payload_cipher`.
+    pub fn with_payload_cipher(
+        mut self,
+        cipher: Arc<crate::background_jobs::PayloadCipher>,
+    ) -> Self {
+        self.inner = self
+            .inner
+            .with_cipher(cipher)
+            .with_sensitive_task_type(crate::auth::worker::tasks::EMAIL_REGISTRATION_TASK_TYPE)
+            .with_sensitive_task_type(crate::auth::worker::tasks::EMAIL_PASSWORD_RESET_TASK_TYPE)
+            .with_sensitive_task_type(crate::auth::worker::tasks::EMAIL_MAGIC_LINK_TASK_TYPE);
+        self
+    }
+
     /// Access the underlying `crate::background_jobs::TaskQueue` (e.g. to enqueue
     /// app-specific tasks from a wrapper queue struct).
     pub fn inner(
@@ -712,27 +888,68 @@ impl EmailTaskDispatcher for AuthTaskQueue {
         )
         .await;
     }
+
+    async fn send_magic_link_email(
+        &self,
+        email: String,
+        name: String,
+        login_url: String,
+        expiry_minutes: i64,
+    ) {
+        crate::auth::worker::tasks::enqueue_email_magic_link(
+            &self.inner,
+            email,
+            name,
+            login_url,
+            expiry_minutes,
+        )
+        .await;
+    }
 }
 
-/// A [`ConfigProvider`] that reads `FRONTEND_URL` and `JWT_SECRET` from env vars.
+/// A [`ConfigProvider`] that reads `FRONTEND_URL`, the JWT keys (see
+/// [`JwtKeys::from_env`]), the token policy (see [`TokenPolicy::from_env`]),
+/// `MFA_ISSUER`, `WEBAUTHN_RP_ID`, `REFRESH_TOKEN_KEY` (defaulting to
+/// `JWT_SECRET`), `MFA_SECRET_KEY` (defaulting to the refresh token key) and
+/// `REFRESH_TOKEN_REUSE_GRACE_SECONDS` from env vars.
 /// This is the standard config provider for apps that follow the Kaleido env
 /// convention. Apps with a different env naming can still use [`StaticConfigProvider`].
 #[derive(Clone)]
 pub struct EnvConfigProvider {
     frontend_url: Arc<str>,
-    jwt_secret: Arc<str>,
+    jwt_keys: JwtKeys,
+    refresh_token_key: Arc<str>,
+    mfa_secret_key: Arc<str>,
+    token_policy: TokenPolicy,
+    mfa_issuer: Arc<str>,
+    webauthn_rp_id: Option<Arc<str>>,
+    refresh_reuse_grace_seconds: i64,
 }
 
 impl EnvConfigProvider {
     pub fn from_env() -> Self {
+        let refresh_token_key = std::env::var("REFRESH_TOKEN_KEY")
+            .or_else(|_| std::env::var("JWT_SECRET"))
+            .unwrap_or_else(|_| "change_me_in_dev".to_string());
         Self {
             frontend_url: Arc::from(
                 std::env::var("FRONTEND_URL")
                     .unwrap_or_else(|_| "http://localhost:5173".to_string()),
             ),
-            jwt_secret: Arc::from(
-                std::env::var("JWT_SECRET").unwrap_or_else(|_| "change_me_in_dev".to_string()),
+            jwt_keys: JwtKeys::from_env().expect("invalid JWT key configuration"),
+            mfa_secret_key: Arc::from(
+                std::env::var("MFA_SECRET_KEY").unwrap_or_else(|_| refresh_token_key.clone()),
+            ),
+            refresh_token_key: Arc::from(refresh_token_key),
+            token_policy: TokenPolicy::from_env(),
+            mfa_issuer: Arc::from(
+                std::env::var("MFA_ISSUER").unwrap_or_else(|_| "Kaleido".to_string()),
             ),
+            webauthn_rp_id: std::env::var("WEBAUTHN_RP_ID").ok().map(Arc::from),
+            refresh_reuse_grace_seconds: std::env::var("REFRESH_TOKEN_REUSE_GRACE_SECONDS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
         }
     }
 }
@@ -742,8 +959,26 @@ impl crate::auth::traits::ConfigProvider for EnvConfigProvider {
         &self.frontend_url
     }
 
-    fn jwt_secret(&self) -> &str {
-        &self.jwt_secret
+    fn jwt_keys(&self) -> &JwtKeys {
+        &self.jwt_keys
+    }
+    fn refresh_token_key(&self) -> &str {
+        &self.refresh_token_key
+    }
+    fn mfa_secret_key(&self) -> &str {
+        &self.mfa_secret_key
+    }
+    fn token_policy(&self) -> &TokenPolicy {
+        &self.token_policy
+    }
+    fn mfa_issuer(&self) -> &str {
+        &self.mfa_issuer
+    }
+    fn webauthn_rp_id(&self) -> Option<&str> {
+        self.webauthn_rp_id.as_deref()
+    }
+    fn refresh_reuse_grace_seconds(&self) -> i64 {
+        self.refresh_reuse_grace_seconds
     }
 }
 
@@ -757,7 +992,7 @@ pub type DefaultEnvAuthService = DefaultAuthService<AuthEmailService, EnvConfigP
 /// Create a fully-wired [`DefaultEnvAuthService`] — the single entry point for
 /// apps that use the standard Kaleido auth stack.
 ///
-/// - Reads `FRONTEND_URL` and `JWT_SECRET` from environment.
+/// - Reads `FRONTEND_URL` and the JWT keys from environment.
 /// - Writes audit events and cooldown state to the provided DB.
 /// - Emits `tracing::debug!` for auth metric events.
 pub fn create_auth_service(
@@ -1,6 +1,7 @@
-use crate::auth::entities::users;
+use crate::auth::entities::{refresh_tokens, users};
 use crate::auth::error::AuthError;
 use crate::auth::extractors::{AdminUserContext, AuthStorage};
+use crate::auth::services::sessions::SessionsRevokedResponse;
 use axum::{
     extract::{Path, Query, State},
     routing::{get, patch, post},
@@ -13,6 +14,7 @@ use sea_orm::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use utoipa::ToSchema;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct AdminUserResponse {
@@ -24,6 +26,7 @@ pub struct AdminUserResponse {
     pub email_verified: bool,
     pub created_at: String,
     pub updated_at: String,
+    pub disabled_at: Option<String>,
 }
 
 impl From<users::Model> for AdminUserResponse {
@@ -37,6 +40,7 @@ impl From<users::Model> for AdminUserResponse {
             email_verified: m.email_verified_at.is_some(),
             created_at: m.created_at.to_rfc3339(),
             updated_at: m.updated_at.to_rfc3339(),
+            disabled_at: m.disabled_at.map(|at| at.to_rfc3339()),
         }
     }
 }
@@ -81,6 +85,7 @@ where
         .route("/:id", get(get_user::<S>))
         .route("/:id", patch(update_user::<S>))
         .route("/:id/disable", post(disable_user::<S>))
+        .route("/:id/logout", post(force_logout::<S>))
 }
 
 #[utoipa::path(
@@ -203,6 +208,7 @@ where
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct DisableUserRequest {
+    /// Defaults to `true`; send `false` to re-enable the user
     pub disabled: Option<bool>,
 }
 
@@ -212,7 +218,7 @@ pub struct DisableUserRequest {
     params(("id" = i32, Path, description = "User ID")),
     request_body = DisableUserRequest,
     responses(
-        (status = 200, description = "User disable status toggled", body = AdminUserResponse),
+        (status = 200, description = "User disabled and signed out everywhere, or re-enabled", body = AdminUserResponse),
         (status = 403, description = "Forbidden"),
         (status = 404, description = "User not found"),
     ),
@@ -223,7 +229,7 @@ async fn disable_user<S>(
     _admin: AdminUserContext<S>,
     State(state): State<Arc<S>>,
     Path(id): Path<i32>,
-    Json(_payload): Json<DisableUserRequest>,
+    Json(payload): Json<DisableUserRequest>,
 ) -> Result<Json<AdminUserResponse>, AuthError>
 where
     S: AuthStorage + 'static,
@@ -234,5 +240,63 @@ where
         .await?
         .ok_or_else(|| AuthError::entity_not_found("User not found"))?;
 
+    let disabled = payload.disabled.unwrap_or(true);
+    let user = match (disabled, user.disabled_at) {
+        (true, None) => {
+            let mut active = user.into_active_model();
+            active.disabled_at = Set(Some(chrono::Utc::now()));
+            let user = active.update(db).await?;
+            sign_out_everywhere(&*state, user.pid).await?;
+            user
+        }
+        (false, Some(_)) => {
+            let mut active = user.into_active_model();
+            active.disabled_at = Set(None);
+            active.update(db).await?
+        }
+        _ => user,
+    };
+
     Ok(Json(AdminUserResponse::from(user)))
 }
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/logout",
+    params(("id" = i32, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "All of the user's sessions signed out", body = SessionsRevokedResponse),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "User not found"),
+    ),
+    tag = "admin",
+    security(("bearer_auth" = []))
+)]
+async fn force_logout<S>(
+    _admin: AdminUserContext<S>,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+) -> Result<Json<SessionsRevokedResponse>, AuthError>
+where
+    S: AuthStorage + 'static,
+{
+    let db: &DatabaseConnection = state.db();
+    let user = users::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| AuthError::entity_not_found("User not found"))?;
+
+    let revoked = sign_out_everywhere(&*state, user.pid).await?;
+    Ok(Json(SessionsRevokedResponse { revoked }))
+}
+
+/// End all of a user's sessions, revoking their access tokens if the app
+/// keeps a revocation store
+async fn sign_out_everywhere<S: AuthStorage>(state: &S, user_pid: Uuid) -> Result<u64, AuthError> {
+    let db = state.db();
+    if let Some(store) = state.revocation_store() {
+        let tokens = refresh_tokens::Model::find_for_user(db, user_pid, None).await?;
+        store.revoke_issued_with(db, &tokens).await?;
+    }
+    Ok(refresh_tokens::Model::revoke_all_sessions(db, user_pid).await?)
+}
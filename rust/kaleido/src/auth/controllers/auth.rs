@@ -1,10 +1,17 @@
-use crate::auth::cookies::{clear_refresh_cookie_value, refresh_cookie_value, REFRESH_COOKIE_NAME};
-use crate::auth::entities::refresh_tokens;
+use crate::auth::cookies::{
+    clear_refresh_cookie_value, refresh_cookie_value_with_max_age, REFRESH_COOKIE_NAME,
+};
 use crate::auth::error::AuthError;
 use crate::auth::extractors::{AuthInfo, UserContext};
+use crate::auth::services::magic_link::{MagicLinkRequest, MagicLinkVerifyRequest};
+use crate::auth::services::mfa::{
+    LoginOutcome, MfaCodeRequest, MfaStatusResponse, MfaVerifyRequest, RecoveryCodesResponse,
+    TotpCodeRequest, TotpEnrollmentResponse,
+};
+use crate::auth::services::sessions::SessionClient;
 use crate::auth::services::{
     AuthService, ForgotPasswordRequest, LoginRequest, RegisterRequest, RegisterResponse,
-    ResendConfirmationRequest, ResetPasswordRequest, UserResponse,
+    ResendConfirmationRequest, ResetPasswordRequest, TokenResponse, UserResponse,
 };
 use crate::auth::traits::{
     AuditLogger, ConfigProvider, CooldownManager, EmailService, MetricsRecorder,
@@ -13,10 +20,10 @@ use axum::body::Body;
 use axum::body::Bytes;
 use axum::extract::{Path, State};
 use axum::http::{HeaderMap, StatusCode};
-use axum::response::Response;
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
-use sea_orm::{DatabaseConnection, EntityTrait};
+use sea_orm::DatabaseConnection;
 use std::sync::Arc;
 use utoipa::ToSchema;
 
@@ -52,6 +59,12 @@ pub trait AuthRouteStorage: Send + Sync + 'static {
     fn registration_enabled(&self) -> bool {
         true
     }
+
+    /// Passwordless login links by email; off unless the app's worker sends
+    /// magic-link emails
+    fn magic_link_enabled(&self) -> bool {
+        false
+    }
 }
 
 pub fn routes<S>() -> Router<Arc<S>>
@@ -68,6 +81,15 @@ where
         .route("/auth/verify/:token", get(verify_email::<S>))
         .route("/auth/forgot", post(forgot_password::<S>))
         .route("/auth/reset", post(reset_password::<S>))
+        .route("/auth/magic-link", post(request_magic_link::<S>))
+        .route("/auth/magic-link/verify", post(verify_magic_link::<S>))
+        .route("/auth/mfa", get(mfa_status::<S>))
+        .route("/auth/mfa/verify", post(mfa_verify::<S>))
+        .route("/auth/mfa/totp/enroll", post(mfa_totp_enroll::<S>))
+        .route("/auth/mfa/totp/confirm", post(mfa_totp_confirm::<S>))
+        .route("/auth/mfa/totp/disable", post(mfa_totp_disable::<S>))
+        .route("/auth/mfa/recovery-codes", post(mfa_recovery_codes::<S>))
+        .merge(super::sessions::routes::<S>())
 }
 
 pub fn session_routes<S>() -> Router<Arc<S>>
@@ -78,9 +100,10 @@ where
         .route("/auth/current", get(current::<S>))
         .route("/auth/refresh", post(refresh::<S>))
         .route("/auth/logout", get(logout::<S>))
+        .merge(super::sessions::routes::<S>())
 }
 
-fn extract_cookie(headers: &HeaderMap, name: &str) -> Result<String, AuthError> {
+pub(crate) fn extract_cookie(headers: &HeaderMap, name: &str) -> Result<String, AuthError> {
     let cookies = headers
         .get(axum::http::header::COOKIE)
         .and_then(|v| v.to_str().ok())
@@ -96,6 +119,70 @@ fn extract_cookie(headers: &HeaderMap, name: &str) -> Result<String, AuthError>
     Err(AuthError::unauthorized(format!("Missing cookie: {}", name)))
 }
 
+/// Client address and user agent of a request, for the session list
+///
+/// The address is the last `X-Forwarded-For` hop, the one appended by the
+/// proxy in front of the app, or `X-Real-IP`; earlier hops are client supplied
+/// and not trusted. Apps that are not behind a proxy setting these get no
+/// address.
+pub(crate) fn session_client(headers: &HeaderMap) -> SessionClient {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    let ip = header("x-forwarded-for")
+        .and_then(|v| v.rsplit(',').map(str::trim).find(|hop| !hop.is_empty()))
+        .or_else(|| header("x-real-ip"))
+        .map(str::to_string);
+
+    SessionClient {
+        ip,
+        user_agent: header("user-agent").map(str::to_string),
+    }
+}
+
+/// `token_response` after recording the client on the new session
+pub(crate) async fn session_token_response<S>(
+    state: &S,
+    headers: &HeaderMap,
+    token: &TokenResponse,
+) -> Result<Response, AuthError>
+where
+    S: AuthRouteStorage,
+{
+    // The session is usable without these details; don't fail the login
+    let _ = state
+        .auth_service()
+        .record_session_client(state.db(), &token.refresh_token, session_client(headers))
+        .await;
+    token_response(token, state.frontend_url())
+}
+
+/// Token body plus the refresh cookie, as returned by every login step
+pub(crate) fn token_response(
+    token: &TokenResponse,
+    frontend_url: &str,
+) -> Result<Response, AuthError> {
+    let cookie_val = refresh_cookie_value_with_max_age(
+        &token.refresh_token,
+        frontend_url,
+        token.refresh_token_expires_in,
+    );
+
+    let body_bytes = serde_json::to_vec(token)
+        .map_err(|e| AuthError::internal_error(format!("Failed to serialize response: {}", e)))?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(axum::http::header::SET_COOKIE, cookie_val)
+        .header(axum::http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body_bytes))
+        .map_err(|e| AuthError::internal_error(format!("Failed to build response: {}", e)))
+}
+
 #[utoipa::path(
     post,
     path = "/auth/register",
@@ -126,7 +213,7 @@ where
     path = "/auth/login",
     request_body = LoginRequest,
     responses(
-        (status = 200, description = "Login successful"),
+        (status = 200, description = "Login successful, or an `MfaChallengeResponse` when two-factor authentication is enabled"),
         (status = 401, description = "Invalid credentials"),
         (status = 422, description = "Validation error"),
         (status = 429, description = "Too many login attempts"),
@@ -135,6 +222,7 @@ where
 )]
 pub async fn login<S>(
     State(state): State<Arc<S>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Response, AuthError>
 where
@@ -144,21 +232,186 @@ where
         return Err(AuthError::forbidden("Password login is disabled"));
     }
 
-    let token = state.auth_service().login(state.db(), payload).await?;
+    match state.auth_service().login(state.db(), payload).await? {
+        LoginOutcome::Tokens(token) => session_token_response(&*state, &headers, &token).await,
+        LoginOutcome::MfaRequired(challenge) => Ok(Json(challenge).into_response()),
+    }
+}
 
-    let cookie_val = refresh_cookie_value(&token.refresh_token, state.frontend_url());
+#[utoipa::path(
+    post,
+    path = "/auth/mfa/verify",
+    request_body = MfaVerifyRequest,
+    responses(
+        (status = 200, description = "Second factor accepted; same body and cookie as login", body = TokenResponse),
+        (status = 401, description = "Invalid challenge or code"),
+        (status = 422, description = "Validation error"),
+        (status = 429, description = "Too many code attempts"),
+    ),
+    tag = "auth"
+)]
+pub async fn mfa_verify<S>(
+    State(state): State<Arc<S>>,
+    headers: HeaderMap,
+    Json(payload): Json<MfaVerifyRequest>,
+) -> Result<Response, AuthError>
+where
+    S: AuthRouteStorage,
+{
+    if !state.password_auth_enabled() {
+        return Err(AuthError::forbidden("Password login is disabled"));
+    }
 
-    let body_bytes = serde_json::to_vec(&token)
-        .map_err(|e| AuthError::internal_error(format!("Failed to serialize response: {}", e)))?;
+    let token = state
+        .auth_service()
+        .verify_mfa_login(state.db(), payload)
+        .await?;
+    session_token_response(&*state, &headers, &token).await
+}
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(axum::http::header::SET_COOKIE, cookie_val)
-        .header(axum::http::header::CONTENT_TYPE, "application/json")
-        .body(Body::from(body_bytes))
-        .map_err(|e| AuthError::internal_error(format!("Failed to build response: {}", e)))?;
+#[utoipa::path(
+    get,
+    path = "/auth/mfa",
+    responses(
+        (status = 200, description = "Two-factor status", body = MfaStatusResponse),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn mfa_status<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+) -> Result<Json<MfaStatusResponse>, AuthError>
+where
+    S: AuthRouteStorage + crate::auth::extractors::AuthStorage,
+{
+    let status = state
+        .auth_service()
+        .mfa_status(AuthRouteStorage::db(&*state), &user)
+        .await?;
+    Ok(Json(status))
+}
 
-    Ok(response)
+#[utoipa::path(
+    post,
+    path = "/auth/mfa/totp/enroll",
+    responses(
+        (status = 200, description = "Secret and provisioning URI for an authenticator app", body = TotpEnrollmentResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 409, description = "Two-factor authentication already enabled")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn mfa_totp_enroll<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+) -> Result<Json<TotpEnrollmentResponse>, AuthError>
+where
+    S: AuthRouteStorage + crate::auth::extractors::AuthStorage,
+{
+    let enrollment = state
+        .auth_service()
+        .begin_totp_enrollment(AuthRouteStorage::db(&*state), &user)
+        .await?;
+    Ok(Json(enrollment))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/mfa/totp/confirm",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodesResponse),
+        (status = 401, description = "Invalid code"),
+        (status = 409, description = "Two-factor authentication already enabled"),
+        (status = 429, description = "Too many code attempts")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn mfa_totp_confirm<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AuthError>
+where
+    S: AuthRouteStorage + crate::auth::extractors::AuthStorage,
+{
+    let codes = state
+        .auth_service()
+        .confirm_totp_enrollment(AuthRouteStorage::db(&*state), &user, &payload.code)
+        .await?;
+    Ok(Json(codes))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/mfa/totp/disable",
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = MessageResponse),
+        (status = 401, description = "Invalid code"),
+        (status = 422, description = "Two-factor authentication not enabled"),
+        (status = 429, description = "Too many code attempts")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn mfa_totp_disable<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<MessageResponse>, AuthError>
+where
+    S: AuthRouteStorage + crate::auth::extractors::AuthStorage,
+{
+    state
+        .auth_service()
+        .disable_totp(AuthRouteStorage::db(&*state), &user, payload)
+        .await?;
+    Ok(Json(MessageResponse {
+        message: "Two-factor authentication disabled".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/mfa/recovery-codes",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes; earlier codes no longer work", body = RecoveryCodesResponse),
+        (status = 401, description = "Invalid code"),
+        (status = 422, description = "Two-factor authentication not enabled"),
+        (status = 429, description = "Too many code attempts")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn mfa_recovery_codes<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Json(payload): Json<TotpCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AuthError>
+where
+    S: AuthRouteStorage + crate::auth::extractors::AuthStorage,
+{
+    let codes = state
+        .auth_service()
+        .regenerate_recovery_codes(AuthRouteStorage::db(&*state), &user, &payload.code)
+        .await?;
+    Ok(Json(codes))
 }
 
 #[utoipa::path(
@@ -250,6 +503,71 @@ where
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/magic-link",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 200, description = "Login link sent if user exists", body = MessageResponse),
+        (status = 403, description = "Magic-link login is disabled"),
+        (status = 422, description = "Validation error"),
+        (status = 429, description = "Too many login link requests")
+    ),
+    tag = "auth"
+)]
+pub async fn request_magic_link<S>(
+    State(state): State<Arc<S>>,
+    Json(payload): Json<MagicLinkRequest>,
+) -> Result<Json<MessageResponse>, AuthError>
+where
+    S: AuthRouteStorage,
+{
+    if !state.magic_link_enabled() {
+        return Err(AuthError::forbidden("Magic-link login is disabled"));
+    }
+
+    state
+        .auth_service()
+        .request_magic_link(state.db(), payload)
+        .await?;
+    Ok(Json(MessageResponse {
+        message: "If the email exists, a login link has been sent".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/magic-link/verify",
+    request_body = MagicLinkVerifyRequest,
+    responses(
+        (status = 200, description = "Login successful, or an `MfaChallengeResponse` when two-factor authentication is enabled"),
+        (status = 401, description = "Invalid or expired login link"),
+        (status = 403, description = "Magic-link login is disabled")
+    ),
+    tag = "auth"
+)]
+pub async fn verify_magic_link<S>(
+    State(state): State<Arc<S>>,
+    headers: HeaderMap,
+    Json(payload): Json<MagicLinkVerifyRequest>,
+) -> Result<Response, AuthError>
+where
+    S: AuthRouteStorage,
+{
+    if !state.magic_link_enabled() {
+        return Err(AuthError::forbidden("Magic-link login is disabled"));
+    }
+
+    match state
+        .auth_service()
+        .verify_magic_link(state.db(), payload)
+        .await?
+    {
+        LoginOutcome::Tokens(token) => session_token_response(&*state, &headers, &token).await,
+        LoginOutcome::MfaRequired(challenge) => Ok(Json(challenge).into_response()),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/auth/reset",
@@ -335,10 +653,18 @@ where
         .auth_service()
         .refresh(state.db(), refresh_token)
         .await?;
+    let _ = state
+        .auth_service()
+        .record_session_client(state.db(), &token.refresh_token, session_client(&headers))
+        .await;
 
     let mut builder = Response::builder().status(StatusCode::OK);
     if used_cookie {
-        let cookie_val = refresh_cookie_value(&token.refresh_token, state.frontend_url());
+        let cookie_val = refresh_cookie_value_with_max_age(
+            &token.refresh_token,
+            state.frontend_url(),
+            token.refresh_token_expires_in,
+        );
         builder = builder.header(axum::http::header::SET_COOKIE, cookie_val);
 
         let user_obj = serde_json::json!({
@@ -391,14 +717,16 @@ pub async fn logout<S>(
 where
     S: AuthRouteStorage + crate::auth::extractors::AuthStorage,
 {
-    if let Some(refresh_token) = auth.refresh_token {
-        let _ = refresh_tokens::Entity::delete_by_id(refresh_token)
-            .exec(crate::auth::extractors::AuthStorage::db(&*state))
-            .await;
-    } else if let Some(identity) = auth.identity {
-        if let crate::auth::extractors::AuthIdentity::User(user_identity) = identity {
-            let _ = state.auth_service().logout(user_identity.user_pid).await;
-        }
+    if let Some(crate::auth::extractors::AuthIdentity::User(user_identity)) = auth.identity {
+        state
+            .auth_service()
+            .logout(
+                AuthRouteStorage::db(&*state),
+                user_identity.user_pid,
+                auth.refresh_token.as_deref(),
+                auth.access_token.as_ref(),
+            )
+            .await?;
     }
 
     let cookie_val = clear_refresh_cookie_value(state.frontend_url());
@@ -416,3 +744,22 @@ where
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_client_uses_proxy_appended_hop() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "6.6.6.6, 10.0.0.1, 203.0.113.7 ".parse().unwrap(),
+        );
+        headers.insert("x-real-ip", "198.51.100.1".parse().unwrap());
+        assert_eq!(session_client(&headers).ip.as_deref(), Some("203.0.113.7"));
+
+        headers.remove("x-forwarded-for");
+        assert_eq!(session_client(&headers).ip.as_deref(), Some("198.51.100.1"));
+    }
+}
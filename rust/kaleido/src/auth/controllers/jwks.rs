@@ -0,0 +1,43 @@
+use crate::auth::extractors::AuthStorage;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use std::sync::Arc;
+
+/// How long clients may cache the key set; keep it shorter than the overlap
+/// between publishing a new key and signing with it
+const JWKS_MAX_AGE_SECONDS: u32 = 300;
+
+/// `/.well-known/jwks.json`, the public keys access tokens can be verified with
+///
+/// Mount at the site root rather than under an API prefix so verifiers can
+/// find it at the conventional path.
+pub fn routes<S>() -> Router<Arc<S>>
+where
+    S: AuthStorage + 'static,
+{
+    Router::new().route("/.well-known/jwks.json", get(jwks::<S>))
+}
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "JSON Web Key Set of the access token verification keys; empty when signing with HS256", content_type = "application/json")
+    ),
+    tag = "auth"
+)]
+pub async fn jwks<S>(State(state): State<Arc<S>>) -> impl IntoResponse
+where
+    S: AuthStorage + 'static,
+{
+    (
+        [(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", JWKS_MAX_AGE_SECONDS),
+        )],
+        Json(state.jwt_keys().jwks()),
+    )
+}
@@ -2,6 +2,9 @@ pub mod admin;
 pub mod auth;
 pub use auth::routes;
 
+pub mod jwks;
 pub mod oauth;
+pub mod passkeys;
+pub mod sessions;
 
 pub use admin::routes as admin_routes;
@@ -1,18 +1,28 @@
-use crate::auth::cookies::refresh_cookie_value;
+use crate::auth::controllers::auth::{extract_cookie, session_client};
+use crate::auth::cookies::{
+    clear_oauth_state_cookie_value, oauth_state_cookie_value, refresh_cookie_value_with_max_age,
+    OAUTH_STATE_COOKIE_NAME,
+};
+use crate::auth::entities::users;
 use crate::auth::error::AuthError;
-use crate::auth::services::oauth::OAuthUserInfo;
+use crate::auth::extractors::{AuthStorage, UserContext};
+use crate::auth::services::identities::IdentityResponse;
+use crate::auth::services::mfa::LoginOutcome;
+use crate::auth::services::oauth::{sanitize_redirect_path, OAuthUserInfo};
 use crate::auth::services::oauth_provider_service::OAuthProviderService;
 use crate::auth::services::provider_settings::{normalize_provider_id, PROVIDER_DEV};
 use crate::auth::AuthRouteStorage;
+use crate::auth::MessageResponse;
 use crate::auth::OAuthService;
 use axum::{
     body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Redirect, Response},
-    routing::get,
+    routing::{delete, get, post},
     Json, Router,
 };
+use sea_orm::EntityTrait;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use utoipa::{IntoParams, ToSchema};
@@ -31,11 +41,14 @@ pub trait OAuthRouteStorage: AuthRouteStorage {
 
 pub fn routes<S>() -> Router<Arc<S>>
 where
-    S: OAuthRouteStorage,
+    S: OAuthRouteStorage + AuthStorage,
 {
     Router::new()
         .route("/providers", get(oauth_providers::<S>))
+        .route("/identities", get(list_identities::<S>))
+        .route("/identities/:id", delete(unlink_identity::<S>))
         .route("/:provider", get(oauth_authorize::<S>))
+        .route("/:provider/link", post(oauth_link::<S>))
         .route("/:provider/callback", get(oauth_callback::<S>))
 }
 
@@ -68,21 +81,31 @@ where
     Ok(Json(OAuthProvidersResponse { providers }))
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct OAuthAuthorizeQuery {
+    /// Frontend path to return to after login; must start with a single `/`
+    redirect: Option<String>,
+}
+
 /// Initiate provider OAuth flow - redirect user to provider login
 #[utoipa::path(
     get,
     path = "/oauth/{provider}",
     params(
-        ("provider" = String, Path, description = "OAuth provider name")
+        ("provider" = String, Path, description = "OAuth provider name"),
+        OAuthAuthorizeQuery
     ),
     responses(
-        (status = 302, description = "Redirect to provider")
+        (status = 302, description = "Redirect to provider"),
+        (status = 422, description = "Invalid redirect path")
     ),
     tag = "oauth"
 )]
 pub async fn oauth_authorize<S>(
     State(state): State<Arc<S>>,
     Path(provider): Path<String>,
+    Query(params): Query<OAuthAuthorizeQuery>,
+    headers: HeaderMap,
 ) -> Result<Response, AuthError>
 where
     S: OAuthRouteStorage,
@@ -93,21 +116,48 @@ where
         return Err(AuthError::forbidden("OAuth is disabled"));
     }
 
+    let redirect_path = match params.redirect.as_deref() {
+        Some(path) => Some(
+            sanitize_redirect_path(path)
+                .ok_or_else(|| AuthError::validation("Invalid redirect path"))?,
+        ),
+        None => None,
+    };
+
     if provider == PROVIDER_DEV {
-        return complete_oauth_login(state, &provider, OAuthService::local_dev_user_info()).await;
+        return complete_oauth_login(
+            state,
+            &headers,
+            &provider,
+            OAuthService::local_dev_user_info(),
+            redirect_path.as_deref(),
+        )
+        .await;
     }
 
-    let auth_url = OAuthService::get_authorization_url(state.db(), &provider, state.api_url())
-        .await
-        .map_err(|e| AuthError::internal_error(e.to_string()))?;
+    let auth_url = OAuthService::get_authorization_url(
+        state.db(),
+        &provider,
+        state.api_url(),
+        redirect_path,
+        None,
+    )
+    .await?;
     tracing::debug!(provider = %provider, auth_url = %auth_url.url, "Initiating OAuth redirect");
-    Ok(Redirect::temporary(&auth_url.url).into_response())
+
+    // Binds the state to this browser, so a callback URL planted by someone
+    // else cannot sign the victim into the attacker's account
+    let cookie_val = oauth_state_cookie_value(&auth_url.state, state.frontend_url());
+    Ok((
+        [(header::SET_COOKIE, cookie_val)],
+        Redirect::temporary(&auth_url.url),
+    )
+        .into_response())
 }
 
 #[derive(Deserialize, IntoParams)]
 pub struct OAuthCallbackQuery {
     code: String,
-    #[allow(dead_code)]
     state: Option<String>,
 }
 
@@ -120,7 +170,8 @@ pub struct OAuthCallbackQuery {
         OAuthCallbackQuery
     ),
     responses(
-        (status = 302, description = "Redirect to frontend")
+        (status = 302, description = "Redirect to frontend, with an MFA challenge in the fragment when TOTP is on"),
+        (status = 401, description = "Missing, mismatched or expired state, or invalid ID token")
     ),
     tag = "oauth"
 )]
@@ -128,6 +179,7 @@ pub async fn oauth_callback<S>(
     State(state): State<Arc<S>>,
     Path(provider): Path<String>,
     Query(params): Query<OAuthCallbackQuery>,
+    headers: HeaderMap,
 ) -> Result<Response, AuthError>
 where
     S: OAuthRouteStorage,
@@ -137,44 +189,98 @@ where
     if !state.oauth_provider_enabled(&provider) {
         return Err(AuthError::forbidden("OAuth is disabled"));
     }
+
+    let returned_state = params
+        .state
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| AuthError::unauthorized("Missing OAuth state"))?;
+    let cookie_state = extract_cookie(&headers, OAUTH_STATE_COOKIE_NAME)
+        .map_err(|_| AuthError::unauthorized("Missing OAuth state cookie"))?;
+    if cookie_state != returned_state {
+        return Err(AuthError::unauthorized("OAuth state mismatch"));
+    }
+    let pending =
+        OAuthService::take_authorization_state(state.db(), &provider, &returned_state).await?;
+
     let provider_user = OAuthService::exchange_code_and_get_user(
         state.db(),
         &provider,
         params.code,
+        &pending,
         state.api_url(),
     )
-    .await
-    .map_err(|e| AuthError::internal_error(e.to_string()))?;
+    .await?;
 
-    complete_oauth_login(state, &provider, provider_user).await
+    if let Some(user_id) = pending.link_user_id {
+        return complete_oauth_link(
+            state,
+            user_id,
+            &provider,
+            provider_user,
+            pending.redirect_path.as_deref(),
+        )
+        .await;
+    }
+
+    complete_oauth_login(
+        state,
+        &headers,
+        &provider,
+        provider_user,
+        pending.redirect_path.as_deref(),
+    )
+    .await
 }
 
 async fn complete_oauth_login<S>(
     state: Arc<S>,
+    headers: &HeaderMap,
     provider: &str,
     provider_user: OAuthUserInfo,
+    redirect_path: Option<&str>,
 ) -> Result<Response, AuthError>
 where
     S: OAuthRouteStorage,
 {
-    let user = OAuthService::find_or_create_provider_user(state.db(), provider, provider_user)
-        .await
-        .map_err(|e| AuthError::internal_error(e.to_string()))?;
+    let user =
+        OAuthService::find_or_create_provider_user(state.db(), provider, provider_user).await?;
 
-    let tokens = state
-        .auth_service()
-        .issue_tokens(state.db(), &user)
-        .await
-        .map_err(AuthError::from)?;
     let frontend_url = state.frontend_url().to_string();
-    let cookie_val = refresh_cookie_value(&tokens.refresh_token, &frontend_url);
+    let mut redirect_url = frontend_callback_url(&frontend_url, None, redirect_path);
+    let mut response = Response::builder().status(StatusCode::FOUND);
 
-    let redirect_url = format!("{}/auth/callback", frontend_url.trim_end_matches('/'));
+    match state
+        .auth_service()
+        .complete_primary_login(state.db(), &user)
+        .await?
+    {
+        LoginOutcome::Tokens(tokens) => {
+            let _ = state
+                .auth_service()
+                .record_session_client(state.db(), &tokens.refresh_token, session_client(headers))
+                .await;
+            response = response.header(
+                header::SET_COOKIE,
+                refresh_cookie_value_with_max_age(
+                    &tokens.refresh_token,
+                    &frontend_url,
+                    tokens.refresh_token_expires_in,
+                ),
+            );
+        }
+        // The challenge rides in the fragment, which browsers never send to
+        // servers; the frontend posts it to `/auth/mfa/verify`
+        LoginOutcome::MfaRequired(challenge) => {
+            redirect_url = mfa_callback_url(&redirect_url, &challenge.mfa_token);
+        }
+    }
 
-    let response = Response::builder()
-        .status(StatusCode::FOUND)
-        .header(axum::http::header::LOCATION, redirect_url)
-        .header(axum::http::header::SET_COOKIE, cookie_val)
+    let response = response
+        .header(header::LOCATION, redirect_url)
+        .header(
+            header::SET_COOKIE,
+            clear_oauth_state_cookie_value(&frontend_url),
+        )
         .body(Body::empty())
         .map_err(|e| {
             AuthError::internal_error(format!("Failed to build redirect response: {}", e))
@@ -182,3 +288,217 @@ where
 
     Ok(response)
 }
+
+/// Link the provider account to the user who started the link flow
+async fn complete_oauth_link<S>(
+    state: Arc<S>,
+    user_id: i32,
+    provider: &str,
+    provider_user: OAuthUserInfo,
+    redirect_path: Option<&str>,
+) -> Result<Response, AuthError>
+where
+    S: OAuthRouteStorage,
+{
+    let user = users::Entity::find_by_id(user_id)
+        .one(state.db())
+        .await?
+        .ok_or_else(|| AuthError::unauthorized("User not found"))?;
+    if user.disabled_at.is_some() {
+        return Err(AuthError::forbidden("Account disabled"));
+    }
+    state
+        .auth_service()
+        .link_identity(state.db(), &user, provider, &provider_user)
+        .await?;
+
+    let frontend_url = state.frontend_url();
+    let redirect_url = frontend_callback_url(frontend_url, Some(provider), redirect_path);
+    Response::builder()
+        .status(StatusCode::FOUND)
+        .header(header::LOCATION, redirect_url)
+        .header(
+            header::SET_COOKIE,
+            clear_oauth_state_cookie_value(frontend_url),
+        )
+        .body(Body::empty())
+        .map_err(|e| AuthError::internal_error(format!("Failed to build redirect response: {}", e)))
+}
+
+/// `{frontend}/auth/callback`, with `linked` naming a newly linked provider
+/// and `redirect` the path the flow was started with
+fn frontend_callback_url(
+    frontend_url: &str,
+    linked: Option<&str>,
+    redirect_path: Option<&str>,
+) -> String {
+    let mut url = format!("{}/auth/callback", frontend_url.trim_end_matches('/'));
+    let mut query = oauth2::url::form_urlencoded::Serializer::new(String::new());
+    if let Some(provider) = linked {
+        query.append_pair("linked", provider);
+    }
+    if let Some(path) = redirect_path {
+        query.append_pair("redirect", path);
+    }
+    let query = query.finish();
+    if !query.is_empty() {
+        url.push('?');
+        url.push_str(&query);
+    }
+    url
+}
+
+/// `callback_url` carrying an MFA challenge for the frontend to complete
+fn mfa_callback_url(callback_url: &str, mfa_token: &str) -> String {
+    let fragment = oauth2::url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("mfa_token", mfa_token)
+        .finish();
+    format!("{}#{}", callback_url, fragment)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct OAuthLinkResponse {
+    /// Provider authorization URL to send the browser to
+    pub url: String,
+}
+
+/// Start linking a provider account to the signed-in user
+///
+/// Returns the provider URL rather than redirecting, since the request
+/// needs the bearer token. Call it with credentials so the state cookie is
+/// stored, then navigate to `url`; the callback links the account and
+/// redirects to `{frontend}/auth/callback?linked={provider}`.
+#[utoipa::path(
+    post,
+    path = "/oauth/{provider}/link",
+    params(
+        ("provider" = String, Path, description = "OAuth provider name"),
+        OAuthAuthorizeQuery
+    ),
+    responses(
+        (status = 200, description = "Provider authorization URL", body = OAuthLinkResponse),
+        (status = 422, description = "Invalid redirect path, or a provider that cannot be linked"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "OAuth is disabled")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "oauth"
+)]
+pub async fn oauth_link<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Path(provider): Path<String>,
+    Query(params): Query<OAuthAuthorizeQuery>,
+) -> Result<Response, AuthError>
+where
+    S: OAuthRouteStorage + AuthStorage,
+{
+    let provider = normalize_provider_id(&provider)?;
+
+    if !state.oauth_provider_enabled(&provider) {
+        return Err(AuthError::forbidden("OAuth is disabled"));
+    }
+    if provider == PROVIDER_DEV {
+        return Err(AuthError::validation("The dev provider cannot be linked"));
+    }
+
+    let redirect_path = match params.redirect.as_deref() {
+        Some(path) => Some(
+            sanitize_redirect_path(path)
+                .ok_or_else(|| AuthError::validation("Invalid redirect path"))?,
+        ),
+        None => None,
+    };
+
+    let auth_url = OAuthService::get_authorization_url(
+        AuthRouteStorage::db(&*state),
+        &provider,
+        state.api_url(),
+        redirect_path,
+        Some(user.id),
+    )
+    .await?;
+
+    let cookie_val = oauth_state_cookie_value(&auth_url.state, state.frontend_url());
+    Ok((
+        [(header::SET_COOKIE, cookie_val)],
+        Json(OAuthLinkResponse { url: auth_url.url }),
+    )
+        .into_response())
+}
+
+/// List the provider accounts linked to the signed-in user
+#[utoipa::path(
+    get,
+    path = "/oauth/identities",
+    responses(
+        (status = 200, description = "Linked identities", body = Vec<IdentityResponse>),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "oauth"
+)]
+pub async fn list_identities<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+) -> Result<Json<Vec<IdentityResponse>>, AuthError>
+where
+    S: OAuthRouteStorage + AuthStorage,
+{
+    let identities = state
+        .auth_service()
+        .list_identities(AuthRouteStorage::db(&*state), &user)
+        .await?;
+    Ok(Json(identities))
+}
+
+/// Unlink a provider account from the signed-in user
+#[utoipa::path(
+    delete,
+    path = "/oauth/identities/{id}",
+    params(("id" = i32, Path, description = "Identity ID")),
+    responses(
+        (status = 200, description = "Identity unlinked", body = MessageResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "Identity not found"),
+        (status = 422, description = "The identity is the user's only way to sign in")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "oauth"
+)]
+pub async fn unlink_identity<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Path(id): Path<i32>,
+) -> Result<Json<MessageResponse>, AuthError>
+where
+    S: OAuthRouteStorage + AuthStorage,
+{
+    state
+        .auth_service()
+        .unlink_identity(AuthRouteStorage::db(&*state), &user, id)
+        .await?;
+    Ok(Json(MessageResponse {
+        message: "Identity unlinked".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mfa_challenge_rides_in_the_fragment() {
+        let callback = frontend_callback_url("http://app.test/", None, Some("/settings"));
+        assert_eq!(
+            mfa_callback_url(&callback, "a.b+c"),
+            "http://app.test/auth/callback?redirect=%2Fsettings#mfa_token=a.b%2Bc"
+        );
+    }
+}
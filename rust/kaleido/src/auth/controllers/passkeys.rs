@@ -0,0 +1,237 @@
+use crate::auth::controllers::auth::{session_token_response, AuthRouteStorage, MessageResponse};
+use crate::auth::error::AuthError;
+use crate::auth::extractors::{AuthStorage, UserContext};
+use crate::auth::services::passkeys::{
+    PasskeyLoginOptionsRequest, PasskeyLoginRequest, PasskeyOptionsResponse,
+    PasskeyRegistrationRequest, PasskeyResponse, RenamePasskeyRequest,
+};
+use crate::auth::services::TokenResponse;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::response::Response;
+use axum::routing::{get, patch, post};
+use axum::{Json, Router};
+use std::sync::Arc;
+
+/// Passkey registration, login and management routes
+///
+/// Mounted by `routes_for_features` when `FeatureSet::passkeys` is on.
+pub fn routes<S>() -> Router<Arc<S>>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    Router::new()
+        .route("/auth/passkeys", get(list_passkeys::<S>))
+        .route(
+            "/auth/passkeys/:id",
+            patch(rename_passkey::<S>).delete(delete_passkey::<S>),
+        )
+        .route(
+            "/auth/passkeys/register/options",
+            post(registration_options::<S>),
+        )
+        .route(
+            "/auth/passkeys/register/verify",
+            post(registration_verify::<S>),
+        )
+        .route("/auth/passkeys/login/options", post(login_options::<S>))
+        .route("/auth/passkeys/login/verify", post(login_verify::<S>))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/register/options",
+    responses(
+        (status = 200, description = "Options for navigator.credentials.create", body = PasskeyOptionsResponse),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn registration_options<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+) -> Result<Json<PasskeyOptionsResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let options = state
+        .auth_service()
+        .begin_passkey_registration(AuthRouteStorage::db(&*state), &user)
+        .await?;
+    Ok(Json(options))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/register/verify",
+    request_body = PasskeyRegistrationRequest,
+    responses(
+        (status = 200, description = "Passkey registered", body = PasskeyResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 409, description = "Passkey already registered"),
+        (status = 422, description = "Invalid or expired challenge, or the credential failed verification")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn registration_verify<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Json(payload): Json<PasskeyRegistrationRequest>,
+) -> Result<Json<PasskeyResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let passkey = state
+        .auth_service()
+        .finish_passkey_registration(AuthRouteStorage::db(&*state), &user, payload)
+        .await?;
+    Ok(Json(passkey))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/login/options",
+    request_body = PasskeyLoginOptionsRequest,
+    responses(
+        (status = 200, description = "Options for navigator.credentials.get", body = PasskeyOptionsResponse)
+    ),
+    tag = "auth"
+)]
+pub async fn login_options<S>(
+    State(state): State<Arc<S>>,
+    Json(payload): Json<PasskeyLoginOptionsRequest>,
+) -> Result<Json<PasskeyOptionsResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let options = state
+        .auth_service()
+        .begin_passkey_login(AuthRouteStorage::db(&*state), payload)
+        .await?;
+    Ok(Json(options))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/login/verify",
+    request_body = PasskeyLoginRequest,
+    responses(
+        (status = 200, description = "Login successful; same body and cookie as password login", body = TokenResponse),
+        (status = 401, description = "Passkey login failed"),
+        (status = 429, description = "Too many login attempts")
+    ),
+    tag = "auth"
+)]
+pub async fn login_verify<S>(
+    State(state): State<Arc<S>>,
+    headers: HeaderMap,
+    Json(payload): Json<PasskeyLoginRequest>,
+) -> Result<Response, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let token = state
+        .auth_service()
+        .finish_passkey_login(AuthRouteStorage::db(&*state), payload)
+        .await?;
+    session_token_response(&*state, &headers, &token).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/passkeys",
+    responses(
+        (status = 200, description = "The user's passkeys", body = [PasskeyResponse]),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn list_passkeys<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+) -> Result<Json<Vec<PasskeyResponse>>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let passkeys = state
+        .auth_service()
+        .list_passkeys(AuthRouteStorage::db(&*state), &user)
+        .await?;
+    Ok(Json(passkeys))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/auth/passkeys/{id}",
+    params(
+        ("id" = i32, Path, description = "Passkey id")
+    ),
+    request_body = RenamePasskeyRequest,
+    responses(
+        (status = 200, description = "Passkey renamed", body = PasskeyResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "Passkey not found"),
+        (status = 422, description = "Validation error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn rename_passkey<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Path(id): Path<i32>,
+    Json(payload): Json<RenamePasskeyRequest>,
+) -> Result<Json<PasskeyResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let passkey = state
+        .auth_service()
+        .rename_passkey(AuthRouteStorage::db(&*state), &user, id, payload)
+        .await?;
+    Ok(Json(passkey))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/passkeys/{id}",
+    params(
+        ("id" = i32, Path, description = "Passkey id")
+    ),
+    responses(
+        (status = 200, description = "Passkey deleted", body = MessageResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "Passkey not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn delete_passkey<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Path(id): Path<i32>,
+) -> Result<Json<MessageResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    state
+        .auth_service()
+        .delete_passkey(AuthRouteStorage::db(&*state), &user, id)
+        .await?;
+    Ok(Json(MessageResponse {
+        message: "Passkey deleted".to_string(),
+    }))
+}
@@ -0,0 +1,154 @@
+use crate::auth::controllers::auth::{extract_cookie, AuthRouteStorage, MessageResponse};
+use crate::auth::cookies::REFRESH_COOKIE_NAME;
+use crate::auth::error::AuthError;
+use crate::auth::extractors::{AuthStorage, UserContext};
+use crate::auth::services::sessions::{
+    RenameSessionRequest, SessionResponse, SessionsRevokedResponse,
+};
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::routing::{get, patch};
+use axum::{Json, Router};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Session listing and revocation routes
+///
+/// Included in both `routes()` and `session_routes()`.
+pub fn routes<S>() -> Router<Arc<S>>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    Router::new()
+        .route(
+            "/auth/sessions",
+            get(list_sessions::<S>).delete(revoke_all_sessions::<S>),
+        )
+        .route(
+            "/auth/sessions/:id",
+            patch(rename_session::<S>).delete(revoke_session::<S>),
+        )
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/sessions",
+    responses(
+        (status = 200, description = "The user's signed-in sessions", body = [SessionResponse]),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn list_sessions<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SessionResponse>>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let current = extract_cookie(&headers, REFRESH_COOKIE_NAME).ok();
+    let sessions = state
+        .auth_service()
+        .list_sessions(AuthRouteStorage::db(&*state), &user, current.as_deref())
+        .await?;
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions",
+    responses(
+        (status = 200, description = "Every session signed out, including this one", body = SessionsRevokedResponse),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn revoke_all_sessions<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+) -> Result<Json<SessionsRevokedResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    let revoked = state
+        .auth_service()
+        .revoke_all_sessions(AuthRouteStorage::db(&*state), &user)
+        .await?;
+    Ok(Json(revoked))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/auth/sessions/{id}",
+    params(
+        ("id" = String, Path, description = "Session id")
+    ),
+    request_body = RenameSessionRequest,
+    responses(
+        (status = 200, description = "Session renamed", body = MessageResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "Session not found"),
+        (status = 422, description = "Validation error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn rename_session<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<RenameSessionRequest>,
+) -> Result<Json<MessageResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    state
+        .auth_service()
+        .rename_session(AuthRouteStorage::db(&*state), &user, id, payload)
+        .await?;
+    Ok(Json(MessageResponse {
+        message: "Session renamed".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions/{id}",
+    params(
+        ("id" = String, Path, description = "Session id")
+    ),
+    responses(
+        (status = 200, description = "Session signed out", body = MessageResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "Session not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn revoke_session<S>(
+    State(state): State<Arc<S>>,
+    UserContext { user, .. }: UserContext<S>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<MessageResponse>, AuthError>
+where
+    S: AuthRouteStorage + AuthStorage,
+{
+    state
+        .auth_service()
+        .revoke_session(AuthRouteStorage::db(&*state), &user, id)
+        .await?;
+    Ok(Json(MessageResponse {
+        message: "Session revoked".to_string(),
+    }))
+}
@@ -1,21 +1,45 @@
-//! Helper functions for issuing and clearing refresh-token cookies.
+//! Helper functions for issuing and clearing refresh-token and OAuth state cookies.
+
+use crate::auth::services::oauth::OAUTH_STATE_TTL_MINUTES;
+use crate::auth::tokens::TokenPolicy;
 
 /// Name of the HttpOnly cookie used for refresh tokens across the starter kit.
 pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
 
+/// Name of the HttpOnly cookie binding an OAuth `state` to the browser that started the login.
+pub const OAUTH_STATE_COOKIE_NAME: &str = "oauth_state";
+
 /// Build a `Set-Cookie` value for the HttpOnly refresh cookie.
 ///
+/// Uses the default refresh token lifetime; see
+/// [`refresh_cookie_value_with_max_age`].
+///
 /// # Arguments
 /// * `token` - The refresh token value
 /// * `frontend_url` - The frontend URL to determine if connection is secure
-/// Number of seconds in 7 days — matches the refresh token TTL in the database.
-const REFRESH_COOKIE_MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
-
 pub fn refresh_cookie_value(token: &str, frontend_url: &str) -> String {
+    refresh_cookie_value_with_max_age(
+        token,
+        frontend_url,
+        TokenPolicy::default_ref().refresh_token_ttl_seconds,
+    )
+}
+
+/// Build a `Set-Cookie` value for the HttpOnly refresh cookie.
+///
+/// # Arguments
+/// * `token` - The refresh token value
+/// * `frontend_url` - The frontend URL to determine if connection is secure
+/// * `max_age_secs` - Lifetime of the refresh token, as `TokenResponse::refresh_token_expires_in`
+pub fn refresh_cookie_value_with_max_age(
+    token: &str,
+    frontend_url: &str,
+    max_age_secs: i64,
+) -> String {
     let secure = frontend_url.starts_with("https://");
     let mut cookie_val = format!(
         "{}={}; HttpOnly; Path=/; SameSite=Strict; Max-Age={};",
-        REFRESH_COOKIE_NAME, token, REFRESH_COOKIE_MAX_AGE_SECS
+        REFRESH_COOKIE_NAME, token, max_age_secs
     );
     if secure {
         cookie_val.push_str(" Secure;");
@@ -38,3 +62,41 @@ pub fn clear_refresh_cookie_value(frontend_url: &str) -> String {
     }
     cookie_val
 }
+
+/// Build a `Set-Cookie` value for the OAuth state cookie.
+///
+/// `SameSite=Lax` rather than `Strict`: the provider sends the browser back
+/// with a cross-site top-level redirect, which a strict cookie would miss.
+///
+/// # Arguments
+/// * `state` - The `state` parameter sent to the provider
+/// * `frontend_url` - The frontend URL to determine if connection is secure
+pub fn oauth_state_cookie_value(state: &str, frontend_url: &str) -> String {
+    let secure = frontend_url.starts_with("https://");
+    let mut cookie_val = format!(
+        "{}={}; HttpOnly; Path=/; SameSite=Lax; Max-Age={};",
+        OAUTH_STATE_COOKIE_NAME,
+        state,
+        OAUTH_STATE_TTL_MINUTES * 60
+    );
+    if secure {
+        cookie_val.push_str(" Secure;");
+    }
+    cookie_val
+}
+
+/// Build a `Set-Cookie` value that clears the OAuth state cookie immediately.
+///
+/// # Arguments
+/// * `frontend_url` - The frontend URL to determine if connection is secure
+pub fn clear_oauth_state_cookie_value(frontend_url: &str) -> String {
+    let secure = frontend_url.starts_with("https://");
+    let mut cookie_val = format!(
+        "{}=; HttpOnly; Path=/; SameSite=Lax; Max-Age=0;",
+        OAUTH_STATE_COOKIE_NAME
+    );
+    if secure {
+        cookie_val.push_str(" Secure;");
+    }
+    cookie_val
+}
@@ -89,6 +89,20 @@ pub enum EventType {
     Logout,
     TokenRefresh,
     TokenRefreshFailed,
+    TokenReuseDetected,
+    MfaChallengeIssued,
+    MfaVerified,
+    MfaFailed,
+    MfaEnabled,
+    MfaDisabled,
+    RecoveryCodeUsed,
+    RecoveryCodesRegenerated,
+    PasskeyRegistered,
+    PasskeyRemoved,
+    MagicLinkRequest,
+    SessionRevoked,
+    IdentityLinked,
+    IdentityUnlinked,
     Other(String),
 }
 
@@ -102,6 +116,20 @@ impl From<&str> for EventType {
             "logout" => EventType::Logout,
             "token_refresh" => EventType::TokenRefresh,
             "token_refresh_failed" => EventType::TokenRefreshFailed,
+            "token_reuse_detected" => EventType::TokenReuseDetected,
+            "mfa_challenge_issued" => EventType::MfaChallengeIssued,
+            "mfa_verified" => EventType::MfaVerified,
+            "mfa_failed" => EventType::MfaFailed,
+            "mfa_enabled" => EventType::MfaEnabled,
+            "mfa_disabled" => EventType::MfaDisabled,
+            "recovery_code_used" => EventType::RecoveryCodeUsed,
+            "recovery_codes_regenerated" => EventType::RecoveryCodesRegenerated,
+            "passkey_registered" => EventType::PasskeyRegistered,
+            "passkey_removed" => EventType::PasskeyRemoved,
+            "magic_link_request" => EventType::MagicLinkRequest,
+            "session_revoked" => EventType::SessionRevoked,
+            "identity_linked" => EventType::IdentityLinked,
+            "identity_unlinked" => EventType::IdentityUnlinked,
             other => EventType::Other(other.to_string()),
         }
     }
@@ -123,6 +151,20 @@ impl ToString for EventType {
             EventType::Logout => "logout".to_string(),
             EventType::TokenRefresh => "token_refresh".to_string(),
             EventType::TokenRefreshFailed => "token_refresh_failed".to_string(),
+            EventType::TokenReuseDetected => "token_reuse_detected".to_string(),
+            EventType::MfaChallengeIssued => "mfa_challenge_issued".to_string(),
+            EventType::MfaVerified => "mfa_verified".to_string(),
+            EventType::MfaFailed => "mfa_failed".to_string(),
+            EventType::MfaEnabled => "mfa_enabled".to_string(),
+            EventType::MfaDisabled => "mfa_disabled".to_string(),
+            EventType::RecoveryCodeUsed => "recovery_code_used".to_string(),
+            EventType::RecoveryCodesRegenerated => "recovery_codes_regenerated".to_string(),
+            EventType::PasskeyRegistered => "passkey_registered".to_string(),
+            EventType::PasskeyRemoved => "passkey_removed".to_string(),
+            EventType::MagicLinkRequest => "magic_link_request".to_string(),
+            EventType::SessionRevoked => "session_revoked".to_string(),
+            EventType::IdentityLinked => "identity_linked".to_string(),
+            EventType::IdentityUnlinked => "identity_unlinked".to_string(),
             EventType::Other(s) => s.clone(),
         }
     }
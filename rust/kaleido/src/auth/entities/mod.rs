@@ -1,7 +1,14 @@
 pub mod api_clients;
 pub mod auth_events;
 pub mod cooldowns;
+pub mod oauth_states;
+pub mod passkey_challenges;
+pub mod passkey_credentials;
 pub mod refresh_tokens;
+pub mod revoked_access_tokens;
+pub mod user_identities;
+pub mod user_recovery_codes;
+pub mod user_totp;
 pub mod users;
 
 pub use api_clients::Entity as ApiClient;
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::entity::prelude::*;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, NotSet, Set};
+
+/// An authorization request in flight, keyed by its `state` parameter
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "oauth_states")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub state: String,
+    pub provider: String,
+    /// PKCE verifier sent with the code exchange
+    pub code_verifier: String,
+    /// Nonce the provider echoes back in the ID token
+    pub nonce: String,
+    /// Frontend path to land on after login, already validated as relative
+    pub redirect_path: Option<String>,
+    /// Signed-in user linking this provider; unset for sign-in
+    pub link_user_id: Option<i32>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Fields of an authorization request to remember until its callback
+#[derive(Debug, Clone, Default)]
+pub struct NewOAuthState<'a> {
+    pub state: &'a str,
+    pub provider: &'a str,
+    pub code_verifier: &'a str,
+    pub nonce: &'a str,
+    pub redirect_path: Option<String>,
+    pub link_user_id: Option<i32>,
+}
+
+impl Model {
+    pub async fn create(
+        db: &DatabaseConnection,
+        new: NewOAuthState<'_>,
+        ttl: Duration,
+    ) -> Result<Self, DbErr> {
+        let now = Utc::now();
+        // Opportunistic cleanup keeps the table to in-flight logins
+        Entity::delete_many()
+            .filter(Column::ExpiresAt.lt(now))
+            .exec(db)
+            .await?;
+
+        ActiveModel {
+            id: NotSet,
+            state: Set(new.state.to_string()),
+            provider: Set(new.provider.to_string()),
+            code_verifier: Set(new.code_verifier.to_string()),
+            nonce: Set(new.nonce.to_string()),
+            redirect_path: Set(new.redirect_path),
+            link_user_id: Set(new.link_user_id),
+            expires_at: Set(now + ttl),
+            created_at: Set(now),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Remove and return an unexpired state for `provider`, so each one is
+    /// redeemed once
+    pub async fn take(
+        db: &DatabaseConnection,
+        state: &str,
+        provider: &str,
+    ) -> Result<Option<Self>, DbErr> {
+        let Some(found) = Entity::find()
+            .filter(Column::State.eq(state))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let deleted = Entity::delete_by_id(found.id).exec(db).await?;
+        if deleted.rows_affected != 1 || found.provider != provider || found.expires_at < Utc::now()
+        {
+            return Ok(None);
+        }
+        Ok(Some(found))
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::entity::prelude::*;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, NotSet, Set};
+
+pub const PURPOSE_REGISTRATION: &str = "registration";
+pub const PURPOSE_AUTHENTICATION: &str = "authentication";
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "passkey_challenges")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// Random challenge, base64url without padding, as echoed in clientDataJSON
+    #[sea_orm(unique)]
+    pub challenge: String,
+    pub purpose: String,
+    /// Registering user; unset for discoverable-credential logins
+    pub user_id: Option<i32>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub async fn create(
+        db: &DatabaseConnection,
+        challenge: &str,
+        purpose: &str,
+        user_id: Option<i32>,
+        ttl: Duration,
+    ) -> Result<Self, DbErr> {
+        let now = Utc::now();
+        // Opportunistic cleanup keeps the table to in-flight ceremonies
+        Entity::delete_many()
+            .filter(Column::ExpiresAt.lt(now))
+            .exec(db)
+            .await?;
+
+        ActiveModel {
+            id: NotSet,
+            challenge: Set(challenge.to_string()),
+            purpose: Set(purpose.to_string()),
+            user_id: Set(user_id),
+            expires_at: Set(now + ttl),
+            created_at: Set(now),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Remove and return an unexpired challenge, so each one verifies once
+    pub async fn take(
+        db: &DatabaseConnection,
+        challenge: &str,
+        purpose: &str,
+    ) -> Result<Option<Self>, DbErr> {
+        let Some(found) = Entity::find()
+            .filter(Column::Challenge.eq(challenge))
+            .filter(Column::Purpose.eq(purpose))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let deleted = Entity::delete_by_id(found.id).exec(db).await?;
+        if deleted.rows_affected != 1 || found.expires_at < Utc::now() {
+            return Ok(None);
+        }
+        Ok(Some(found))
+    }
+}
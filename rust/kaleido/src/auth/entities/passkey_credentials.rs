@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{DatabaseConnection, DbErr, QueryOrder};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "passkey_credentials")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    /// Authenticator-chosen credential id, base64url without padding
+    #[sea_orm(unique)]
+    pub credential_id: String,
+    /// COSE public key, base64url without padding
+    #[sea_orm(column_type = "Text")]
+    pub public_key: String,
+    /// COSE algorithm identifier, e.g. -7 for ES256
+    pub algorithm: i32,
+    /// Last signature counter seen, to spot cloned authenticators
+    pub sign_count: i64,
+    pub name: String,
+    pub aaguid: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub async fn find_by_credential_id(
+        db: &DatabaseConnection,
+        credential_id: &str,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::CredentialId.eq(credential_id))
+            .one(db)
+            .await
+    }
+
+    /// Passkey `id` if it belongs to `user_id`
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+        id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    pub async fn list_for_user(db: &DatabaseConnection, user_id: i32) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_asc(Column::CreatedAt)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await
+    }
+
+    /// Store the counter from a successful assertion
+    ///
+    /// Only applies if the stored counter is still the one the assertion was
+    /// checked against, so two concurrent logins with the same counter cannot
+    /// both succeed. Returns false when the row moved on in between.
+    pub async fn record_use(
+        &self,
+        db: &DatabaseConnection,
+        sign_count: i64,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(Column::SignCount, Expr::value(sign_count))
+            .col_expr(Column::LastUsedAt, Expr::value(Utc::now()))
+            .filter(Column::Id.eq(self.id))
+            .filter(Column::SignCount.eq(self.sign_count))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+}
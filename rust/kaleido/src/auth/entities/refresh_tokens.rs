@@ -1,19 +1,41 @@
 use crate::auth::error::AuthError;
+use crate::auth::tokens::TokenPolicy;
 use chrono::{DateTime, Utc};
 use sea_orm::entity::prelude::*;
-use sea_orm::Condition;
+use sea_orm::sea_query::Expr;
 use sea_orm::DatabaseConnection;
 use sea_orm::Set;
+use sea_orm::{Condition, QueryOrder};
 use uuid::Uuid;
 
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
 #[sea_orm(table_name = "refresh_tokens")]
 pub struct Model {
+    /// `tokens::hash_refresh_token` of the cookie value; the raw token is
+    /// never stored
     #[sea_orm(primary_key)]
     pub token: String,
     pub user_pid: Uuid,
     pub expires_at: i64,
     pub created_at: DateTime<Utc>,
+    /// Shared by every token rotated from the same login; unset for tokens
+    /// issued before families existed
+    pub family_id: Option<Uuid>,
+    /// Stored hash of the token this one was rotated from
+    pub parent_token: Option<String>,
+    /// Set once this token has been exchanged; presenting it again is reuse
+    pub rotated_at: Option<DateTime<Utc>>,
+    /// Last refresh of the session; `created_at` is carried over on rotation
+    /// so it stays the sign-in time
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Name the user gave this session
+    pub label: Option<String>,
+    /// `jti` of the access token issued alongside, for revoking it with the
+    /// session
+    pub access_token_jti: Option<String>,
+    pub access_token_expires_at: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -22,25 +44,66 @@ pub enum Relation {}
 impl ActiveModelBehavior for ActiveModel {}
 
 impl Model {
-    pub async fn remove_by_user_pid(db: &DatabaseConnection, user_pid: &Uuid) -> Result<(), DbErr> {
-        Entity::delete_many()
+    pub async fn remove_by_user_pid(
+        db: &DatabaseConnection,
+        user_pid: &Uuid,
+    ) -> Result<u64, DbErr> {
+        let result = Entity::delete_many()
             .filter(Condition::all().add(Column::UserPid.eq(*user_pid)))
             .exec(db)
             .await?;
-        Ok(())
+        Ok(result.rows_affected)
     }
 
+    /// Store a token that starts a new family, with the policy's lifetime
     pub async fn create_record(
         db: &DatabaseConnection,
         user_pid: Uuid,
         token: &str,
+        policy: &TokenPolicy,
+    ) -> Result<(), AuthError> {
+        let expires_at =
+            (Utc::now() + chrono::Duration::seconds(policy.refresh_token_ttl_seconds)).timestamp();
+        Self::create_in_family(db, user_pid, token, expires_at, None, Uuid::new_v4(), None).await
+    }
+
+    /// Store a token in `family_id`, rotated from `parent` if given
+    ///
+    /// A rotated token inherits the session details of its parent.
+    /// `access_token` is the `jti` and expiry of the access token issued with
+    /// it.
+    pub async fn create_in_family(
+        db: &DatabaseConnection,
+        user_pid: Uuid,
+        token: &str,
+        expires_at: i64,
+        access_token: Option<(&str, i64)>,
+        family_id: Uuid,
+        parent: Option<&Model>,
     ) -> Result<(), AuthError> {
-        let am = ActiveModel {
+        let mut am = ActiveModel {
             token: Set(token.to_owned()),
             user_pid: Set(user_pid),
-            expires_at: Set((chrono::Utc::now() + chrono::Duration::days(7)).timestamp()),
+            expires_at: Set(expires_at),
+            family_id: Set(Some(family_id)),
+            parent_token: Set(None),
+            rotated_at: Set(None),
+            last_used_at: Set(None),
+            ip: Set(None),
+            user_agent: Set(None),
+            label: Set(None),
+            access_token_jti: Set(access_token.map(|(jti, _)| jti.to_owned())),
+            access_token_expires_at: Set(access_token.map(|(_, exp)| exp)),
             ..Default::default()
         };
+        if let Some(parent) = parent {
+            am.parent_token = Set(Some(parent.token.clone()));
+            am.created_at = Set(parent.created_at);
+            am.last_used_at = Set(Some(Utc::now()));
+            am.ip = Set(parent.ip.clone());
+            am.user_agent = Set(parent.user_agent.clone());
+            am.label = Set(parent.label.clone());
+        }
 
         am.insert(db).await.map_err(|e| {
             AuthError::internal_error(format!("Failed to create refresh token: {}", e))
@@ -48,4 +111,176 @@ impl Model {
 
         Ok(())
     }
+
+    /// Mark this token exchanged; false if another request got there first
+    ///
+    /// `family_id` is stored as well, which adopts tokens issued before
+    /// families existed into the family of their successor.
+    pub async fn mark_rotated(
+        &self,
+        db: &DatabaseConnection,
+        family_id: Uuid,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(Column::RotatedAt, Expr::value(Utc::now()))
+            .col_expr(Column::FamilyId, Expr::value(family_id))
+            .filter(Column::Token.eq(self.token.as_str()))
+            .filter(Column::RotatedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    fn family_condition(&self) -> Condition {
+        match self.family_id {
+            Some(family_id) => Condition::any()
+                .add(Column::FamilyId.eq(family_id))
+                .add(Column::Token.eq(self.token.as_str())),
+            None => Condition::all().add(Column::Token.eq(self.token.as_str())),
+        }
+    }
+
+    /// This token and every token in its family, as `revoke_family` deletes
+    pub async fn family_members(&self, db: &DatabaseConnection) -> Result<Vec<Model>, DbErr> {
+        Entity::find().filter(self.family_condition()).all(db).await
+    }
+
+    /// Delete this token and every token in its family
+    pub async fn revoke_family(&self, db: &DatabaseConnection) -> Result<u64, DbErr> {
+        let result = Entity::delete_many()
+            .filter(self.family_condition())
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// Delete expired tokens, and rotated ones rotated before `rotated_before`
+    ///
+    /// Rotated tokens are only kept so presenting one again is caught as
+    /// reuse. `user_pid` limits the cleanup to one user's tokens.
+    pub async fn prune(
+        db: &DatabaseConnection,
+        rotated_before: DateTime<Utc>,
+        user_pid: Option<Uuid>,
+    ) -> Result<u64, DbErr> {
+        let mut query = Entity::delete_many().filter(
+            Condition::any()
+                .add(Column::ExpiresAt.lt(Utc::now().timestamp()))
+                .add(Column::RotatedAt.lt(rotated_before)),
+        );
+        if let Some(user_pid) = user_pid {
+            query = query.filter(Column::UserPid.eq(user_pid));
+        }
+        Ok(query.exec(db).await?.rows_affected)
+    }
+
+    pub fn is_rotated(&self) -> bool {
+        self.rotated_at.is_some()
+    }
+
+    /// Record where the request that issued the token stored as `token` came from
+    pub async fn record_client(
+        db: &DatabaseConnection,
+        token: &str,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), DbErr> {
+        Entity::update_many()
+            .col_expr(Column::Ip, Expr::value(ip))
+            .col_expr(Column::UserAgent, Expr::value(user_agent))
+            .filter(Column::Token.eq(token))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Unrotated, unexpired tokens of a user, newest first; one per session
+    /// except briefly inside the reuse grace window
+    ///
+    /// Tokens issued before families existed are given one here, so every
+    /// session can be addressed by `family_id`.
+    pub async fn list_live_for_user(
+        db: &DatabaseConnection,
+        user_pid: Uuid,
+    ) -> Result<Vec<Model>, DbErr> {
+        let mut tokens = Entity::find()
+            .filter(Column::UserPid.eq(user_pid))
+            .filter(Column::RotatedAt.is_null())
+            .filter(Column::ExpiresAt.gt(Utc::now().timestamp()))
+            .order_by_desc(Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        for token in tokens.iter_mut().filter(|t| t.family_id.is_none()) {
+            let family_id = Uuid::new_v4();
+            Entity::update_many()
+                .col_expr(Column::FamilyId, Expr::value(family_id))
+                .filter(Column::Token.eq(token.token.as_str()))
+                .exec(db)
+                .await?;
+            token.family_id = Some(family_id);
+        }
+
+        Ok(tokens)
+    }
+
+    /// Delete every token of one of a user's sessions
+    pub async fn revoke_session(
+        db: &DatabaseConnection,
+        user_pid: Uuid,
+        family_id: Uuid,
+    ) -> Result<u64, DbErr> {
+        let result = Entity::delete_many()
+            .filter(Column::UserPid.eq(user_pid))
+            .filter(Column::FamilyId.eq(family_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// Every token of one of the user's sessions, rotated ones included, or
+    /// of all of them when `family_id` is `None`
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        user_pid: Uuid,
+        family_id: Option<Uuid>,
+    ) -> Result<Vec<Model>, DbErr> {
+        let mut query = Entity::find().filter(Column::UserPid.eq(user_pid));
+        if let Some(family_id) = family_id {
+            query = query.filter(Column::FamilyId.eq(family_id));
+        }
+        query.all(db).await
+    }
+
+    /// Delete all of a user's tokens; returns how many live sessions ended
+    pub async fn revoke_all_sessions(
+        db: &DatabaseConnection,
+        user_pid: Uuid,
+    ) -> Result<u64, DbErr> {
+        let mut families: Vec<Uuid> = Self::list_live_for_user(db, user_pid)
+            .await?
+            .into_iter()
+            .filter_map(|t| t.family_id)
+            .collect();
+        families.sort();
+        families.dedup();
+        Self::remove_by_user_pid(db, &user_pid).await?;
+        Ok(families.len() as u64)
+    }
+
+    /// Set the label on every token of a session, so it survives rotation
+    pub async fn label_session(
+        db: &DatabaseConnection,
+        user_pid: Uuid,
+        family_id: Uuid,
+        label: Option<String>,
+    ) -> Result<u64, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(Column::Label, Expr::value(label))
+            .filter(Column::UserPid.eq(user_pid))
+            .filter(Column::FamilyId.eq(family_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
 }
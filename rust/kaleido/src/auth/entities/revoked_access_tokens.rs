@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{DatabaseConnection, DbErr, Set};
+
+/// Access tokens rejected before they expire, see `revocation::RevocationStore`
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "revoked_access_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub jti: String,
+    pub user_pid: Option<Uuid>,
+    /// `exp` of the token; the row is useless afterwards
+    pub expires_at: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Record a revocation; revoking a token twice is a no-op
+    pub async fn revoke(
+        db: &DatabaseConnection,
+        jti: &str,
+        user_pid: Option<Uuid>,
+        expires_at: i64,
+    ) -> Result<(), DbErr> {
+        Entity::insert(ActiveModel {
+            jti: Set(jti.to_string()),
+            user_pid: Set(user_pid),
+            expires_at: Set(expires_at),
+            created_at: Set(Utc::now()),
+        })
+        .on_conflict(OnConflict::column(Column::Jti).do_nothing().to_owned())
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Revocations of tokens that have not expired yet
+    pub async fn list_live(db: &DatabaseConnection) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::ExpiresAt.gte(Utc::now().timestamp()))
+            .all(db)
+            .await
+    }
+
+    /// Delete revocations of tokens that have expired anyway
+    pub async fn prune(db: &DatabaseConnection) -> Result<u64, DbErr> {
+        let result = Entity::delete_many()
+            .filter(Column::ExpiresAt.lt(Utc::now().timestamp()))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
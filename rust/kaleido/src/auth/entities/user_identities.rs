@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use sea_orm::{
+    ActiveModelTrait, DatabaseConnection, DbErr, NotSet, PaginatorTrait, QueryOrder, Set,
+};
+
+/// A provider account a user can sign in with
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "user_identities")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub provider: String,
+    /// Provider's stable user id (`sub`); unique per provider
+    pub subject: String,
+    /// Email the provider reported at the last sign-in
+    pub email: Option<String>,
+    /// Whether the provider asserted that email as verified
+    pub email_verified: bool,
+    pub linked_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub async fn find_by_subject(
+        db: &DatabaseConnection,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::Provider.eq(provider))
+            .filter(Column::Subject.eq(subject))
+            .one(db)
+            .await
+    }
+
+    /// Identity `id` if it belongs to `user_id`
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+        id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    pub async fn list_for_user(db: &DatabaseConnection, user_id: i32) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_asc(Column::LinkedAt)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await
+    }
+
+    pub async fn count_for_user(db: &DatabaseConnection, user_id: i32) -> Result<u64, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .count(db)
+            .await
+    }
+
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: i32,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+        email_verified: bool,
+    ) -> Result<Self, DbErr> {
+        ActiveModel {
+            id: NotSet,
+            user_id: Set(user_id),
+            provider: Set(provider.to_string()),
+            subject: Set(subject.to_string()),
+            email: Set(email.map(str::to_string)),
+            email_verified: Set(email_verified),
+            linked_at: Set(Utc::now()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Keep the stored email current with what the provider last reported
+    pub async fn record_email(
+        self,
+        db: &DatabaseConnection,
+        email: &str,
+        email_verified: bool,
+    ) -> Result<Self, DbErr> {
+        if self.email.as_deref() == Some(email) && self.email_verified == email_verified {
+            return Ok(self);
+        }
+        let mut active: ActiveModel = self.into();
+        active.email = Set(Some(email.to_string()));
+        active.email_verified = Set(email_verified);
+        active.update(db).await
+    }
+}
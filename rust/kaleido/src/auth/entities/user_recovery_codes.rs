@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{DatabaseConnection, DbErr, NotSet, Set, TransactionTrait};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "user_recovery_codes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    /// SHA-256 of the normalized code; the code itself is shown once
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Replace every recovery code of `user_id` with `code_hashes`
+    pub async fn replace_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+        code_hashes: Vec<String>,
+    ) -> Result<(), DbErr> {
+        let txn = db.begin().await?;
+        Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+
+        let now = Utc::now();
+        let rows = code_hashes.into_iter().map(|code_hash| ActiveModel {
+            id: NotSet,
+            user_id: Set(user_id),
+            code_hash: Set(code_hash),
+            used_at: Set(None),
+            created_at: Set(now),
+        });
+        Entity::insert_many(rows).exec(&txn).await?;
+        txn.commit().await
+    }
+
+    /// Mark an unused code as used; false if it does not exist or was spent
+    pub async fn consume(
+        db: &DatabaseConnection,
+        user_id: i32,
+        code_hash: &str,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(Column::UsedAt, Expr::value(Utc::now()))
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::CodeHash.eq(code_hash))
+            .filter(Column::UsedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    pub async fn count_remaining(db: &DatabaseConnection, user_id: i32) -> Result<u64, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::UsedAt.is_null())
+            .count(db)
+            .await
+    }
+
+    pub async fn remove_for_user(db: &DatabaseConnection, user_id: i32) -> Result<(), DbErr> {
+        Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
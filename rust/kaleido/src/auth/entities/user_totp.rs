@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{Condition, DatabaseConnection, DbErr};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "user_totp")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub user_id: i32,
+    /// Base32 shared secret sealed with `totp::seal_secret`
+    pub secret: String,
+    /// Set once the user proves the authenticator works; unconfirmed rows
+    /// do not gate login
+    pub confirmed_at: Option<DateTime<Utc>>,
+    /// Last accepted time step, so a code cannot be replayed
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub async fn find_by_user_id(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// Confirmed enrollment for `user_id`, if two-factor login is on
+    pub async fn find_confirmed(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::ConfirmedAt.is_not_null())
+            .one(db)
+            .await
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+    /// Record `step` as used, unless this or a later step already was
+    ///
+    /// The check and the write are one conditional update, so two requests
+    /// presenting the same code cannot both succeed. Returns the updated
+    /// enrollment, or `None` when the step was already spent.
+    pub async fn use_step(self, db: &DatabaseConnection, step: i64) -> Result<Option<Self>, DbErr> {
+        let now = Utc::now();
+        let result = Entity::update_many()
+            .col_expr(Column::LastUsedStep, Expr::value(step))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(self.id))
+            .filter(
+                Condition::any()
+                    .add(Column::LastUsedStep.is_null())
+                    .add(Column::LastUsedStep.lt(step)),
+            )
+            .exec(db)
+            .await?;
+
+        Ok((result.rows_affected == 1).then_some(Self {
+            last_used_step: Some(step),
+            updated_at: now,
+            ..self
+        }))
+    }
+}
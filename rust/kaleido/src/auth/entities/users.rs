@@ -22,10 +22,10 @@ pub struct Model {
     pub email_verified_at: Option<DateTime<Utc>>,
     pub magic_link_token: Option<String>,
     pub magic_link_expiration: Option<DateTime<Utc>>,
-    pub oauth_subject: Option<String>,
-    pub oauth_provider: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Set by an admin; disabled users cannot sign in or use existing tokens
+    pub disabled_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,7 +1,11 @@
 use crate::auth::cookies::REFRESH_COOKIE_NAME;
 use crate::auth::entities::{api_clients, refresh_tokens};
 use crate::auth::error::AuthError;
-use crate::auth::tokens::{verify_access_token, TokenType};
+use crate::auth::jwt_keys::JwtKeys;
+use crate::auth::revocation::RevocationStore;
+use crate::auth::tokens::{
+    hash_refresh_token, verify_access_token, Claims, TokenPolicy, TokenType,
+};
 use axum::http::request::Parts;
 use axum::{
     async_trait,
@@ -32,6 +36,8 @@ pub struct AuthInfo<T: AuthStorage> {
     pub identity: Option<AuthIdentity>,
     /// If the request used a refresh cookie, this contains the token value
     pub refresh_token: Option<String>,
+    /// Claims of the bearer token, if the request was authenticated by one
+    pub access_token: Option<Claims>,
     _phantom: std::marker::PhantomData<T>,
 }
 
@@ -40,9 +46,15 @@ impl<T: AuthStorage> AuthInfo<T> {
         Self {
             identity,
             refresh_token,
+            access_token: None,
             _phantom: std::marker::PhantomData,
         }
     }
+
+    fn with_access_token(mut self, claims: Claims) -> Self {
+        self.access_token = Some(claims);
+        self
+    }
 }
 
 impl ApiClientIdentity {
@@ -57,7 +69,26 @@ impl ApiClientIdentity {
 /// Implement this trait to integrate with your application's storage layer.
 pub trait AuthStorage: Send + Sync {
     fn db(&self) -> &DatabaseConnection;
-    fn jwt_secret(&self) -> &str;
+
+    /// Keys for verifying access tokens; must match `ConfigProvider::jwt_keys`
+    fn jwt_keys(&self) -> &JwtKeys;
+
+    /// Key for the HMAC under which refresh tokens are stored; must match
+    /// `ConfigProvider::refresh_token_key`
+    fn refresh_token_key(&self) -> &str;
+
+    /// Claims required of access tokens; must match
+    /// `ConfigProvider::token_policy`
+    fn token_policy(&self) -> &TokenPolicy {
+        TokenPolicy::default_ref()
+    }
+
+    /// Denylist checked for every bearer token; `None` keeps access tokens
+    /// stateless. Should be the store given to
+    /// `AuthService::with_revocation_store`.
+    fn revocation_store(&self) -> Option<&RevocationStore> {
+        None
+    }
 }
 
 fn extract_cookie(headers: &axum::http::HeaderMap, name: &str) -> Result<String, AuthError> {
@@ -91,7 +122,7 @@ where
         let mut cookie_error: Option<AuthError> = None;
 
         if let Ok(token) = extract_cookie(headers, REFRESH_COOKIE_NAME) {
-            match verify_refresh_token(storage.db(), &token).await {
+            match verify_refresh_token(storage.db(), storage.refresh_token_key(), &token).await {
                 Ok(user_pid) => {
                     return Ok(AuthInfo::new(
                         Some(AuthIdentity::User(UserIdentity { user_pid })),
@@ -106,16 +137,17 @@ where
 
         if let Some(auth_hdr) = headers.get("authorization").and_then(|v| v.to_str().ok()) {
             if let Some(tok) = auth_hdr.strip_prefix("Bearer ") {
-                let secret = storage.jwt_secret();
-                match verify_access_token(tok, secret) {
+                match verify_access_token(tok, storage.jwt_keys(), storage.token_policy()) {
                     Ok(td) => match td.claims.token_type {
                         TokenType::User => {
                             let user_pid = Uuid::parse_str(&td.claims.sub)
                                 .map_err(|_| AuthError::unauthorized("Invalid token"))?;
+                            check_revocation(&*storage, &td.claims).await?;
                             return Ok(AuthInfo::new(
                                 Some(AuthIdentity::User(UserIdentity { user_pid })),
                                 None,
-                            ));
+                            )
+                            .with_access_token(td.claims));
                         }
                         TokenType::ApiClient => {
                             let client_id = Uuid::parse_str(&td.claims.sub)
@@ -128,6 +160,7 @@ where
                             if revoked {
                                 return Err(AuthError::unauthorized("API client revoked"));
                             }
+                            check_revocation(&*storage, &td.claims).await?;
 
                             return Ok(AuthInfo::new(
                                 Some(AuthIdentity::ApiClient(ApiClientIdentity {
@@ -135,7 +168,12 @@ where
                                     scopes,
                                 })),
                                 None,
-                            ));
+                            )
+                            .with_access_token(td.claims));
+                        }
+                        // Only `/auth/mfa/verify` accepts challenge tokens
+                        TokenType::MfaChallenge => {
+                            return Err(AuthError::unauthorized("Invalid token"));
                         }
                     },
                     Err(_) => return Err(AuthError::unauthorized("Invalid token")),
@@ -151,12 +189,31 @@ where
     }
 }
 
-async fn verify_refresh_token(db: &DatabaseConnection, token: &str) -> Result<Uuid, AuthError> {
-    let rt = refresh_tokens::Entity::find_by_id(token.to_string())
+async fn check_revocation<T: AuthStorage>(storage: &T, claims: &Claims) -> Result<(), AuthError> {
+    let (Some(store), Some(jti)) = (storage.revocation_store(), claims.jti.as_deref()) else {
+        return Ok(());
+    };
+    if store.is_revoked(storage.db(), jti).await? {
+        return Err(AuthError::unauthorized("Token revoked"));
+    }
+    Ok(())
+}
+
+async fn verify_refresh_token(
+    db: &DatabaseConnection,
+    key: &str,
+    token: &str,
+) -> Result<Uuid, AuthError> {
+    let rt = refresh_tokens::Entity::find_by_id(hash_refresh_token(token, key))
         .one(db)
         .await?
         .ok_or_else(|| AuthError::unauthorized("Invalid refresh token"))?;
 
+    // A rotated token only exists to detect reuse; it no longer identifies anyone
+    if rt.is_rotated() {
+        return Err(AuthError::unauthorized("Invalid refresh token"));
+    }
+
     Ok(rt.user_pid)
 }
 
@@ -95,6 +95,10 @@ where
             .map_err(|e| AuthError::internal_error(format!("Failed to query user: {}", e)))?
             .ok_or_else(|| AuthError::unauthorized("User not found"))?;
 
+        if user.disabled_at.is_some() {
+            return Err(AuthError::forbidden("Account disabled"));
+        }
+
         Ok(Self {
             user,
             _phantom: PhantomData,
@@ -136,6 +140,10 @@ where
             .map_err(|e| AuthError::internal_error(format!("Failed to query user: {}", e)))?
             .ok_or_else(|| AuthError::unauthorized("User not found"))?;
 
+        if user.disabled_at.is_some() {
+            return Err(AuthError::forbidden("Account disabled"));
+        }
+
         if user.email_verified_at.is_none() {
             return Err(AuthError::forbidden("Email verification required"));
         }
@@ -181,6 +189,10 @@ where
             .map_err(|e| AuthError::internal_error(format!("Failed to query user: {}", e)))?
             .ok_or_else(|| AuthError::unauthorized("User not found"))?;
 
+        if user.disabled_at.is_some() {
+            return Err(AuthError::forbidden("Account disabled"));
+        }
+
         if !user.is_admin.unwrap_or(false) {
             return Err(AuthError::forbidden("Admin access required"));
         }
@@ -7,6 +7,7 @@ pub enum AuthFeature {
     Auth,
     OAuth,
     AdminUsers,
+    Passkeys,
 }
 
 #[derive(Debug, Clone)]
@@ -14,6 +15,8 @@ pub struct FeatureSet {
     pub auth: bool,
     pub oauth: bool,
     pub admin_users: bool,
+    /// Passkey (WebAuthn) registration and login under `/auth/passkeys`
+    pub passkeys: bool,
 }
 
 impl Default for FeatureSet {
@@ -22,6 +25,7 @@ impl Default for FeatureSet {
             auth: true,
             oauth: false,
             admin_users: false,
+            passkeys: false,
         }
     }
 }
@@ -32,6 +36,7 @@ impl FeatureSet {
             auth: true,
             oauth: false,
             admin_users: false,
+            passkeys: false,
         }
     }
 
@@ -40,6 +45,7 @@ impl FeatureSet {
             auth: true,
             oauth: true,
             admin_users: true,
+            passkeys: true,
         }
     }
 
@@ -48,6 +54,7 @@ impl FeatureSet {
             auth: false,
             oauth: false,
             admin_users: false,
+            passkeys: false,
         };
 
         for feature in features {
@@ -55,6 +62,7 @@ impl FeatureSet {
                 AuthFeature::Auth => set.auth = true,
                 AuthFeature::OAuth => set.oauth = true,
                 AuthFeature::AdminUsers => set.admin_users = true,
+                AuthFeature::Passkeys => set.passkeys = true,
             }
         }
 
@@ -82,6 +90,10 @@ where
         router = router.nest("/oauth", controllers::oauth::routes::<S>());
     }
 
+    if features.passkeys {
+        router = router.merge(controllers::passkeys::routes::<S>());
+    }
+
     // Placeholder for upcoming feature-module route installation.
     // `admin_users` endpoints will be mounted here when extracted from app code.
     if features.admin_users {
@@ -1,10 +1,13 @@
 // Authentication and authorization library for SaaS projects
 //
 // This crate provides:
-// - User authentication (email/password, OAuth)
+// - User authentication (email/password, magic link, OAuth)
 // - API client authentication
 // - JWT token generation and validation
 // - Refresh token management
+// - Optional access token revocation
+// - TOTP two-factor authentication with recovery codes
+// - Passkey (WebAuthn) registration and login
 // - OAuth provider integration
 // - Extractors for securing routes
 // - HTTP controllers/routes for auth endpoints
@@ -16,10 +19,14 @@ pub mod entities;
 pub mod error;
 pub mod extractors;
 pub mod features;
+pub mod jwt_keys;
 pub mod openapi;
+pub mod revocation;
 pub mod services;
 pub mod tokens;
+pub mod totp;
 pub mod traits;
+pub mod webauthn;
 pub mod worker;
 
 // Re-export commonly used types
@@ -34,29 +41,48 @@ pub use controllers::admin::routes as admin_routes;
 pub use controllers::auth::session_routes;
 pub use controllers::auth::{AuthRouteStorage, MessageResponse};
 pub use controllers::routes;
-pub use cookies::{clear_refresh_cookie_value, refresh_cookie_value, REFRESH_COOKIE_NAME};
+pub use cookies::{
+    clear_oauth_state_cookie_value, clear_refresh_cookie_value, oauth_state_cookie_value,
+    refresh_cookie_value, refresh_cookie_value_with_max_age, OAUTH_STATE_COOKIE_NAME,
+    REFRESH_COOKIE_NAME,
+};
 pub use error::AuthError;
 pub use extractors::{
     AdminUserContext, ApiClientContext, ApiClientIdentity, AuthIdentity, AuthInfo, AuthStorage,
     UserContext, UserIdentity, VerifiedUserContext,
 };
 pub use features::{routes_for_features, AuthFeature, FeatureSet};
+pub use jwt_keys::{JwtKey, JwtKeyError, JwtKeys};
+pub use revocation::RevocationStore;
 pub use services::{
     api_client::{
         ApiClientCredentials, ApiClientService, ClientLoginRequest, ClientLoginResponse,
         CreateApiClientRequest,
     },
+    identities::IdentityResponse,
+    magic_link::{MagicLinkRequest, MagicLinkVerifyRequest},
+    mfa::{
+        LoginOutcome, MfaChallengeResponse, MfaCodeRequest, MfaStatusResponse, MfaVerifyRequest,
+        RecoveryCodesResponse, TotpCodeRequest, TotpEnrollmentResponse,
+    },
     oauth::{OAuthAuthorizeUrl, OAuthService, OAuthUserInfo},
     oauth_provider_service::{OAuthProviderMetadata, OAuthProviderService},
+    oidc::{IdTokenClaims, JwksCache, OidcProvider},
+    passkeys::{
+        PasskeyLoginOptionsRequest, PasskeyLoginRequest, PasskeyOptionsResponse,
+        PasskeyRegistrationRequest, PasskeyResponse, RenamePasskeyRequest,
+    },
     provider_settings::{
         env_prefix, get_provider_config, normalize_provider_id, scopes_for_provider,
         ProviderConfig, PROVIDER_DEV,
     },
+    sessions::{RenameSessionRequest, SessionClient, SessionResponse, SessionsRevokedResponse},
     AuthService, LoginRequest, RegisterRequest, RegisterResponse, TokenResponse, UserResponse,
 };
 pub use tokens::{
     access_token_ttl_seconds, generate_access_token, generate_api_client_access_token,
-    generate_refresh_token, verify_access_token, Claims, TokenType,
+    generate_refresh_token, hash_refresh_token, verify_access_token, Claims, CustomClaims,
+    TokenPolicy, TokenType,
 };
 pub use traits::{
     AuditLogger, AuthEventPayload, AuthEventType, ConfigProvider, CooldownError, CooldownManager,
@@ -64,5 +90,7 @@ pub use traits::{
     NoOpEmailService, NoOpMetricsRecorder,
 };
 
+pub use controllers::jwks::routes as jwks_routes;
 pub use controllers::oauth;
 pub use controllers::oauth::routes as oauth_routes;
+pub use controllers::passkeys::routes as passkey_routes;
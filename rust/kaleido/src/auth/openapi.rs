@@ -5,26 +5,70 @@
 pub mod paths {
     // Re-export the original path functions
     pub use crate::auth::controllers::auth::{
-        current, forgot_password, login, logout, refresh, register, resend_confirmation,
-        reset_password, verify_email,
+        current, forgot_password, login, logout, mfa_recovery_codes, mfa_status, mfa_totp_confirm,
+        mfa_totp_disable, mfa_totp_enroll, mfa_verify, refresh, register, request_magic_link,
+        resend_confirmation, reset_password, verify_email, verify_magic_link,
+    };
+    pub use crate::auth::controllers::jwks::jwks;
+    pub use crate::auth::controllers::oauth::{
+        list_identities, oauth_authorize, oauth_callback, oauth_link, oauth_providers,
+        unlink_identity,
+    };
+    pub use crate::auth::controllers::passkeys::{
+        delete_passkey, list_passkeys, login_options as passkey_login_options,
+        login_verify as passkey_login_verify, registration_options as passkey_registration_options,
+        registration_verify as passkey_registration_verify, rename_passkey,
+    };
+    pub use crate::auth::controllers::sessions::{
+        list_sessions, rename_session, revoke_all_sessions, revoke_session,
     };
-    pub use crate::auth::controllers::oauth::{oauth_authorize, oauth_callback, oauth_providers};
 
     // Re-export the utoipa-generated marker types so downstream `derive(OpenApi)`
     // can resolve the path markers from this module (e.g. `auth::openapi::paths::register`).
     pub use crate::auth::controllers::auth::{
-        __path_current, __path_forgot_password, __path_login, __path_logout, __path_refresh,
-        __path_register, __path_resend_confirmation, __path_reset_password, __path_verify_email,
+        __path_current, __path_forgot_password, __path_login, __path_logout,
+        __path_mfa_recovery_codes, __path_mfa_status, __path_mfa_totp_confirm,
+        __path_mfa_totp_disable, __path_mfa_totp_enroll, __path_mfa_verify, __path_refresh,
+        __path_register, __path_request_magic_link, __path_resend_confirmation,
+        __path_reset_password, __path_verify_email, __path_verify_magic_link,
     };
+    pub use crate::auth::controllers::jwks::__path_jwks;
     pub use crate::auth::controllers::oauth::{
-        __path_oauth_authorize, __path_oauth_callback, __path_oauth_providers,
+        __path_list_identities, __path_oauth_authorize, __path_oauth_callback, __path_oauth_link,
+        __path_oauth_providers, __path_unlink_identity,
+    };
+    pub use crate::auth::controllers::passkeys::{
+        __path_delete_passkey, __path_list_passkeys,
+        __path_login_options as __path_passkey_login_options,
+        __path_login_verify as __path_passkey_login_verify,
+        __path_registration_options as __path_passkey_registration_options,
+        __path_registration_verify as __path_passkey_registration_verify, __path_rename_passkey,
+    };
+    pub use crate::auth::controllers::sessions::{
+        __path_list_sessions, __path_rename_session, __path_revoke_all_sessions,
+        __path_revoke_session,
     };
 }
 
 pub mod schemas {
     pub use crate::auth::controllers::auth::MessageResponse;
-    pub use crate::auth::controllers::oauth::OAuthProvidersResponse;
+    pub use crate::auth::controllers::oauth::{OAuthLinkResponse, OAuthProvidersResponse};
+    pub use crate::auth::services::identities::IdentityResponse;
+    pub use crate::auth::services::magic_link::{MagicLinkRequest, MagicLinkVerifyRequest};
+    pub use crate::auth::services::mfa::{
+        MfaChallengeResponse, MfaCodeRequest, MfaStatusResponse, MfaVerifyRequest,
+        RecoveryCodesResponse, TotpCodeRequest, TotpEnrollmentResponse,
+    };
     pub use crate::auth::services::oauth_provider_service::OAuthProviderMetadata;
+    pub use crate::auth::services::passkeys::{
+        PasskeyAssertionResponse, PasskeyAttestationResponse, PasskeyAuthenticationCredential,
+        PasskeyLoginOptionsRequest, PasskeyLoginRequest, PasskeyOptionsResponse,
+        PasskeyRegistrationCredential, PasskeyRegistrationRequest, PasskeyResponse,
+        RenamePasskeyRequest,
+    };
+    pub use crate::auth::services::sessions::{
+        RenameSessionRequest, SessionResponse, SessionsRevokedResponse,
+    };
     pub use crate::auth::services::{
         ForgotPasswordRequest, LoginRequest, RegisterRequest, RegisterResponse,
         ResendConfirmationRequest, ResetPasswordRequest, TokenResponse, UserResponse,
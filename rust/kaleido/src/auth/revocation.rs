@@ -0,0 +1,153 @@
+// Access token revocation
+//
+// Access tokens are checked by signature alone, so signing a session out or
+// disabling a user leaves the tokens already handed out working until they
+// expire. A `RevocationStore` closes that gap: the `jti`s of those tokens
+// go into `revoked_access_tokens` and the `AuthInfo` extractor rejects them.
+//
+// Each instance keeps the denylist in memory and reloads it at most every
+// `refresh_interval`, so a revocation made by another instance takes effect
+// within that interval and a check costs no query in between. Rows are
+// pruned on reload once the token would have expired anyway, which keeps
+// the list to roughly one access token lifetime of revocations.
+//
+// The store is optional. Apps that prefer stateless tokens leave it out of
+// `AuthService` and `AuthStorage`, and tokens stay valid until `exp`.
+
+use crate::auth::entities::{refresh_tokens, revoked_access_tokens};
+use crate::auth::tokens::Claims;
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, DbErr};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Database-backed `jti` denylist with an in-memory cache
+///
+/// Clones share the cache; hand the same store to `AuthService` and
+/// `AuthStorage`.
+#[derive(Clone)]
+pub struct RevocationStore {
+    refresh_interval: Duration,
+    cache: Arc<RwLock<Cache>>,
+}
+
+#[derive(Default)]
+struct Cache {
+    /// `jti` to `exp`
+    revoked: HashMap<String, i64>,
+    loaded_at: Option<Instant>,
+}
+
+impl Cache {
+    fn is_fresh(&self, refresh_interval: Duration) -> bool {
+        self.loaded_at
+            .is_some_and(|at| at.elapsed() < refresh_interval)
+    }
+}
+
+impl Default for RevocationStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for RevocationStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RevocationStore")
+            .field("refresh_interval", &self.refresh_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RevocationStore {
+    pub fn new() -> Self {
+        Self {
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            cache: Arc::new(RwLock::new(Cache::default())),
+        }
+    }
+
+    /// How stale the cached denylist may get before it is reloaded
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Reject the token `jti` until `expires_at`
+    pub async fn revoke(
+        &self,
+        db: &DatabaseConnection,
+        jti: &str,
+        user_pid: Option<Uuid>,
+        expires_at: i64,
+    ) -> Result<(), DbErr> {
+        if expires_at < Utc::now().timestamp() {
+            return Ok(());
+        }
+        revoked_access_tokens::Model::revoke(db, jti, user_pid, expires_at).await?;
+        self.cache
+            .write()
+            .await
+            .revoked
+            .insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+
+    /// Revoke a verified access token; tokens without a `jti` cannot be
+    pub async fn revoke_claims(
+        &self,
+        db: &DatabaseConnection,
+        claims: &Claims,
+    ) -> Result<(), DbErr> {
+        let Some(jti) = claims.jti.as_deref() else {
+            return Ok(());
+        };
+        let user_pid = Uuid::parse_str(&claims.sub).ok();
+        self.revoke(db, jti, user_pid, claims.exp).await
+    }
+
+    /// Revoke the access tokens issued alongside `tokens`
+    pub async fn revoke_issued_with(
+        &self,
+        db: &DatabaseConnection,
+        tokens: &[refresh_tokens::Model],
+    ) -> Result<(), DbErr> {
+        for token in tokens {
+            if let (Some(jti), Some(expires_at)) = (
+                token.access_token_jti.as_deref(),
+                token.access_token_expires_at,
+            ) {
+                self.revoke(db, jti, Some(token.user_pid), expires_at)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn is_revoked(&self, db: &DatabaseConnection, jti: &str) -> Result<bool, DbErr> {
+        {
+            let cache = self.cache.read().await;
+            if cache.is_fresh(self.refresh_interval) {
+                return Ok(cache.revoked.contains_key(jti));
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+        // Another request may have reloaded while this one waited
+        if !cache.is_fresh(self.refresh_interval) {
+            revoked_access_tokens::Model::prune(db).await?;
+            cache.revoked = revoked_access_tokens::Model::list_live(db)
+                .await?
+                .into_iter()
+                .map(|row| (row.jti, row.expires_at))
+                .collect();
+            cache.loaded_at = Some(Instant::now());
+        }
+        Ok(cache.revoked.contains_key(jti))
+    }
+}
@@ -5,25 +5,36 @@
 // reusable across different SaaS applications.
 
 pub mod api_client;
+pub mod identities;
+pub mod magic_link;
+pub mod mfa;
 pub mod oauth;
 pub mod oauth_provider_service;
+pub mod oidc;
+pub mod passkeys;
 pub mod provider_settings;
+pub mod sessions;
 
 use crate::auth::entities::{refresh_tokens, users};
 use crate::auth::error::AuthError;
-use crate::auth::tokens::generate_access_token;
+use crate::auth::revocation::RevocationStore;
+use crate::auth::services::mfa::LoginOutcome;
+use crate::auth::tokens::{
+    generate_access_token, hash_refresh_token, Claims, CustomClaims, REFRESH_REUSE_DETECTION_HOURS,
+};
 use crate::auth::traits::{
     AuditLogger, AuthEventPayload, AuthEventType, ConfigProvider, CooldownManager, CooldownType,
     EmailService, MetricsRecorder,
 };
 use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use argon2::Argon2;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rand::rngs::OsRng;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, Set,
 };
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
@@ -106,8 +117,6 @@ impl RegisterRequest {
             email_verified_at: Set(None),
             magic_link_token: Set(None),
             magic_link_expiration: Set(None),
-            oauth_subject: Set(None),
-            oauth_provider: Set(None),
             ..Default::default()
         }
     }
@@ -124,7 +133,12 @@ pub struct LoginRequest {
 #[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct TokenResponse {
     pub access_token: String,
+    /// Seconds until `access_token` expires
+    pub expires_in: i64,
     pub refresh_token: String,
+    /// Seconds until `refresh_token` expires; sets the cookie `Max-Age`
+    #[serde(skip)]
+    pub refresh_token_expires_in: i64,
     pub pid: String,
     pub name: String,
     pub email: String,
@@ -177,6 +191,9 @@ impl From<users::Model> for UserResponse {
     }
 }
 
+/// Custom claims for a user's access tokens, see [`AuthService::with_claims_hook`]
+pub type ClaimsHook = Arc<dyn Fn(&users::Model) -> CustomClaims + Send + Sync>;
+
 /// Authentication service with dependency injection
 ///
 /// Generic parameters allow different implementations of core services
@@ -194,6 +211,8 @@ where
     audit: A,
     metrics: M,
     config: F,
+    claims_hook: Option<ClaimsHook>,
+    revocation_store: Option<RevocationStore>,
 }
 impl<E, C, A, M, F> AuthService<E, C, A, M, F>
 where
@@ -210,9 +229,33 @@ where
             audit,
             metrics,
             config,
+            claims_hook: None,
+            revocation_store: None,
         }
     }
 
+    /// Revoke access tokens along with their sessions on logout, session
+    /// revocation and refresh token reuse
+    ///
+    /// Pass the same store to `AuthStorage::revocation_store` so the
+    /// extractors reject them.
+    pub fn with_revocation_store(mut self, store: RevocationStore) -> Self {
+        self.revocation_store = Some(store);
+        self
+    }
+
+    /// Add app claims, e.g. roles or tenant, to every user access token
+    ///
+    /// Runs on each login and refresh, so keep it cheap. Claims named in
+    /// [`RESERVED_CLAIMS`](crate::auth::tokens::RESERVED_CLAIMS) are dropped.
+    pub fn with_claims_hook<H>(mut self, hook: H) -> Self
+    where
+        H: Fn(&users::Model) -> CustomClaims + Send + Sync + 'static,
+    {
+        self.claims_hook = Some(Arc::new(hook));
+        self
+    }
+
     // --- INTERNAL ORCHESTRATORS (The "Magic" simplification) ---
 
     async fn gatekeep(&self, kind: CooldownType, id: Option<i32>) -> Result<(), AuthError> {
@@ -247,7 +290,9 @@ where
 
         match event {
             AuthEventType::LoginSucceeded => self.metrics.record_login(),
-            AuthEventType::LoginFailed => self.metrics.record_failed_login(),
+            AuthEventType::LoginFailed | AuthEventType::MfaFailed => {
+                self.metrics.record_failed_login()
+            }
             AuthEventType::TokenRefresh => self.metrics.record_token_refresh(),
             AuthEventType::Logout => self.metrics.record_logout(),
             _ => {}
@@ -397,7 +442,7 @@ where
         &self,
         db: &DatabaseConnection,
         payload: LoginRequest,
-    ) -> Result<TokenResponse, AuthError> {
+    ) -> Result<LoginOutcome, AuthError> {
         payload.validate()?;
 
         let user = users::Model::find_by_email(db, &payload.email)
@@ -420,16 +465,12 @@ where
             return Err(e);
         }
 
-        let resp = self.issue_tokens(db, &user).await?;
-
         let _ = self
             .cooldown
             .reset_cooldown(CooldownType::Login, Some(user.id))
             .await;
-        self.log_and_track(AuthEventType::LoginSucceeded, Some(&user), None)
-            .await;
 
-        Ok(resp)
+        self.complete_primary_login(db, &user).await
     }
 
     pub async fn forgot_password(
@@ -468,12 +509,19 @@ where
         Ok(())
     }
 
+    /// Exchange a refresh token for a new pair
+    ///
+    /// The presented token is kept and marked rotated rather than deleted,
+    /// so presenting it again is recognised as reuse: the legitimate client
+    /// and whoever else holds a copy have diverged, and the whole family is
+    /// revoked. Reuse within `ConfigProvider::refresh_reuse_grace_seconds`
+    /// is treated as a concurrent refresh and gets its own new token.
     pub async fn refresh(
         &self,
         db: &DatabaseConnection,
         refresh_token: String,
     ) -> Result<TokenResponse, AuthError> {
-        let db_token = refresh_tokens::Entity::find_by_id(refresh_token)
+        let db_token = refresh_tokens::Entity::find_by_id(self.refresh_token_hash(&refresh_token))
             .one(db)
             .await?
             .ok_or_else(|| AuthError::unauthorized("Invalid refresh token"))?;
@@ -493,27 +541,96 @@ where
             .await?
             .ok_or_else(|| AuthError::unauthorized("User not found"))?;
 
-        refresh_tokens::Entity::delete_by_id(db_token.token)
-            .exec(db)
+        let family_id = db_token.family_id.unwrap_or_else(Uuid::new_v4);
+        let rotated_at = match db_token.rotated_at {
+            Some(rotated_at) => Some(rotated_at),
+            // Losing the race means a concurrent request rotated it just now
+            None if !db_token.mark_rotated(db, family_id).await? => Some(Utc::now()),
+            None => None,
+        };
+
+        if let Some(rotated_at) = rotated_at {
+            let grace = chrono::Duration::seconds(self.config.refresh_reuse_grace_seconds());
+            if Utc::now() - rotated_at > grace {
+                let db_token = refresh_tokens::Model {
+                    family_id: Some(family_id),
+                    ..db_token
+                };
+                self.revoke_access_tokens(db, &db_token.family_members(db).await?)
+                    .await?;
+                db_token.revoke_family(db).await?;
+                self.log_and_track(AuthEventType::TokenReuseDetected, Some(&user), None)
+                    .await;
+                return Err(AuthError::unauthorized("Invalid refresh token"));
+            }
+        }
+
+        let resp = self
+            .issue_tokens_in_family(db, &user, family_id, Some(&db_token))
             .await?;
-        let resp = self.issue_tokens(db, &user).await?;
 
         self.log_and_track(AuthEventType::TokenRefresh, Some(&user), None)
             .await;
         Ok(resp)
     }
 
-    pub async fn logout(&self, _pid: Uuid) -> Result<(), AuthError> {
-        let _ = self
-            .audit
-            .log_event(AuthEventType::Logout, AuthEventPayload::default())
+    /// End the session `refresh_token` belongs to
+    ///
+    /// Without a refresh token, as for bearer-only clients, there is no
+    /// session to end. With a revocation store, the presented access token
+    /// and those issued to the session are revoked as well.
+    pub async fn logout(
+        &self,
+        db: &DatabaseConnection,
+        pid: Uuid,
+        refresh_token: Option<&str>,
+        access_token: Option<&Claims>,
+    ) -> Result<(), AuthError> {
+        if let Some(refresh_token) = refresh_token {
+            let token = refresh_tokens::Entity::find_by_id(self.refresh_token_hash(refresh_token))
+                .one(db)
+                .await?;
+            if let Some(token) = token.filter(|t| t.user_pid == pid) {
+                self.revoke_access_tokens(db, &token.family_members(db).await?)
+                    .await?;
+                token.revoke_family(db).await?;
+            }
+        }
+        if let (Some(store), Some(claims)) = (&self.revocation_store, access_token) {
+            store.revoke_claims(db, claims).await?;
+        }
+
+        let user = users::Entity::find()
+            .filter(users::Column::Pid.eq(pid))
+            .one(db)
+            .await?;
+        self.log_and_track(AuthEventType::Logout, user.as_ref(), None)
             .await;
-        self.metrics.record_logout();
         Ok(())
     }
 
+    /// Delete expired refresh tokens, and rotated ones past reuse detection
+    ///
+    /// Each user's tokens are already pruned whenever they sign in or
+    /// refresh; run this on a schedule to clear out users who stopped
+    /// coming back.
+    pub async fn prune_refresh_tokens(&self, db: &DatabaseConnection) -> Result<u64, AuthError> {
+        Ok(refresh_tokens::Model::prune(db, self.rotated_token_cutoff(), None).await?)
+    }
+
     // --- HELPERS ---
 
+    /// Tokens rotated before this are no longer needed to detect reuse
+    ///
+    /// They are also kept while the access tokens issued with them may still
+    /// be valid, so revoking the session can still revoke those.
+    fn rotated_token_cutoff(&self) -> DateTime<Utc> {
+        let detection = self.config.refresh_reuse_grace_seconds().max(0)
+            + REFRESH_REUSE_DETECTION_HOURS * 60 * 60;
+        let keep = detection.max(self.config.token_policy().access_token_ttl_seconds);
+        Utc::now() - chrono::Duration::seconds(keep)
+    }
+
     async fn hash_password(password: &str) -> Result<String, AuthError> {
         let salt = SaltString::generate(&mut OsRng);
         Ok(Argon2::default()
@@ -528,19 +645,55 @@ where
             .map_err(|_| AuthError::unauthorized("Invalid password"))
     }
 
+    /// Tokens for a new session, starting a new refresh token family
     pub async fn issue_tokens(
         &self,
         db: &DatabaseConnection,
         user: &users::Model,
     ) -> Result<TokenResponse, AuthError> {
-        let access_token = generate_access_token(user, self.config.jwt_secret())?;
-        let refresh_token = Self::generate_refresh_token(user);
+        self.issue_tokens_in_family(db, user, Uuid::new_v4(), None)
+            .await
+    }
+
+    async fn issue_tokens_in_family(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        family_id: Uuid,
+        parent: Option<&refresh_tokens::Model>,
+    ) -> Result<TokenResponse, AuthError> {
+        if user.disabled_at.is_some() {
+            return Err(AuthError::forbidden("Account disabled"));
+        }
 
-        refresh_tokens::Model::create_record(db, user.pid, &refresh_token.token).await?;
+        let policy = self.config.token_policy();
+        let custom = self
+            .claims_hook
+            .as_ref()
+            .map(|hook| hook(user))
+            .unwrap_or_default();
+        let (access_token, claims) =
+            generate_access_token(user, self.config.jwt_keys(), policy, custom)?;
+        let refresh_token = self.generate_refresh_token(user);
+
+        refresh_tokens::Model::create_in_family(
+            db,
+            user.pid,
+            &self.refresh_token_hash(&refresh_token.token),
+            refresh_token.expires_at,
+            claims.jti.as_deref().map(|jti| (jti, claims.exp)),
+            family_id,
+            parent,
+        )
+        .await?;
+        // Cleanup only; a failure here must not fail the sign-in
+        let _ = refresh_tokens::Model::prune(db, self.rotated_token_cutoff(), Some(user.pid)).await;
 
         Ok(TokenResponse {
             access_token,
+            expires_in: policy.access_token_ttl_seconds,
             refresh_token: refresh_token.token,
+            refresh_token_expires_in: policy.refresh_token_ttl_seconds,
             pid: user.pid.to_string(),
             name: user.name.clone(),
             email: user.email.clone(),
@@ -548,9 +701,26 @@ where
         })
     }
 
-    fn generate_refresh_token(_user: &users::Model) -> RefreshToken {
+    /// Revoke the access tokens issued with `tokens`, if revocation is on
+    async fn revoke_access_tokens(
+        &self,
+        db: &DatabaseConnection,
+        tokens: &[refresh_tokens::Model],
+    ) -> Result<(), AuthError> {
+        if let Some(store) = &self.revocation_store {
+            store.revoke_issued_with(db, tokens).await?;
+        }
+        Ok(())
+    }
+
+    /// Stored form of a refresh token, as used for `refresh_tokens.token`
+    fn refresh_token_hash(&self, token: &str) -> String {
+        hash_refresh_token(token, self.config.refresh_token_key())
+    }
+
+    fn generate_refresh_token(&self, _user: &users::Model) -> RefreshToken {
         use crate::auth::tokens::generate_refresh_token as gen_token;
-        let (token, exp) = gen_token();
+        let (token, exp) = gen_token(self.config.token_policy());
         RefreshToken {
             token,
             expires_at: exp,
@@ -1,6 +1,7 @@
 use crate::auth::entities::api_clients;
 use crate::auth::error::AuthError;
-use crate::auth::tokens::{access_token_ttl_seconds, generate_api_client_access_token};
+use crate::auth::jwt_keys::JwtKeys;
+use crate::auth::tokens::{generate_api_client_access_token, TokenPolicy};
 use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use argon2::Argon2;
 use chrono::Utc;
@@ -151,16 +152,17 @@ impl ApiClientService {
     pub async fn authenticate_and_build_response(
         db: &DatabaseConnection,
         payload: ClientLoginRequest,
-        jwt_secret: &str,
+        jwt_keys: &JwtKeys,
+        policy: &TokenPolicy,
     ) -> Result<ClientLoginResponse, AuthError> {
         let client = ApiClientService::authenticate(db, payload).await?;
 
-        let token = generate_api_client_access_token(&client, jwt_secret)
+        let token = generate_api_client_access_token(&client, jwt_keys, policy)
             .map_err(|e| AuthError::internal_error(e.to_string()))?;
         let resp = ClientLoginResponse {
             access_token: token,
             token_type: "Bearer".to_string(),
-            expires_in: access_token_ttl_seconds(),
+            expires_in: policy.access_token_ttl_seconds,
             client_id: client.client_id.to_string(),
             scopes: client.parsed_scopes(),
         };
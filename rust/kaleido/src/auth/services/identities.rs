@@ -0,0 +1,128 @@
+// Linked provider identities
+//
+// A user can sign in through any number of OAuth/OIDC provider accounts,
+// each a row in `user_identities` keyed by provider and subject. Signing in
+// with an unknown provider account links it to the user with the same
+// email only when the provider asserts the email is verified; otherwise a
+// signed-in user links it explicitly through the link flow.
+
+use super::AuthService;
+use crate::auth::entities::{passkey_credentials, user_identities, users};
+use crate::auth::error::AuthError;
+use crate::auth::services::oauth::OAuthUserInfo;
+use crate::auth::traits::{
+    AuditLogger, AuthEventType, ConfigProvider, CooldownManager, EmailService, MetricsRecorder,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseConnection, ModelTrait};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct IdentityResponse {
+    pub id: i32,
+    pub provider: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub linked_at: DateTime<Utc>,
+}
+
+impl From<user_identities::Model> for IdentityResponse {
+    fn from(m: user_identities::Model) -> Self {
+        Self {
+            id: m.id,
+            provider: m.provider,
+            email: m.email,
+            email_verified: m.email_verified,
+            linked_at: m.linked_at,
+        }
+    }
+}
+
+impl<E, C, A, M, F> AuthService<E, C, A, M, F>
+where
+    E: EmailService,
+    C: CooldownManager,
+    A: AuditLogger,
+    M: MetricsRecorder,
+    F: ConfigProvider,
+{
+    pub async fn list_identities(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<Vec<IdentityResponse>, AuthError> {
+        Ok(user_identities::Model::list_for_user(db, user.id)
+            .await?
+            .into_iter()
+            .map(IdentityResponse::from)
+            .collect())
+    }
+
+    /// Link the provider account that just authenticated to `user`
+    ///
+    /// Linking an account that is already `user`'s is a no-op; one linked
+    /// to someone else is a conflict.
+    pub async fn link_identity(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        provider: &str,
+        provider_user: &OAuthUserInfo,
+    ) -> Result<IdentityResponse, AuthError> {
+        if let Some(identity) =
+            user_identities::Model::find_by_subject(db, provider, &provider_user.subject).await?
+        {
+            if identity.user_id != user.id {
+                return Err(AuthError::conflict(
+                    "This provider account is linked to another user",
+                ));
+            }
+            return Ok(identity.into());
+        }
+
+        let identity = user_identities::Model::create(
+            db,
+            user.id,
+            provider,
+            &provider_user.subject,
+            Some(&provider_user.email),
+            provider_user.verified_email == Some(true),
+        )
+        .await?;
+
+        self.log_and_track(AuthEventType::IdentityLinked, Some(user), Some(provider))
+            .await;
+        Ok(identity.into())
+    }
+
+    /// Unlink an identity, as long as the user keeps another way to sign in
+    pub async fn unlink_identity(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        id: i32,
+    ) -> Result<(), AuthError> {
+        let identity = user_identities::Model::find_for_user(db, user.id, id)
+            .await?
+            .ok_or_else(|| AuthError::entity_not_found("Identity not found"))?;
+
+        let has_other_sign_in = user.password.is_some()
+            || user_identities::Model::count_for_user(db, user.id).await? > 1
+            || !passkey_credentials::Model::list_for_user(db, user.id)
+                .await?
+                .is_empty();
+        if !has_other_sign_in {
+            return Err(AuthError::validation(
+                "Cannot unlink the only way to sign in; set a password or link another provider first",
+            ));
+        }
+
+        let provider = identity.provider.clone();
+        identity.delete(db).await?;
+
+        self.log_and_track(AuthEventType::IdentityUnlinked, Some(user), Some(&provider))
+            .await;
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+// Passwordless login by email
+//
+// `request_magic_link` stores a SHA-256 hash of a random token in
+// `users.magic_link_token` and emails the raw token as a link.
+// `verify_magic_link` clears the hash in the same statement that matches it,
+// so each link signs in at most once, then continues like a correct password.
+
+use super::AuthService;
+use crate::auth::entities::users;
+use crate::auth::error::AuthError;
+use crate::auth::services::mfa::LoginOutcome;
+use crate::auth::traits::{
+    AuditLogger, AuthEventType, ConfigProvider, CooldownManager, CooldownType, EmailService,
+    MetricsRecorder,
+};
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use validator::Validate;
+
+pub const MAGIC_LINK_EXPIRY_MINUTES: i64 = 15;
+
+const LOGIN_FAILED_REASON: &str = "magic_link";
+
+#[derive(Deserialize, Validate, Debug, Clone, ToSchema)]
+pub struct MagicLinkRequest {
+    #[validate(email(message = "invalid email"))]
+    pub email: String,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct MagicLinkVerifyRequest {
+    pub token: String,
+}
+
+fn generate_magic_link_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash_magic_link_token(token: &str) -> String {
+    let digest = Sha256::digest(token.trim().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl<E, C, A, M, F> AuthService<E, C, A, M, F>
+where
+    E: EmailService,
+    C: CooldownManager,
+    A: AuditLogger,
+    M: MetricsRecorder,
+    F: ConfigProvider,
+{
+    /// Email a login link; unknown addresses succeed silently
+    pub async fn request_magic_link(
+        &self,
+        db: &DatabaseConnection,
+        payload: MagicLinkRequest,
+    ) -> Result<(), AuthError> {
+        payload.validate()?;
+
+        let user = match users::Model::find_by_email(db, &payload.email).await? {
+            Some(u) => u,
+            None => return Ok(()),
+        };
+
+        self.gatekeep(CooldownType::EmailMagicLink, Some(user.id))
+            .await?;
+
+        // A new link replaces any earlier one
+        let token = generate_magic_link_token();
+        let mut user_am: users::ActiveModel = user.clone().into();
+        user_am.magic_link_token = Set(Some(hash_magic_link_token(&token)));
+        user_am.magic_link_expiration = Set(Some(
+            Utc::now() + Duration::minutes(MAGIC_LINK_EXPIRY_MINUTES),
+        ));
+        user_am.update(db).await?;
+
+        let login_url = format!("{}/magic-link?token={}", self.config.frontend_url(), token);
+        self.email
+            .send_magic_link_email(
+                user.email.clone(),
+                user.name.clone(),
+                login_url,
+                MAGIC_LINK_EXPIRY_MINUTES,
+            )
+            .await;
+
+        let _ = self
+            .cooldown
+            .update_cooldown(CooldownType::EmailMagicLink, Some(user.id))
+            .await;
+        self.log_and_track(AuthEventType::MagicLinkRequest, Some(&user), None)
+            .await;
+
+        Ok(())
+    }
+
+    /// Consume a link token and log in
+    ///
+    /// Following the link proves the user controls the address, so an
+    /// unverified email is marked verified.
+    pub async fn verify_magic_link(
+        &self,
+        db: &DatabaseConnection,
+        payload: MagicLinkVerifyRequest,
+    ) -> Result<LoginOutcome, AuthError> {
+        let hash = hash_magic_link_token(&payload.token);
+        let now = Utc::now();
+
+        let user = users::Entity::find()
+            .filter(users::Column::MagicLinkToken.eq(hash.clone()))
+            .one(db)
+            .await?;
+
+        let consumed = match &user {
+            Some(user) => {
+                users::Entity::update_many()
+                    .col_expr(users::Column::MagicLinkToken, Expr::value(None::<String>))
+                    .col_expr(
+                        users::Column::MagicLinkExpiration,
+                        Expr::value(None::<chrono::DateTime<Utc>>),
+                    )
+                    .filter(users::Column::Id.eq(user.id))
+                    .filter(users::Column::MagicLinkToken.eq(hash))
+                    .filter(users::Column::MagicLinkExpiration.gt(now))
+                    .exec(db)
+                    .await?
+                    .rows_affected
+                    == 1
+            }
+            None => false,
+        };
+
+        let user = match user {
+            Some(user) if consumed => user,
+            user => {
+                self.log_and_track(
+                    AuthEventType::LoginFailed,
+                    user.as_ref(),
+                    Some(LOGIN_FAILED_REASON),
+                )
+                .await;
+                return Err(AuthError::unauthorized("Invalid or expired login link"));
+            }
+        };
+
+        let user = if user.email_verified_at.is_none() {
+            let mut user_am: users::ActiveModel = user.into();
+            user_am.email_verified_at = Set(Some(now));
+            user_am.email_verification_token = Set(None);
+            user_am.update(db).await?
+        } else {
+            user
+        };
+
+        self.complete_primary_login(db, &user).await
+    }
+}
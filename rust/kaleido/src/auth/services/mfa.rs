@@ -0,0 +1,386 @@
+// TOTP two-factor authentication
+//
+// Enrollment is two steps: `begin_totp_enrollment` stores an unconfirmed
+// secret and returns the provisioning URI, and `confirm_totp_enrollment`
+// turns it on once the user proves their authenticator works. From then on
+// `AuthService::login`, magic-link and OAuth login return an MFA challenge
+// instead of tokens, and `verify_mfa_login` exchanges the challenge plus a
+// code for tokens.
+
+use super::{AuthService, TokenResponse};
+use crate::auth::entities::{user_recovery_codes, user_totp, users};
+use crate::auth::error::AuthError;
+use crate::auth::tokens::{generate_mfa_challenge_token, verify_mfa_challenge_token};
+use crate::auth::totp;
+use crate::auth::traits::{
+    AuditLogger, AuthEventType, ConfigProvider, CooldownManager, CooldownType, EmailService,
+    MetricsRecorder,
+};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, NotSet, QueryFilter, Set,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const MFA_METHOD_TOTP: &str = "totp";
+pub const MFA_METHOD_RECOVERY_CODE: &str = "recovery_code";
+
+/// Result of a first factor: a password, magic link or OAuth sign-in
+#[derive(Debug, Clone)]
+pub enum LoginOutcome {
+    Tokens(TokenResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct MfaChallengeResponse {
+    /// Always `true`; lets clients tell this apart from a token response
+    pub mfa_required: bool,
+    /// Short-lived token for `POST /auth/mfa/verify`
+    pub mfa_token: String,
+    pub methods: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct MfaVerifyRequest {
+    pub mfa_token: String,
+    /// Current authenticator code
+    pub code: Option<String>,
+    /// Unused recovery code, instead of `code`
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct MfaCodeRequest {
+    /// Current authenticator code
+    pub code: Option<String>,
+    /// Unused recovery code, instead of `code`
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct TotpCodeRequest {
+    /// Current authenticator code
+    pub code: String,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct TotpEnrollmentResponse {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct RecoveryCodesResponse {
+    /// Shown once; only hashes are stored
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct MfaStatusResponse {
+    pub enabled: bool,
+    pub recovery_codes_remaining: u64,
+}
+
+/// Which second factor a request presented
+enum SecondFactor<'a> {
+    Totp(&'a str),
+    RecoveryCode(&'a str),
+}
+
+impl<'a> SecondFactor<'a> {
+    fn from_request(
+        code: &'a Option<String>,
+        recovery_code: &'a Option<String>,
+    ) -> Result<Self, AuthError> {
+        match (code.as_deref(), recovery_code.as_deref()) {
+            (Some(code), None) => Ok(SecondFactor::Totp(code)),
+            (None, Some(recovery_code)) => Ok(SecondFactor::RecoveryCode(recovery_code)),
+            _ => Err(AuthError::validation(
+                "Provide either code or recovery_code",
+            )),
+        }
+    }
+}
+
+impl<E, C, A, M, F> AuthService<E, C, A, M, F>
+where
+    E: EmailService,
+    C: CooldownManager,
+    A: AuditLogger,
+    M: MetricsRecorder,
+    F: ConfigProvider,
+{
+    /// Tokens after a first factor, or a challenge when the user has TOTP on
+    pub async fn complete_primary_login(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<LoginOutcome, AuthError> {
+        if user_totp::Model::find_confirmed(db, user.id)
+            .await?
+            .is_none()
+        {
+            let resp = self.issue_tokens(db, user).await?;
+            self.log_and_track(AuthEventType::LoginSucceeded, Some(user), None)
+                .await;
+            return Ok(LoginOutcome::Tokens(resp));
+        }
+
+        let mfa_token =
+            generate_mfa_challenge_token(user, self.config.jwt_keys(), self.config.token_policy())?;
+        self.log_and_track(AuthEventType::MfaChallengeIssued, Some(user), None)
+            .await;
+        Ok(LoginOutcome::MfaRequired(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token,
+            methods: vec![
+                MFA_METHOD_TOTP.to_string(),
+                MFA_METHOD_RECOVERY_CODE.to_string(),
+            ],
+        }))
+    }
+
+    /// Second login step: exchange a challenge and a code for tokens
+    pub async fn verify_mfa_login(
+        &self,
+        db: &DatabaseConnection,
+        payload: MfaVerifyRequest,
+    ) -> Result<TokenResponse, AuthError> {
+        let pid = verify_mfa_challenge_token(
+            &payload.mfa_token,
+            self.config.jwt_keys(),
+            self.config.token_policy(),
+        )?;
+        let user = users::Model::find_by_pid(db, &pid)
+            .await?
+            .ok_or_else(|| AuthError::unauthorized("Invalid or expired MFA challenge"))?;
+        let enrollment = user_totp::Model::find_confirmed(db, user.id)
+            .await?
+            .ok_or_else(|| AuthError::unauthorized("Invalid or expired MFA challenge"))?;
+
+        let factor = SecondFactor::from_request(&payload.code, &payload.recovery_code)?;
+        self.check_second_factor(db, &user, enrollment, factor)
+            .await?;
+
+        let resp = self.issue_tokens(db, &user).await?;
+        self.log_and_track(AuthEventType::LoginSucceeded, Some(&user), None)
+            .await;
+        Ok(resp)
+    }
+
+    pub async fn mfa_status(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<MfaStatusResponse, AuthError> {
+        let enabled = user_totp::Model::find_confirmed(db, user.id)
+            .await?
+            .is_some();
+        let recovery_codes_remaining = if enabled {
+            user_recovery_codes::Model::count_remaining(db, user.id).await?
+        } else {
+            0
+        };
+        Ok(MfaStatusResponse {
+            enabled,
+            recovery_codes_remaining,
+        })
+    }
+
+    /// Store a new unconfirmed secret, sealed under
+    /// `ConfigProvider::mfa_secret_key`, replacing any earlier unconfirmed one
+    pub async fn begin_totp_enrollment(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<TotpEnrollmentResponse, AuthError> {
+        let secret = totp::generate_secret();
+        let sealed = totp::seal_secret(&secret, self.config.mfa_secret_key(), user.id);
+        let now = Utc::now();
+
+        match user_totp::Model::find_by_user_id(db, user.id).await? {
+            Some(existing) if existing.is_confirmed() => {
+                return Err(AuthError::conflict(
+                    "Two-factor authentication is already enabled",
+                ));
+            }
+            Some(existing) => {
+                let mut am: user_totp::ActiveModel = existing.into();
+                am.secret = Set(sealed);
+                am.last_used_step = Set(None);
+                am.updated_at = Set(now);
+                am.update(db).await?;
+            }
+            None => {
+                user_totp::ActiveModel {
+                    id: NotSet,
+                    user_id: Set(user.id),
+                    secret: Set(sealed),
+                    confirmed_at: Set(None),
+                    last_used_step: Set(None),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                }
+                .insert(db)
+                .await?;
+            }
+        }
+
+        Ok(TotpEnrollmentResponse {
+            otpauth_uri: totp::provisioning_uri(&secret, self.config.mfa_issuer(), &user.email),
+            secret,
+        })
+    }
+
+    /// Turn TOTP on with a first valid code and issue recovery codes
+    pub async fn confirm_totp_enrollment(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AuthError> {
+        let enrollment = user_totp::Model::find_by_user_id(db, user.id)
+            .await?
+            .ok_or_else(|| AuthError::validation("Start enrollment first"))?;
+        if enrollment.is_confirmed() {
+            return Err(AuthError::conflict(
+                "Two-factor authentication is already enabled",
+            ));
+        }
+
+        let mut enrollment = self
+            .check_second_factor(db, user, enrollment, SecondFactor::Totp(code))
+            .await?;
+        enrollment.confirmed_at = Set(Some(Utc::now()));
+        enrollment.update(db).await?;
+
+        let recovery_codes = Self::replace_recovery_codes(db, user).await?;
+        self.log_and_track(AuthEventType::MfaEnabled, Some(user), None)
+            .await;
+        Ok(recovery_codes)
+    }
+
+    /// Turn TOTP off; requires a current code or a recovery code
+    pub async fn disable_totp(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        payload: MfaCodeRequest,
+    ) -> Result<(), AuthError> {
+        let enrollment = user_totp::Model::find_confirmed(db, user.id)
+            .await?
+            .ok_or_else(|| AuthError::validation("Two-factor authentication is not enabled"))?;
+        let factor = SecondFactor::from_request(&payload.code, &payload.recovery_code)?;
+        self.check_second_factor(db, user, enrollment, factor)
+            .await?;
+
+        user_totp::Entity::delete_many()
+            .filter(user_totp::Column::UserId.eq(user.id))
+            .exec(db)
+            .await?;
+        user_recovery_codes::Model::remove_for_user(db, user.id).await?;
+
+        self.log_and_track(AuthEventType::MfaDisabled, Some(user), None)
+            .await;
+        Ok(())
+    }
+
+    /// Replace all recovery codes; requires a current code
+    pub async fn regenerate_recovery_codes(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AuthError> {
+        let enrollment = user_totp::Model::find_confirmed(db, user.id)
+            .await?
+            .ok_or_else(|| AuthError::validation("Two-factor authentication is not enabled"))?;
+        self.check_second_factor(db, user, enrollment, SecondFactor::Totp(code))
+            .await?;
+
+        let recovery_codes = Self::replace_recovery_codes(db, user).await?;
+        self.log_and_track(AuthEventType::RecoveryCodesRegenerated, Some(user), None)
+            .await;
+        Ok(recovery_codes)
+    }
+
+    /// Check a code under the `MfaVerify` cooldown
+    ///
+    /// Accepted TOTP codes advance `last_used_step` and accepted recovery
+    /// codes are spent. The returned active model carries the step update
+    /// already saved, for callers that change the enrollment further.
+    async fn check_second_factor(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        enrollment: user_totp::Model,
+        factor: SecondFactor<'_>,
+    ) -> Result<user_totp::ActiveModel, AuthError> {
+        self.gatekeep(CooldownType::MfaVerify, Some(user.id))
+            .await?;
+
+        let accepted = match factor {
+            SecondFactor::Totp(code) => {
+                let secret =
+                    totp::open_secret(&enrollment.secret, self.config.mfa_secret_key(), user.id)
+                        .ok_or_else(|| {
+                            AuthError::internal_error("stored TOTP secret does not open")
+                        })?;
+                match totp::verify(
+                    &secret,
+                    code,
+                    Utc::now().timestamp(),
+                    enrollment.last_used_step,
+                ) {
+                    Some(step) => enrollment.use_step(db, step).await?.map(Into::into),
+                    None => None,
+                }
+            }
+            SecondFactor::RecoveryCode(recovery_code) => {
+                let hash = totp::hash_recovery_code(recovery_code);
+                if user_recovery_codes::Model::consume(db, user.id, &hash).await? {
+                    self.log_and_track(AuthEventType::RecoveryCodeUsed, Some(user), None)
+                        .await;
+                    Some(enrollment.into())
+                } else {
+                    None
+                }
+            }
+        };
+
+        let Some(enrollment) = accepted else {
+            let _ = self
+                .cooldown
+                .record_failure(CooldownType::MfaVerify, Some(user.id))
+                .await;
+            self.log_and_track(AuthEventType::MfaFailed, Some(user), None)
+                .await;
+            return Err(AuthError::unauthorized("Invalid authentication code"));
+        };
+
+        let _ = self
+            .cooldown
+            .reset_cooldown(CooldownType::MfaVerify, Some(user.id))
+            .await;
+        self.log_and_track(AuthEventType::MfaVerified, Some(user), None)
+            .await;
+        Ok(enrollment)
+    }
+
+    async fn replace_recovery_codes(
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<RecoveryCodesResponse, AuthError> {
+        let recovery_codes = totp::generate_recovery_codes();
+        let hashes = recovery_codes
+            .iter()
+            .map(|code| totp::hash_recovery_code(code))
+            .collect();
+        user_recovery_codes::Model::replace_for_user(db, user.id, hashes).await?;
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+}
@@ -1,18 +1,50 @@
 use crate::auth::entities::users::{self, Entity as Users};
+use crate::auth::entities::{oauth_states, user_identities};
 use crate::auth::error::AuthError;
+use crate::auth::services::oidc::{self, JwksCache};
 use crate::auth::services::provider_settings;
-use oauth2::basic::BasicClient;
+use oauth2::basic::{
+    BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
+    BasicTokenType,
+};
 use oauth2::reqwest::async_http_client;
 use oauth2::AuthType;
 use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
-    TokenResponse, TokenUrl,
+    AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken, ExtraTokenFields,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, StandardRevocableToken,
+    StandardTokenResponse, TokenResponse, TokenUrl,
 };
 use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
 use serde::{Deserialize, Serialize};
 use std::env;
 use uuid::Uuid;
 
+/// How long a user has to finish signing in at the provider
+pub const OAUTH_STATE_TTL_MINUTES: i64 = 10;
+
+const MAX_REDIRECT_PATH_LEN: usize = 512;
+
+/// The `id_token` an OIDC provider adds to the token response
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct IdTokenFields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+type OAuthTokenResponse = StandardTokenResponse<IdTokenFields, BasicTokenType>;
+
+/// `BasicClient`, except the token response keeps the `id_token`
+type OAuthClient = Client<
+    BasicErrorResponse,
+    OAuthTokenResponse,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
 #[derive(Debug, Serialize)]
 pub struct OAuthAuthorizeUrl {
     pub url: String,
@@ -46,7 +78,7 @@ pub struct OAuthService;
 impl OAuthService {
     fn create_client_from_config(
         cfg: provider_settings::ProviderConfig,
-    ) -> Result<BasicClient, AuthError> {
+    ) -> Result<OAuthClient, AuthError> {
         let auth_url = AuthUrl::new(cfg.auth_url.clone())
             .map_err(|e| AuthError::internal_error(format!("Invalid auth URL: {}", e)))?;
 
@@ -58,7 +90,7 @@ impl OAuthService {
 
         tracing::debug!(redirect_url = %cfg.redirect_url, "OAuth redirect URL configured");
 
-        Ok(BasicClient::new(
+        Ok(OAuthClient::new(
             ClientId::new(cfg.client_id),
             Some(ClientSecret::new(cfg.client_secret)),
             auth_url,
@@ -87,16 +119,29 @@ impl OAuthService {
     }
 
     /// Generate authorization URL for a named provider.
+    ///
+    /// The request carries a random `state`, a PKCE S256 challenge and an
+    /// OIDC `nonce`. Those are stored with `redirect_path` under the state,
+    /// which the callback redeems with [`Self::take_authorization_state`].
+    /// `link_user_id` makes the callback link the provider account to that
+    /// user instead of signing in.
     pub async fn get_authorization_url(
-        _db: &DatabaseConnection,
+        db: &DatabaseConnection,
         provider: &str,
         api_url: &str,
+        redirect_path: Option<String>,
+        link_user_id: Option<i32>,
     ) -> Result<OAuthAuthorizeUrl, AuthError> {
         let cfg = provider_settings::get_provider_config(provider, api_url).await?;
 
         let client = Self::create_client_from_config(cfg.clone())?;
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let nonce = CsrfToken::new_random();
 
-        let mut auth_req = client.authorize_url(CsrfToken::new_random);
+        let mut auth_req = client
+            .authorize_url(CsrfToken::new_random)
+            .set_pkce_challenge(pkce_challenge)
+            .add_extra_param("nonce", nonce.secret());
 
         for scope in cfg.scopes {
             auth_req = auth_req.add_scope(Scope::new(scope));
@@ -104,7 +149,21 @@ impl OAuthService {
 
         let (auth_url, csrf_token) = auth_req.url();
 
-        tracing::debug!(auth_url = %auth_url.to_string(), state = %csrf_token.secret().clone(), "Generated OAuth authorization URL");
+        oauth_states::Model::create(
+            db,
+            oauth_states::NewOAuthState {
+                state: csrf_token.secret(),
+                provider,
+                code_verifier: pkce_verifier.secret(),
+                nonce: nonce.secret(),
+                redirect_path,
+                link_user_id,
+            },
+            chrono::Duration::minutes(OAUTH_STATE_TTL_MINUTES),
+        )
+        .await?;
+
+        tracing::debug!(auth_url = %auth_url.to_string(), "Generated OAuth authorization URL");
 
         Ok(OAuthAuthorizeUrl {
             url: auth_url.to_string(),
@@ -112,11 +171,29 @@ impl OAuthService {
         })
     }
 
+    /// Redeem the `state` returned to the callback. Each state works once,
+    /// only for the provider it was issued for, and only until it expires.
+    pub async fn take_authorization_state(
+        db: &DatabaseConnection,
+        provider: &str,
+        state: &str,
+    ) -> Result<oauth_states::Model, AuthError> {
+        oauth_states::Model::take(db, state, provider)
+            .await?
+            .ok_or_else(|| AuthError::unauthorized("Invalid or expired OAuth state"))
+    }
+
     /// Exchange authorization code for access token and get user info for a provider.
+    ///
+    /// `authorization` is the redeemed state of the request, supplying the
+    /// PKCE verifier and the nonce. For OIDC providers the user comes from
+    /// the validated ID token; the userinfo endpoint is only called when the
+    /// provider returns no ID token or the token carries no email.
     pub async fn exchange_code_and_get_user(
         _db: &DatabaseConnection,
         provider: &str,
         code: String,
+        authorization: &oauth_states::Model,
         api_url: &str,
     ) -> Result<OAuthUserInfo, AuthError> {
         let cfg = provider_settings::get_provider_config(provider, api_url).await?;
@@ -125,19 +202,41 @@ impl OAuthService {
         // Exchange the code for an access token
         let token_result = client
             .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(PkceCodeVerifier::new(authorization.code_verifier.clone()))
             .request_async(async_http_client)
             .await
             .map_err(|e| AuthError::internal_error(format!("Token exchange failed: {}", e)))?;
 
-        let user_info: OAuthUserInfo = reqwest::Client::new()
-            .get(&cfg.userinfo_url)
-            .bearer_auth(token_result.access_token().secret())
-            .send()
-            .await
-            .map_err(|e| AuthError::internal_error(format!("Failed to fetch user info: {}", e)))?
-            .json()
-            .await
-            .map_err(|e| AuthError::internal_error(format!("Failed to parse user info: {}", e)))?;
+        let id_claims = match (&cfg.oidc, token_result.extra_fields().id_token.as_deref()) {
+            (Some(oidc_provider), Some(id_token)) => Some(
+                oidc::validate_id_token(
+                    JwksCache::global(),
+                    id_token,
+                    oidc_provider,
+                    &cfg.client_id,
+                    &authorization.nonce,
+                )
+                .await?,
+            ),
+            _ => None,
+        };
+
+        let user_info = match id_claims.as_ref().and_then(|claims| claims.user_info()) {
+            Some(user_info) => user_info,
+            None => {
+                let user_info =
+                    fetch_userinfo(&cfg.userinfo_url, token_result.access_token().secret()).await?;
+                if id_claims
+                    .as_ref()
+                    .is_some_and(|claims| claims.sub != user_info.subject)
+                {
+                    return Err(AuthError::unauthorized(
+                        "Userinfo subject does not match ID token",
+                    ));
+                }
+                user_info
+            }
+        };
 
         if user_info.verified_email == Some(false) {
             return Err(AuthError::validation("Email not verified by provider"));
@@ -147,39 +246,52 @@ impl OAuthService {
     }
 
     /// Find or create user from an OAuth/OIDC provider.
+    ///
+    /// A provider account already linked signs in as its user. Otherwise it
+    /// is linked to the user with the same email, but only if the provider
+    /// asserts that email is verified: an unverified match could belong to
+    /// anyone who typed the address at the provider.
     pub async fn find_or_create_provider_user(
         db: &DatabaseConnection,
         provider: &str,
         provider_user: OAuthUserInfo,
     ) -> Result<users::Model, AuthError> {
-        // First, try to find user by provider subject.
-        if let Some(user) = Users::find()
-            .filter(users::Column::OauthSubject.eq(&provider_user.subject))
-            .filter(users::Column::OauthProvider.eq(provider))
-            .one(db)
-            .await
-            .map_err(|e| AuthError::internal_error(format!("Database error: {}", e)))?
+        let email_verified = provider_user.verified_email == Some(true);
+
+        // First, the user this provider account is linked to.
+        if let Some(identity) =
+            user_identities::Model::find_by_subject(db, provider, &provider_user.subject).await?
         {
-            return Ok(user);
+            let identity = identity
+                .record_email(db, &provider_user.email, email_verified)
+                .await?;
+            return Users::find_by_id(identity.user_id)
+                .one(db)
+                .await?
+                .ok_or_else(|| AuthError::internal_error("Linked identity has no user"));
         }
 
-        // Second, try to find user by email and link this provider.
-        if let Some(mut user) = Users::find()
+        // Second, link to the user with this email, when both the provider
+        // and the local account have verified it.
+        if let Some(user) = Users::find()
             .filter(users::Column::Email.eq(&provider_user.email))
             .one(db)
-            .await
-            .map_err(|e| AuthError::internal_error(format!("Database error: {}", e)))?
+            .await?
         {
-            // Link provider account to existing user.
-            let mut active_user: users::ActiveModel = user.clone().into();
-            active_user.oauth_subject = Set(Some(provider_user.subject));
-            active_user.oauth_provider = Set(Some(provider.to_string()));
-
-            user = active_user
-                .update(db)
-                .await
-                .map_err(|e| AuthError::internal_error(format!("Failed to update user: {}", e)))?;
-
+            if !email_verified || user.email_verified_at.is_none() {
+                return Err(AuthError::conflict(
+                    "An account with this email already exists; sign in and link this provider from your account",
+                ));
+            }
+            user_identities::Model::create(
+                db,
+                user.id,
+                provider,
+                &provider_user.subject,
+                Some(&provider_user.email),
+                true,
+            )
+            .await?;
             return Ok(user);
         }
 
@@ -189,10 +301,8 @@ impl OAuthService {
             pid: Set(Uuid::new_v4()),
             email: Set(provider_user.email.clone()),
             password: Set(None), // OAuth users don't have a password
-            oauth_subject: Set(Some(provider_user.subject)),
-            oauth_provider: Set(Some(provider.to_string())),
             name: Set(display_name),
-            email_verified_at: Set(Some(chrono::Utc::now())), // provider verified the email
+            email_verified_at: Set(email_verified.then(chrono::Utc::now)),
             api_key: Set(Uuid::new_v4().to_string()),
             ..Default::default()
         };
@@ -201,6 +311,15 @@ impl OAuthService {
             .insert(db)
             .await
             .map_err(|e| AuthError::internal_error(format!("Failed to create user: {}", e)))?;
+        user_identities::Model::create(
+            db,
+            user.id,
+            provider,
+            &provider_user.subject,
+            Some(&provider_user.email),
+            email_verified,
+        )
+        .await?;
 
         Ok(user)
     }
@@ -219,6 +338,38 @@ impl OAuthService {
     }
 }
 
+async fn fetch_userinfo(
+    userinfo_url: &str,
+    access_token: &str,
+) -> Result<OAuthUserInfo, AuthError> {
+    reqwest::Client::new()
+        .get(userinfo_url)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .map_err(|e| AuthError::internal_error(format!("Failed to fetch user info: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| AuthError::internal_error(format!("Failed to parse user info: {}", e)))
+}
+
+/// Accept a post-login redirect only if it is a path on the frontend.
+///
+/// Rejects absolute and scheme-relative URLs (`//evil.example`), backslashes
+/// that browsers treat as slashes, and control characters, so the callback
+/// cannot be used as an open redirect.
+pub fn sanitize_redirect_path(path: &str) -> Option<String> {
+    let path = path.trim();
+    if !path.starts_with('/')
+        || path.starts_with("//")
+        || path.len() > MAX_REDIRECT_PATH_LEN
+        || path.chars().any(|c| c == '\\' || c.is_control())
+    {
+        return None;
+    }
+    Some(path.to_string())
+}
+
 fn env_or_default(name: &str, default: &str) -> String {
     env::var(name)
         .ok()
@@ -226,3 +377,26 @@ fn env_or_default(name: &str, default: &str) -> String {
         .filter(|value| !value.is_empty())
         .unwrap_or_else(|| default.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_redirect_path;
+
+    #[test]
+    fn redirect_paths_must_stay_on_the_frontend() {
+        assert_eq!(
+            sanitize_redirect_path("/settings?tab=security"),
+            Some("/settings?tab=security".to_string())
+        );
+        for rejected in [
+            "",
+            "settings",
+            "https://evil.example",
+            "//evil.example",
+            "/\\evil.example",
+            "/ok\nSet-Cookie: x=y",
+        ] {
+            assert_eq!(sanitize_redirect_path(rejected), None, "{rejected:?}");
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// OpenID Connect ID token validation
+//
+// Providers configured with `OAUTH_<P>_ISSUER_URL` return an `id_token`
+// from the code exchange. It is verified against the provider's JWKS, taken
+// from the discovery document, and its `iss`, `aud`, `exp` and `nonce` are
+// checked before the claims are trusted as the user's identity.
+//
+// JWKS documents are cached per `jwks_uri` for `JWKS_CACHE_TTL`. A token
+// signed with a `kid` the cache does not know triggers a refetch, so a
+// provider's key rotation is picked up right away; refetches are limited
+// to one per `JWKS_MIN_REFETCH_INTERVAL` so junk tokens cannot make every
+// login hit the provider.
+
+use crate::auth::error::AuthError;
+use crate::auth::services::oauth::OAuthUserInfo;
+use crate::auth::tokens::audience;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+pub const JWKS_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+pub const JWKS_MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Clock skew tolerated on `exp`, in seconds
+const LEEWAY_SECONDS: u64 = 60;
+
+/// Signing algorithms accepted for ID tokens; shared-secret `HS*` tokens
+/// would let anyone holding the client secret mint identities
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+static GLOBAL_JWKS_CACHE: Lazy<JwksCache> = Lazy::new(JwksCache::new);
+
+/// Issuer metadata needed to validate a provider's ID tokens
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    pub issuer: String,
+    pub jwks_uri: String,
+}
+
+/// Claims read from a validated ID token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    #[serde(
+        serialize_with = "audience::serialize",
+        deserialize_with = "audience::deserialize"
+    )]
+    pub aud: Vec<String>,
+    pub exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// Profile from the token alone; `None` when it carries no email, in
+    /// which case the caller falls back to the userinfo endpoint
+    pub fn user_info(&self) -> Option<OAuthUserInfo> {
+        Some(OAuthUserInfo {
+            subject: self.sub.clone(),
+            email: self.email.clone()?,
+            name: self.name.clone(),
+            preferred_username: self.preferred_username.clone(),
+            verified_email: self.email_verified,
+        })
+    }
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Provider JWKS documents keyed by `jwks_uri`
+pub struct JwksCache {
+    ttl: Duration,
+    min_refetch_interval: Duration,
+    entries: RwLock<HashMap<String, CachedJwks>>,
+}
+
+impl Default for JwksCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JwksCache {
+    pub fn new() -> Self {
+        Self {
+            ttl: JWKS_CACHE_TTL,
+            min_refetch_interval: JWKS_MIN_REFETCH_INTERVAL,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Process-wide cache used by `OAuthService`
+    pub fn global() -> &'static Self {
+        &GLOBAL_JWKS_CACHE
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_min_refetch_interval(mut self, interval: Duration) -> Self {
+        self.min_refetch_interval = interval;
+        self
+    }
+
+    /// Key named by `kid`, fetching the JWKS when it is stale or lacks the key
+    async fn key(&self, jwks_uri: &str, kid: Option<&str>) -> Result<Jwk, AuthError> {
+        {
+            let entries = self.entries.read().await;
+            if let Some(cached) = entries.get(jwks_uri) {
+                let age = cached.fetched_at.elapsed();
+                if age < self.ttl {
+                    if let Some(key) = find_key(&cached.keys, kid) {
+                        return Ok(key.clone());
+                    }
+                    if age < self.min_refetch_interval {
+                        return Err(AuthError::unauthorized("Unknown ID token signing key"));
+                    }
+                }
+            }
+        }
+
+        let keys = fetch_jwks(jwks_uri).await?;
+        let key = find_key(&keys, kid).cloned();
+        self.entries.write().await.insert(
+            jwks_uri.to_string(),
+            CachedJwks {
+                keys,
+                fetched_at: Instant::now(),
+            },
+        );
+        key.ok_or_else(|| AuthError::unauthorized("Unknown ID token signing key"))
+    }
+}
+
+/// Without a `kid`, only a single-key JWKS is unambiguous
+fn find_key<'a>(keys: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => keys.find(kid),
+        None => match keys.keys.as_slice() {
+            [only] => Some(only),
+            _ => None,
+        },
+    }
+}
+
+async fn fetch_jwks(jwks_uri: &str) -> Result<JwkSet, AuthError> {
+    tracing::debug!(jwks_uri = %jwks_uri, "Fetching OIDC provider JWKS");
+    reqwest::Client::new()
+        .get(jwks_uri)
+        .send()
+        .await
+        .map_err(|err| AuthError::internal_error(format!("JWKS request failed: {err}")))?
+        .error_for_status()
+        .map_err(|err| AuthError::internal_error(format!("JWKS request failed: {err}")))?
+        .json()
+        .await
+        .map_err(|err| AuthError::internal_error(format!("Invalid JWKS document: {err}")))
+}
+
+/// Verify an ID token's signature and its `iss`, `aud`, `exp` and `nonce`
+///
+/// `nonce` is the value sent with the authorization request. When the token
+/// names several audiences, `azp` must be `client_id`.
+pub async fn validate_id_token(
+    cache: &JwksCache,
+    id_token: &str,
+    provider: &OidcProvider,
+    client_id: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, AuthError> {
+    let invalid = |reason: &str| {
+        tracing::warn!(issuer = %provider.issuer, reason, "Rejected OIDC ID token");
+        AuthError::unauthorized("Invalid ID token")
+    };
+
+    let header = decode_header(id_token).map_err(|_| invalid("malformed header"))?;
+    if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+        return Err(invalid("disallowed algorithm"));
+    }
+    let jwk = cache.key(&provider.jwks_uri, header.kid.as_deref()).await?;
+    let key = DecodingKey::from_jwk(&jwk).map_err(|_| invalid("unusable JWK"))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = LEEWAY_SECONDS;
+    validation.set_issuer(&[provider.issuer.as_str()]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|_| invalid("signature or claims"))?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(invalid("nonce mismatch"));
+    }
+    if claims.aud.len() > 1 && claims.azp.as_deref() != Some(client_id) {
+        return Err(invalid("azp mismatch"));
+    }
+    Ok(claims)
+}
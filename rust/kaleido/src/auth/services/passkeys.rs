@@ -0,0 +1,400 @@
+// Passkey (WebAuthn) registration and login
+//
+// Each ceremony is two requests: an options call stores a single-use
+// challenge and returns the JSON for `navigator.credentials.create`/`get`,
+// and a verify call checks the browser's response against it. Passkey
+// login issues tokens directly; the authenticator already combines
+// possession with an optional PIN or biometric, so it does not go through
+// the TOTP challenge.
+
+use super::{AuthService, TokenResponse};
+use crate::auth::entities::{passkey_challenges, passkey_credentials, users};
+use crate::auth::error::AuthError;
+use crate::auth::traits::{
+    AuditLogger, AuthEventType, ConfigProvider, CooldownManager, CooldownType, EmailService,
+    MetricsRecorder,
+};
+use crate::auth::webauthn::{self, RelyingParty};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, ModelTrait, NotSet, Set};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const DEFAULT_PASSKEY_NAME: &str = "Passkey";
+pub const MAX_PASSKEY_NAME_LEN: usize = 100;
+
+const LOGIN_FAILED_REASON: &str = "passkey";
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PasskeyOptionsResponse {
+    /// Options for `navigator.credentials.create` or `navigator.credentials.get`,
+    /// in the JSON form accepted by `PublicKeyCredential.parse*OptionsFromJSON`
+    #[schema(value_type = Object)]
+    pub public_key: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct PasskeyLoginOptionsRequest {
+    /// Restrict the prompt to this account's passkeys; omit to let the
+    /// browser offer any passkey for the site
+    pub email: Option<String>,
+}
+
+/// `PublicKeyCredential.toJSON()` of a registration
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct PasskeyRegistrationCredential {
+    pub id: String,
+    pub response: PasskeyAttestationResponse,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct PasskeyAttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct PasskeyRegistrationRequest {
+    /// Label shown in the passkey list; defaults to "Passkey"
+    pub name: Option<String>,
+    pub credential: PasskeyRegistrationCredential,
+}
+
+/// `PublicKeyCredential.toJSON()` of a login
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct PasskeyAuthenticationCredential {
+    pub id: String,
+    pub response: PasskeyAssertionResponse,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct PasskeyAssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct PasskeyLoginRequest {
+    pub credential: PasskeyAuthenticationCredential,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct RenamePasskeyRequest {
+    pub name: String,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PasskeyResponse {
+    pub id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<passkey_credentials::Model> for PasskeyResponse {
+    fn from(m: passkey_credentials::Model) -> Self {
+        Self {
+            id: m.id,
+            name: m.name,
+            created_at: m.created_at,
+            last_used_at: m.last_used_at,
+        }
+    }
+}
+
+fn passkey_name(name: Option<&str>) -> Result<String, AuthError> {
+    let name = name.map(str::trim).unwrap_or(DEFAULT_PASSKEY_NAME);
+    if name.is_empty() || name.chars().count() > MAX_PASSKEY_NAME_LEN {
+        return Err(AuthError::validation(format!(
+            "Passkey name must be between 1 and {} characters",
+            MAX_PASSKEY_NAME_LEN
+        )));
+    }
+    Ok(name.to_string())
+}
+
+fn invalid_login() -> AuthError {
+    AuthError::unauthorized("Passkey login failed")
+}
+
+impl<E, C, A, M, F> AuthService<E, C, A, M, F>
+where
+    E: EmailService,
+    C: CooldownManager,
+    A: AuditLogger,
+    M: MetricsRecorder,
+    F: ConfigProvider,
+{
+    pub fn relying_party(&self) -> Result<RelyingParty, AuthError> {
+        RelyingParty::from_frontend_url(
+            self.config.frontend_url(),
+            self.config.mfa_issuer(),
+            self.config.webauthn_rp_id(),
+        )
+        .ok_or_else(|| AuthError::internal_error("Frontend URL is not a valid passkey origin"))
+    }
+
+    /// Registration options for a logged-in user
+    pub async fn begin_passkey_registration(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<PasskeyOptionsResponse, AuthError> {
+        let rp = self.relying_party()?;
+        let existing: Vec<String> = passkey_credentials::Model::list_for_user(db, user.id)
+            .await?
+            .into_iter()
+            .map(|c| c.credential_id)
+            .collect();
+
+        let challenge = webauthn::generate_challenge();
+        passkey_challenges::Model::create(
+            db,
+            &challenge,
+            passkey_challenges::PURPOSE_REGISTRATION,
+            Some(user.id),
+            Duration::seconds(webauthn::CHALLENGE_TTL_SECONDS),
+        )
+        .await?;
+
+        Ok(PasskeyOptionsResponse {
+            public_key: webauthn::registration_options(
+                &rp,
+                &challenge,
+                user.pid.as_bytes(),
+                &user.email,
+                &user.name,
+                &existing,
+            ),
+        })
+    }
+
+    /// Store the credential created from `begin_passkey_registration`
+    pub async fn finish_passkey_registration(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        payload: PasskeyRegistrationRequest,
+    ) -> Result<PasskeyResponse, AuthError> {
+        let name = passkey_name(payload.name.as_deref())?;
+        let rp = self.relying_party()?;
+        let invalid = |e: webauthn::WebauthnError| AuthError::validation(e.to_string());
+
+        let response = &payload.credential.response;
+        let client_data =
+            webauthn::decode(&response.client_data_json, "clientDataJSON").map_err(invalid)?;
+        let attestation =
+            webauthn::decode(&response.attestation_object, "attestationObject").map_err(invalid)?;
+
+        let challenge = webauthn::client_data_challenge(&client_data).map_err(invalid)?;
+        passkey_challenges::Model::take(db, &challenge, passkey_challenges::PURPOSE_REGISTRATION)
+            .await?
+            .filter(|c| c.user_id == Some(user.id))
+            .ok_or_else(|| AuthError::validation("Invalid or expired passkey challenge"))?;
+
+        let registered = webauthn::verify_registration(&rp, &challenge, &client_data, &attestation)
+            .map_err(invalid)?;
+
+        let credential_id = webauthn::encode(&registered.credential_id);
+        if passkey_credentials::Model::find_by_credential_id(db, &credential_id)
+            .await?
+            .is_some()
+        {
+            return Err(AuthError::conflict("This passkey is already registered"));
+        }
+
+        let passkey = passkey_credentials::ActiveModel {
+            id: NotSet,
+            user_id: Set(user.id),
+            credential_id: Set(credential_id),
+            public_key: Set(webauthn::encode(&registered.public_key)),
+            algorithm: Set(registered.algorithm as i32),
+            sign_count: Set(registered.sign_count as i64),
+            name: Set(name),
+            aaguid: Set(Some(uuid::Uuid::from_bytes(registered.aaguid).to_string())),
+            created_at: Set(Utc::now()),
+            last_used_at: Set(None),
+        }
+        .insert(db)
+        .await?;
+
+        self.log_and_track(AuthEventType::PasskeyRegistered, Some(user), None)
+            .await;
+        Ok(passkey.into())
+    }
+
+    /// Login options; with an email, only that account's passkeys are allowed
+    ///
+    /// Unknown emails get the same response as accounts without passkeys so
+    /// the endpoint does not reveal which emails are registered.
+    pub async fn begin_passkey_login(
+        &self,
+        db: &DatabaseConnection,
+        payload: PasskeyLoginOptionsRequest,
+    ) -> Result<PasskeyOptionsResponse, AuthError> {
+        let rp = self.relying_party()?;
+        let allow = match payload.email.as_deref() {
+            Some(email) => match users::Model::find_by_email(db, email).await? {
+                Some(user) => passkey_credentials::Model::list_for_user(db, user.id)
+                    .await?
+                    .into_iter()
+                    .map(|c| c.credential_id)
+                    .collect(),
+                None => Vec::new(),
+            },
+            None => Vec::new(),
+        };
+
+        let challenge = webauthn::generate_challenge();
+        passkey_challenges::Model::create(
+            db,
+            &challenge,
+            passkey_challenges::PURPOSE_AUTHENTICATION,
+            None,
+            Duration::seconds(webauthn::CHALLENGE_TTL_SECONDS),
+        )
+        .await?;
+
+        Ok(PasskeyOptionsResponse {
+            public_key: webauthn::authentication_options(&rp, &challenge, &allow),
+        })
+    }
+
+    /// Verify a passkey assertion and issue tokens
+    pub async fn finish_passkey_login(
+        &self,
+        db: &DatabaseConnection,
+        payload: PasskeyLoginRequest,
+    ) -> Result<TokenResponse, AuthError> {
+        let rp = self.relying_party()?;
+        let response = &payload.credential.response;
+        let client_data = webauthn::decode(&response.client_data_json, "clientDataJSON")
+            .map_err(|_| invalid_login())?;
+        let authenticator_data =
+            webauthn::decode(&response.authenticator_data, "authenticatorData")
+                .map_err(|_| invalid_login())?;
+        let signature =
+            webauthn::decode(&response.signature, "signature").map_err(|_| invalid_login())?;
+        let credential_id = webauthn::decode(&payload.credential.id, "credential id")
+            .map(|id| webauthn::encode(&id))
+            .map_err(|_| invalid_login())?;
+
+        let challenge =
+            webauthn::client_data_challenge(&client_data).map_err(|_| invalid_login())?;
+        passkey_challenges::Model::take(db, &challenge, passkey_challenges::PURPOSE_AUTHENTICATION)
+            .await?
+            .ok_or_else(invalid_login)?;
+
+        let passkey = passkey_credentials::Model::find_by_credential_id(db, &credential_id)
+            .await?
+            .ok_or_else(invalid_login)?;
+        let user = users::Entity::find_by_id(passkey.user_id)
+            .one(db)
+            .await?
+            .ok_or_else(invalid_login)?;
+
+        self.gatekeep(CooldownType::Login, Some(user.id)).await?;
+
+        let user_handle_matches = match response.user_handle.as_deref() {
+            Some(handle) => webauthn::decode(handle, "userHandle")
+                .is_ok_and(|handle| handle == user.pid.as_bytes()),
+            None => true,
+        };
+        let verified = match webauthn::decode(&passkey.public_key, "public key") {
+            Ok(public_key) if user_handle_matches => webauthn::verify_authentication(
+                &rp,
+                &challenge,
+                &client_data,
+                &authenticator_data,
+                &signature,
+                &public_key,
+                passkey.sign_count as u32,
+            )
+            .ok()
+            // A passkey signs in on its own, so the authenticator must have
+            // checked the user (PIN or biometric), not just their presence
+            .filter(|verified| verified.user_verified),
+            _ => None,
+        };
+
+        let recorded = match verified {
+            Some(verified) => passkey.record_use(db, verified.sign_count as i64).await?,
+            None => false,
+        };
+        if !recorded {
+            let _ = self
+                .cooldown
+                .record_failure(CooldownType::Login, Some(user.id))
+                .await;
+            self.log_and_track(
+                AuthEventType::LoginFailed,
+                Some(&user),
+                Some(LOGIN_FAILED_REASON),
+            )
+            .await;
+            return Err(invalid_login());
+        }
+
+        let _ = self
+            .cooldown
+            .reset_cooldown(CooldownType::Login, Some(user.id))
+            .await;
+
+        let resp = self.issue_tokens(db, &user).await?;
+        self.log_and_track(AuthEventType::LoginSucceeded, Some(&user), None)
+            .await;
+        Ok(resp)
+    }
+
+    pub async fn list_passkeys(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<Vec<PasskeyResponse>, AuthError> {
+        Ok(passkey_credentials::Model::list_for_user(db, user.id)
+            .await?
+            .into_iter()
+            .map(PasskeyResponse::from)
+            .collect())
+    }
+
+    pub async fn rename_passkey(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        id: i32,
+        payload: RenamePasskeyRequest,
+    ) -> Result<PasskeyResponse, AuthError> {
+        let name = passkey_name(Some(&payload.name))?;
+        let passkey = passkey_credentials::Model::find_for_user(db, user.id, id)
+            .await?
+            .ok_or_else(|| AuthError::entity_not_found("Passkey not found"))?;
+
+        let mut am: passkey_credentials::ActiveModel = passkey.into();
+        am.name = Set(name);
+        Ok(am.update(db).await?.into())
+    }
+
+    pub async fn delete_passkey(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        id: i32,
+    ) -> Result<(), AuthError> {
+        let passkey = passkey_credentials::Model::find_for_user(db, user.id, id)
+            .await?
+            .ok_or_else(|| AuthError::entity_not_found("Passkey not found"))?;
+        passkey.delete(db).await?;
+
+        self.log_and_track(AuthEventType::PasskeyRemoved, Some(user), None)
+            .await;
+        Ok(())
+    }
+}
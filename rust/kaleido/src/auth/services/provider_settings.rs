@@ -1,4 +1,5 @@
 use crate::auth::error::AuthError;
+use crate::auth::services::oidc::OidcProvider;
 use std::env;
 use url::Url;
 
@@ -17,6 +18,8 @@ pub struct ProviderConfig {
     pub token_url: String,
     pub userinfo_url: String,
     pub scopes: Vec<String>,
+    /// Set for providers found through OIDC discovery; their ID tokens are validated
+    pub oidc: Option<OidcProvider>,
 }
 
 pub async fn get_provider_config(name: &str, api_url: &str) -> Result<ProviderConfig, AuthError> {
@@ -47,6 +50,7 @@ pub async fn get_provider_config(name: &str, api_url: &str) -> Result<ProviderCo
         token_url: endpoints.token_url,
         userinfo_url: endpoints.userinfo_url,
         scopes: scopes_for_prefix(&prefix),
+        oidc: endpoints.oidc,
     })
 }
 
@@ -55,6 +59,7 @@ struct ProviderEndpoints {
     auth_url: String,
     token_url: String,
     userinfo_url: String,
+    oidc: Option<OidcProvider>,
 }
 
 pub fn normalize_provider_id(name: &str) -> Result<String, AuthError> {
@@ -133,6 +138,7 @@ async fn provider_endpoints(prefix: &str, provider: &str) -> Result<ProviderEndp
             auth_url,
             token_url,
             userinfo_url,
+            oidc: None,
         }),
         _ => None,
     };
@@ -154,9 +160,12 @@ async fn provider_endpoints(prefix: &str, provider: &str) -> Result<ProviderEndp
 
 #[derive(serde::Deserialize)]
 struct OidcDiscoveryDocument {
+    issuer: String,
     authorization_endpoint: String,
     token_endpoint: String,
     userinfo_endpoint: String,
+    #[serde(default)]
+    jwks_uri: Option<String>,
 }
 
 async fn discover_oidc_endpoints(issuer_url: &str) -> Result<ProviderEndpoints, AuthError> {
@@ -185,5 +194,9 @@ async fn discover_oidc_endpoints(issuer_url: &str) -> Result<ProviderEndpoints,
         auth_url: document.authorization_endpoint,
         token_url: document.token_endpoint,
         userinfo_url: document.userinfo_endpoint,
+        oidc: document.jwks_uri.map(|jwks_uri| OidcProvider {
+            issuer: document.issuer,
+            jwks_uri,
+        }),
     })
 }
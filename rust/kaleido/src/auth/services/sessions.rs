@@ -0,0 +1,186 @@
+// Signed-in sessions
+//
+// A session is a refresh token family: the token issued at sign-in and
+// every token rotated from it. Sessions are addressed by `family_id`, which
+// stays the same across refreshes, and revoking one deletes the family, so
+// its refresh cookie stops working. Access tokens already issued stay valid
+// until they expire, unless the service has a revocation store.
+
+use super::AuthService;
+use crate::auth::entities::{refresh_tokens, users};
+use crate::auth::error::AuthError;
+use crate::auth::traits::{
+    AuditLogger, AuthEventType, ConfigProvider, CooldownManager, EmailService, MetricsRecorder,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const MAX_SESSION_LABEL_LEN: usize = 100;
+pub const MAX_USER_AGENT_LEN: usize = 512;
+pub const MAX_IP_LEN: usize = 64;
+
+/// Where a sign-in or refresh request came from
+#[derive(Debug, Clone, Default)]
+pub struct SessionClient {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct SessionResponse {
+    pub id: String,
+    pub label: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Whether this is the session making the request; only known when the
+    /// request carries the refresh cookie
+    pub current: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct RenameSessionRequest {
+    /// Omit or send an empty string to clear the label
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct SessionsRevokedResponse {
+    /// Number of sessions signed out
+    pub revoked: u64,
+}
+
+impl<E, C, A, M, F> AuthService<E, C, A, M, F>
+where
+    E: EmailService,
+    C: CooldownManager,
+    A: AuditLogger,
+    M: MetricsRecorder,
+    F: ConfigProvider,
+{
+    /// Store the client details of the session `refresh_token` was issued to
+    pub async fn record_session_client(
+        &self,
+        db: &DatabaseConnection,
+        refresh_token: &str,
+        client: SessionClient,
+    ) -> Result<(), AuthError> {
+        let ip = client.ip.map(|ip| ip.chars().take(MAX_IP_LEN).collect());
+        let user_agent = client
+            .user_agent
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        refresh_tokens::Model::record_client(
+            db,
+            &self.refresh_token_hash(refresh_token),
+            ip,
+            user_agent,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// The user's sessions, most recently signed in first
+    ///
+    /// `current_token` is the refresh token of the request, if any, and marks
+    /// its session as current.
+    pub async fn list_sessions(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        current_token: Option<&str>,
+    ) -> Result<Vec<SessionResponse>, AuthError> {
+        let tokens = refresh_tokens::Model::list_live_for_user(db, user.pid).await?;
+        let current_family = match current_token {
+            Some(current) => refresh_tokens::Entity::find_by_id(self.refresh_token_hash(current))
+                .one(db)
+                .await?
+                .and_then(|t| t.family_id),
+            None => None,
+        };
+
+        let mut seen = HashSet::new();
+        let mut sessions = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let Some(id) = token.family_id else { continue };
+            // Siblings issued inside the reuse grace window are one session
+            if !seen.insert(id) {
+                continue;
+            }
+            sessions.push(SessionResponse {
+                id: id.to_string(),
+                label: token.label,
+                ip: token.ip,
+                user_agent: token.user_agent,
+                created_at: token.created_at,
+                last_used_at: token.last_used_at,
+                current: current_family == Some(id),
+            });
+        }
+        Ok(sessions)
+    }
+
+    pub async fn rename_session(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        id: Uuid,
+        payload: RenameSessionRequest,
+    ) -> Result<(), AuthError> {
+        let label = payload
+            .label
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty());
+        if label
+            .as_ref()
+            .is_some_and(|l| l.chars().count() > MAX_SESSION_LABEL_LEN)
+        {
+            return Err(AuthError::validation(format!(
+                "Session label must be at most {} characters",
+                MAX_SESSION_LABEL_LEN
+            )));
+        }
+
+        if refresh_tokens::Model::label_session(db, user.pid, id, label).await? == 0 {
+            return Err(AuthError::entity_not_found("Session not found"));
+        }
+        Ok(())
+    }
+
+    /// Sign one of the user's sessions out
+    pub async fn revoke_session(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        id: Uuid,
+    ) -> Result<(), AuthError> {
+        let tokens = refresh_tokens::Model::find_for_user(db, user.pid, Some(id)).await?;
+        self.revoke_access_tokens(db, &tokens).await?;
+        if refresh_tokens::Model::revoke_session(db, user.pid, id).await? == 0 {
+            return Err(AuthError::entity_not_found("Session not found"));
+        }
+
+        self.log_and_track(AuthEventType::SessionRevoked, Some(user), None)
+            .await;
+        Ok(())
+    }
+
+    /// Sign the user out everywhere, including the session making the request
+    pub async fn revoke_all_sessions(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> Result<SessionsRevokedResponse, AuthError> {
+        let tokens = refresh_tokens::Model::find_for_user(db, user.pid, None).await?;
+        self.revoke_access_tokens(db, &tokens).await?;
+        let revoked = refresh_tokens::Model::revoke_all_sessions(db, user.pid).await?;
+
+        self.log_and_track(AuthEventType::SessionRevoked, Some(user), Some("all"))
+            .await;
+        Ok(SessionsRevokedResponse { revoked })
+    }
+}
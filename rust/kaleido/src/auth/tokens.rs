@@ -1,8 +1,11 @@
 use crate::auth::entities::{api_clients, users};
 use crate::auth::error::AuthError;
+use crate::auth::jwt_keys::JwtKeys;
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{TokenData, Validation};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -10,14 +13,171 @@ use uuid::Uuid;
 pub enum TokenType {
     User,
     ApiClient,
+    /// Proof of a correct password while a second factor is still pending
+    MfaChallenge,
 }
 
 pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 30;
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 7;
+pub const MFA_CHALLENGE_TTL_MINUTES: i64 = 5;
+/// `aud` of MFA challenge tokens, which access token verification rejects
+pub const MFA_CHALLENGE_AUDIENCE: &str = "kaleido:mfa_challenge";
+/// How long a rotated refresh token is kept, past the reuse grace window, so
+/// presenting it again is still caught as reuse
+pub const REFRESH_REUSE_DETECTION_HOURS: i64 = 24;
 
+/// Default access token lifetime; see [`TokenPolicy::access_token_ttl_seconds`]
 pub fn access_token_ttl_seconds() -> i64 {
     ACCESS_TOKEN_TTL_MINUTES * 60
 }
 
+/// Claims written by Kaleido, which custom claims cannot replace
+pub const RESERVED_CLAIMS: &[&str] = &[
+    "sub",
+    "exp",
+    "iat",
+    "nbf",
+    "jti",
+    "iss",
+    "aud",
+    "token_type",
+];
+
+/// Extra claims added to a user's access tokens, see
+/// [`AuthService::with_claims_hook`](crate::auth::services::AuthService::with_claims_hook)
+pub type CustomClaims = serde_json::Map<String, serde_json::Value>;
+
+/// Issuer, audiences and lifetimes of issued tokens
+///
+/// Issuing (`ConfigProvider::token_policy`) and verifying
+/// (`AuthStorage::token_policy`) must use the same policy.
+#[derive(Debug, Clone)]
+pub struct TokenPolicy {
+    /// Written to `iss` and required when verifying
+    pub issuer: Option<String>,
+    /// Written to `aud`; verification accepts a token naming any of them
+    pub audiences: Vec<String>,
+    pub access_token_ttl_seconds: i64,
+    pub refresh_token_ttl_seconds: i64,
+}
+
+impl Default for TokenPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static DEFAULT_TOKEN_POLICY: TokenPolicy = TokenPolicy::new();
+
+impl TokenPolicy {
+    /// No issuer or audience, 30 minute access tokens, 7 day refresh tokens
+    pub const fn new() -> Self {
+        Self {
+            issuer: None,
+            audiences: Vec::new(),
+            access_token_ttl_seconds: ACCESS_TOKEN_TTL_MINUTES * 60,
+            refresh_token_ttl_seconds: REFRESH_TOKEN_TTL_DAYS * 24 * 60 * 60,
+        }
+    }
+
+    /// The policy used when an app does not configure one
+    pub fn default_ref() -> &'static TokenPolicy {
+        &DEFAULT_TOKEN_POLICY
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    pub fn with_access_token_ttl_seconds(mut self, seconds: i64) -> Self {
+        self.access_token_ttl_seconds = seconds;
+        self
+    }
+
+    pub fn with_refresh_token_ttl_seconds(mut self, seconds: i64) -> Self {
+        self.refresh_token_ttl_seconds = seconds;
+        self
+    }
+
+    /// Reads `JWT_ISSUER`, `JWT_AUDIENCE` (comma-separated),
+    /// `ACCESS_TOKEN_TTL_SECONDS` and `REFRESH_TOKEN_TTL_SECONDS`
+    pub fn from_env() -> Self {
+        let defaults = Self::new();
+        let seconds = |name: &str, default: i64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<i64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            issuer: std::env::var("JWT_ISSUER")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            audiences: std::env::var("JWT_AUDIENCE")
+                .unwrap_or_default()
+                .split(',')
+                .map(|a| a.trim().to_string())
+                .filter(|a| !a.is_empty())
+                .collect(),
+            access_token_ttl_seconds: seconds(
+                "ACCESS_TOKEN_TTL_SECONDS",
+                defaults.access_token_ttl_seconds,
+            ),
+            refresh_token_ttl_seconds: seconds(
+                "REFRESH_TOKEN_TTL_SECONDS",
+                defaults.refresh_token_ttl_seconds,
+            ),
+        }
+    }
+
+    /// Claim checks for tokens issued under this policy
+    ///
+    /// With no audiences configured, a token naming any audience is rejected,
+    /// so MFA challenge tokens never pass as access tokens.
+    pub fn validation(&self) -> Validation {
+        self.validation_for(&self.audiences)
+    }
+
+    fn validation_for(&self, audiences: &[String]) -> Validation {
+        let mut validation = Validation::default();
+        validation.validate_nbf = true;
+        let mut required = vec!["exp"];
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            required.push("iss");
+        }
+        if !audiences.is_empty() {
+            validation.set_audience(audiences);
+            required.push("aud");
+        }
+        validation.set_required_spec_claims(&required);
+        validation
+    }
+
+    fn claims(&self, sub: String, token_type: TokenType, ttl_seconds: i64) -> Claims {
+        let now = Utc::now();
+        Claims {
+            sub,
+            exp: (now + Duration::seconds(ttl_seconds)).timestamp(),
+            iat: now.timestamp(),
+            nbf: now.timestamp(),
+            jti: Some(Uuid::new_v4().to_string()),
+            iss: self.issuer.clone(),
+            aud: self.audiences.clone(),
+            token_type,
+            custom: CustomClaims::new(),
+        }
+    }
+}
+
 fn default_token_type() -> TokenType {
     TokenType::User
 }
@@ -26,73 +186,183 @@ fn default_token_type() -> TokenType {
 pub struct Claims {
     pub sub: String,
     pub exp: i64,
+    /// Zero on tokens issued before these claims existed
+    #[serde(default)]
+    pub iat: i64,
+    #[serde(default)]
+    pub nbf: i64,
+    /// Unique token id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "audience::serialize",
+        deserialize_with = "audience::deserialize"
+    )]
+    pub aud: Vec<String>,
     #[serde(default = "default_token_type")]
     pub token_type: TokenType,
+    /// App claims from the claims hook
+    #[serde(flatten)]
+    pub custom: CustomClaims,
+}
+
+/// `aud` is a string for one audience and an array for several (RFC 7519)
+pub(crate) mod audience {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(aud: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        match aud {
+            [single] => single.serialize(serializer),
+            many => many.serialize(serializer),
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(aud) => vec![aud],
+            OneOrMany::Many(aud) => aud,
+        })
+    }
 }
 
-/// Configuration trait for JWT secret
+/// Configuration trait for JWT keys
 pub trait JwtConfig {
-    fn jwt_secret(&self) -> String;
+    fn jwt_keys(&self) -> &JwtKeys;
 }
 
+/// Access token for `user`, with `custom` merged in, and its claims
+///
+/// Custom claims named in [`RESERVED_CLAIMS`] are dropped.
 pub fn generate_access_token(
     user: &users::Model,
-    secret: &str,
-) -> Result<String, jsonwebtoken::errors::Error> {
-    let exp = Utc::now()
-        .checked_add_signed(Duration::minutes(ACCESS_TOKEN_TTL_MINUTES))
-        .unwrap()
-        .timestamp();
-    let claims = Claims {
-        sub: user.pid.to_string(),
-        exp,
-        token_type: TokenType::User,
-    };
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )?;
-    Ok(token)
+    keys: &JwtKeys,
+    policy: &TokenPolicy,
+    custom: CustomClaims,
+) -> Result<(String, Claims), jsonwebtoken::errors::Error> {
+    let mut claims = policy.claims(
+        user.pid.to_string(),
+        TokenType::User,
+        policy.access_token_ttl_seconds,
+    );
+    for (name, value) in custom {
+        if RESERVED_CLAIMS.contains(&name.as_str()) {
+            tracing::warn!(claim = %name, "ignoring custom claim that would replace a reserved claim");
+            continue;
+        }
+        claims.custom.insert(name, value);
+    }
+    Ok((keys.encode(&claims)?, claims))
 }
 
 pub fn generate_api_client_access_token(
     client: &api_clients::Model,
-    secret: &str,
+    keys: &JwtKeys,
+    policy: &TokenPolicy,
 ) -> Result<String, jsonwebtoken::errors::Error> {
-    let exp = Utc::now()
-        .checked_add_signed(Duration::minutes(ACCESS_TOKEN_TTL_MINUTES))
-        .unwrap()
-        .timestamp();
-    let claims = Claims {
-        sub: client.client_id.to_string(),
-        exp,
-        token_type: TokenType::ApiClient,
-    };
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
+    let claims = policy.claims(
+        client.client_id.to_string(),
+        TokenType::ApiClient,
+        policy.access_token_ttl_seconds,
+    );
+    keys.encode(&claims)
+}
+
+/// Short-lived proof of a correct password
+///
+/// Its only audience is [`MFA_CHALLENGE_AUDIENCE`], so it never passes
+/// [`TokenPolicy::validation`] as an access token.
+pub fn generate_mfa_challenge_token(
+    user: &users::Model,
+    keys: &JwtKeys,
+    policy: &TokenPolicy,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    keys.encode(&mfa_challenge_claims(user.pid, policy))
+}
+
+fn mfa_challenge_claims(pid: Uuid, policy: &TokenPolicy) -> Claims {
+    let mut claims = policy.claims(
+        pid.to_string(),
+        TokenType::MfaChallenge,
+        MFA_CHALLENGE_TTL_MINUTES * 60,
+    );
+    claims.aud = vec![MFA_CHALLENGE_AUDIENCE.to_string()];
+    claims
+}
+
+/// User pid of a valid, unexpired MFA challenge token
+pub fn verify_mfa_challenge_token(
+    token: &str,
+    keys: &JwtKeys,
+    policy: &TokenPolicy,
+) -> Result<Uuid, AuthError> {
+    let td = verify_token(
+        token,
+        keys,
+        policy.validation_for(&[MFA_CHALLENGE_AUDIENCE.to_string()]),
     )
+    .map_err(|_| AuthError::unauthorized("Invalid or expired MFA challenge"))?;
+    if td.claims.token_type != TokenType::MfaChallenge {
+        return Err(AuthError::unauthorized("Invalid or expired MFA challenge"));
+    }
+    Uuid::parse_str(&td.claims.sub)
+        .map_err(|_| AuthError::unauthorized("Invalid or expired MFA challenge"))
 }
 
-pub fn generate_refresh_token() -> (String, i64) {
+/// Value stored in `refresh_tokens.token` for a refresh token
+///
+/// Keyed, so a copy of the table alone cannot be turned back into cookies.
+pub fn hash_refresh_token(token: &str, key: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(token.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn generate_refresh_token(policy: &TokenPolicy) -> (String, i64) {
     let exp = Utc::now()
-        .checked_add_signed(Duration::days(7))
+        .checked_add_signed(Duration::seconds(policy.refresh_token_ttl_seconds))
         .unwrap()
         .timestamp();
     let token = Uuid::new_v4().to_string();
     (token, exp)
 }
 
-pub fn verify_access_token(token: &str, secret: &str) -> Result<TokenData<Claims>, AuthError> {
-    let validation = Validation::default();
-    let td = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &validation,
-    )
-    .map_err(|_| AuthError::unauthorized("Invalid token"))?;
+/// Verify an access token's signature and the claims `policy` requires
+pub fn verify_access_token(
+    token: &str,
+    keys: &JwtKeys,
+    policy: &TokenPolicy,
+) -> Result<TokenData<Claims>, AuthError> {
+    let td = verify_token(token, keys, policy.validation())?;
+    if td.claims.token_type == TokenType::MfaChallenge {
+        return Err(AuthError::unauthorized("Invalid token"));
+    }
+    Ok(td)
+}
+
+fn verify_token(
+    token: &str,
+    keys: &JwtKeys,
+    validation: Validation,
+) -> Result<TokenData<Claims>, AuthError> {
+    let td = keys.decode::<Claims>(token, validation)?;
 
     // Enforce expiry explicitly
     let now = Utc::now().timestamp();
@@ -102,3 +372,72 @@ pub fn verify_access_token(token: &str, secret: &str) -> Result<TokenData<Claims
 
     Ok(td)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> JwtKeys {
+        JwtKeys::hs256("test-secret")
+    }
+
+    fn encode(claims: serde_json::Value) -> String {
+        keys().encode(&claims).unwrap()
+    }
+
+    #[test]
+    fn test_audience_is_a_string_for_one_and_an_array_for_several() {
+        let mut claims = TokenPolicy::default().with_audience("api").claims(
+            "sub".to_string(),
+            TokenType::User,
+            60,
+        );
+        assert_eq!(serde_json::to_value(&claims).unwrap()["aud"], "api");
+
+        claims.aud.push("billing".to_string());
+        let value = serde_json::to_value(&claims).unwrap();
+        assert_eq!(value["aud"], serde_json::json!(["api", "billing"]));
+        let back: Claims = serde_json::from_value(value).unwrap();
+        assert_eq!(back.aud, vec!["api".to_string(), "billing".to_string()]);
+    }
+
+    #[test]
+    fn test_configured_issuer_and_audience_are_required() {
+        let policy = TokenPolicy::default()
+            .with_issuer("kaleido")
+            .with_audience("api");
+        let exp = Utc::now().timestamp() + 60;
+
+        let bare = encode(serde_json::json!({ "sub": "u", "exp": exp }));
+        assert!(verify_access_token(&bare, &keys(), &policy).is_err());
+        assert!(verify_access_token(&bare, &keys(), &TokenPolicy::default()).is_ok());
+
+        let future = encode(serde_json::json!({
+            "sub": "u", "exp": exp + 600, "nbf": exp + 300, "iss": "kaleido", "aud": "api"
+        }));
+        assert!(verify_access_token(&future, &keys(), &policy).is_err());
+    }
+
+    #[test]
+    fn test_mfa_challenge_is_not_an_access_token() {
+        for policy in [
+            TokenPolicy::default(),
+            TokenPolicy::default().with_audience("api"),
+        ] {
+            let pid = Uuid::new_v4();
+            let token = keys().encode(&mfa_challenge_claims(pid, &policy)).unwrap();
+
+            assert!(verify_access_token(&token, &keys(), &policy).is_err());
+            // Nor with the token type claim stripped
+            let mut claims = mfa_challenge_claims(pid, &policy);
+            claims.token_type = TokenType::User;
+            let untyped = keys().encode(&claims).unwrap();
+            assert!(verify_access_token(&untyped, &keys(), &policy).is_err());
+
+            assert_eq!(
+                verify_mfa_challenge_token(&token, &keys(), &policy).unwrap(),
+                pid
+            );
+        }
+    }
+}
@@ -0,0 +1,227 @@
+// Time-based one-time passwords (RFC 6238) and recovery codes
+//
+// Secrets are 160-bit, base32 encoded without padding, and codes are six
+// digits over 30 second steps with HMAC-SHA1 - the defaults every common
+// authenticator app assumes. Stored secrets are sealed with AES-256-GCM
+// under a configured key.
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+pub const STEP_SECONDS: i64 = 30;
+pub const DIGITS: u32 = 6;
+/// Steps accepted either side of the current one, to absorb clock drift
+pub const ALLOWED_DRIFT_STEPS: i64 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Prefix of sealed secrets; base32 secrets never contain `:`
+const SEALED_PREFIX: &str = "v1:";
+const NONCE_LEN: usize = 12;
+
+/// New random secret, base32 encoded
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+fn secret_cipher(key: &str) -> Aes256Gcm {
+    let key = Sha256::digest(key.as_bytes());
+    <Aes256Gcm as aes_gcm::KeyInit>::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+fn secret_aad(user_id: i32) -> String {
+    format!("user_totp:{}", user_id)
+}
+
+/// Value stored in `user_totp.secret` for `secret`
+///
+/// Bound to `user_id`, so a sealed secret copied to another user's row does
+/// not open.
+pub fn seal_secret(secret: &str, key: &str, user_id: i32) -> String {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+    let aad = secret_aad(user_id);
+    let ciphertext = secret_cipher(key)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: secret.as_bytes(),
+                aad: aad.as_bytes(),
+            },
+        )
+        .expect("AES-GCM encryption of a short secret cannot fail");
+    format!(
+        "{}{}:{}",
+        SEALED_PREFIX,
+        STANDARD.encode(nonce),
+        STANDARD.encode(ciphertext)
+    )
+}
+
+/// Secret from a `user_totp.secret` value, or `None` if it does not open
+/// under `key`
+///
+/// Values stored before secrets were sealed are returned as they are.
+pub fn open_secret(stored: &str, key: &str, user_id: i32) -> Option<String> {
+    let Some(sealed) = stored.strip_prefix(SEALED_PREFIX) else {
+        return Some(stored.to_string());
+    };
+    let (nonce, ciphertext) = sealed.split_once(':')?;
+    let nonce = STANDARD
+        .decode(nonce)
+        .ok()
+        .filter(|nonce| nonce.len() == NONCE_LEN)?;
+    let ciphertext = STANDARD.decode(ciphertext).ok()?;
+    let aad = secret_aad(user_id);
+    let secret = secret_cipher(key)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: aad.as_bytes(),
+            },
+        )
+        .ok()?;
+    String::from_utf8(secret).ok()
+}
+
+/// `otpauth://` URI for QR provisioning
+pub fn provisioning_uri(secret: &str, issuer: &str, account: &str) -> String {
+    let mut uri = url::Url::parse("otpauth://totp/").expect("static otpauth URI is valid");
+    uri.path_segments_mut()
+        .expect("otpauth URI has a path")
+        .pop_if_empty()
+        .push(&format!("{}:{}", issuer, account));
+    uri.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &STEP_SECONDS.to_string());
+    uri.to_string()
+}
+
+pub fn step_at(unix_seconds: i64) -> i64 {
+    unix_seconds.div_euclid(STEP_SECONDS)
+}
+
+/// Code for `step`, or `None` when `secret` is not valid base32
+pub fn code_at(secret: &str, step: i64) -> Option<String> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    ))
+}
+
+/// Step that `code` matches at `unix_seconds`, if any
+///
+/// Steps at or before `last_used_step` are rejected so an observed code
+/// cannot be replayed within its window.
+pub fn verify(
+    secret: &str,
+    code: &str,
+    unix_seconds: i64,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = step_at(unix_seconds);
+    (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| code_at(secret, *step).as_deref() == Some(code))
+}
+
+/// Fresh recovery codes, formatted `xxxxx-xxxxx`
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 7];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let code = BASE32_NOPAD.encode(&bytes).to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..10])
+        })
+        .collect()
+}
+
+/// Hash stored for a recovery code; ignores case, dashes and whitespace
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let digest = Sha256::digest(normalized.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc6238_sha1_vectors() {
+        // RFC 6238 appendix B secret "12345678901234567890", last six digits
+        let secret = BASE32_NOPAD.encode(b"12345678901234567890");
+        assert_eq!(code_at(&secret, step_at(59)).unwrap(), "287082");
+        assert_eq!(code_at(&secret, step_at(1111111109)).unwrap(), "081804");
+        assert_eq!(code_at(&secret, step_at(2000000000)).unwrap(), "279037");
+
+        assert_eq!(verify(&secret, "081804", 1111111109, None), Some(37037036));
+        // Accepted one step late, but never twice
+        assert_eq!(
+            verify(&secret, "081804", 1111111109 + 30, None),
+            Some(37037036)
+        );
+        assert_eq!(verify(&secret, "081804", 1111111109, Some(37037036)), None);
+        assert_eq!(verify(&secret, "12345", 1111111109, None), None);
+    }
+
+    #[test]
+    fn test_recovery_code_hash_is_normalized() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(
+            hash_recovery_code(&codes[0]),
+            hash_recovery_code(&codes[0].replace('-', "").to_uppercase())
+        );
+        assert_ne!(hash_recovery_code(&codes[0]), hash_recovery_code(&codes[1]));
+    }
+
+    #[test]
+    fn test_sealed_secret_opens_only_with_its_key_and_user() {
+        let secret = generate_secret();
+        let sealed = seal_secret(&secret, "key", 7);
+        assert!(!sealed.contains(&secret));
+
+        assert_eq!(open_secret(&sealed, "key", 7), Some(secret.clone()));
+        assert_eq!(open_secret(&sealed, "other", 7), None);
+        assert_eq!(open_secret(&sealed, "key", 8), None);
+        // Rows written before sealing still verify
+        assert_eq!(open_secret(&secret, "key", 7), Some(secret));
+    }
+}
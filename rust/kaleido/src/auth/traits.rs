@@ -3,6 +3,8 @@
 // These traits allow the auth crate to remain independent of specific
 // implementations while still providing full authentication functionality.
 
+use crate::auth::jwt_keys::JwtKeys;
+use crate::auth::tokens::TokenPolicy;
 use async_trait::async_trait;
 
 /// Email service for sending authentication-related emails
@@ -19,6 +21,20 @@ pub trait EmailService: Send + Sync + Clone {
         reset_url: String,
         expiry_hours: i64,
     );
+
+    /// Send a passwordless login link
+    ///
+    /// Services that predate magic-link login drop the email with a warning.
+    async fn send_magic_link_email(
+        &self,
+        email: String,
+        name: String,
+        login_url: String,
+        expiry_minutes: i64,
+    ) {
+        let _ = (email, name, login_url, expiry_minutes);
+        tracing::warn!("EmailService does not implement send_magic_link_email; email dropped");
+    }
 }
 
 /// Cooldown/rate limiting service
@@ -58,6 +74,10 @@ pub enum CooldownType {
     Login,
     EmailResend,
     EmailForgotPassword,
+    /// Second-factor code attempts, keyed by user
+    MfaVerify,
+    /// Magic-link login emails, keyed by user
+    EmailMagicLink,
 }
 
 #[derive(Debug)]
@@ -84,8 +104,23 @@ pub enum AuthEventType {
     Logout,
     TokenRefresh,
     TokenRefreshFailed,
+    /// A rotated refresh token was presented again; its family was revoked
+    TokenReuseDetected,
     PasswordResetRequest,
     PasswordReset,
+    MfaChallengeIssued,
+    MfaVerified,
+    MfaFailed,
+    MfaEnabled,
+    MfaDisabled,
+    RecoveryCodeUsed,
+    RecoveryCodesRegenerated,
+    PasskeyRegistered,
+    PasskeyRemoved,
+    MagicLinkRequest,
+    SessionRevoked,
+    IdentityLinked,
+    IdentityUnlinked,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -115,8 +150,41 @@ pub trait ConfigProvider: Send + Sync + Clone {
     /// Get the frontend URL for generating email links
     fn frontend_url(&self) -> &str;
 
-    /// Get the JWT secret for token generation
-    fn jwt_secret(&self) -> &str;
+    /// Keys for signing access tokens; must match `AuthStorage::jwt_keys`
+    fn jwt_keys(&self) -> &JwtKeys;
+
+    /// Key for the HMAC under which refresh tokens are stored; must match
+    /// `AuthStorage::refresh_token_key`
+    fn refresh_token_key(&self) -> &str;
+
+    /// Issuer, audiences and lifetimes of issued tokens; must match
+    /// `AuthStorage::token_policy`
+    fn token_policy(&self) -> &TokenPolicy {
+        TokenPolicy::default_ref()
+    }
+
+    /// Key sealing TOTP secrets in `user_totp`; defaults to
+    /// `refresh_token_key`. Changing it makes existing enrollments unusable.
+    fn mfa_secret_key(&self) -> &str {
+        self.refresh_token_key()
+    }
+
+    /// Issuer shown next to the account in authenticator apps
+    fn mfa_issuer(&self) -> &str {
+        "Kaleido"
+    }
+
+    /// WebAuthn relying party id for passkeys; defaults to the frontend host
+    fn webauthn_rp_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Seconds after rotation during which a refresh token may be presented
+    /// again, e.g. by several tabs refreshing at once, without counting as
+    /// reuse. Zero disables the window.
+    fn refresh_reuse_grace_seconds(&self) -> i64 {
+        0
+    }
 }
 
 /// No-op implementations for optional features
@@ -127,6 +195,7 @@ pub struct NoOpEmailService;
 impl EmailService for NoOpEmailService {
     async fn send_registration_email(&self, _: String, _: String, _: String) {}
     async fn send_password_reset_email(&self, _: String, _: String, _: String, _: i64) {}
+    async fn send_magic_link_email(&self, _: String, _: String, _: String, _: i64) {}
 }
 
 #[derive(Clone)]
@@ -0,0 +1,726 @@
+// WebAuthn (passkey) ceremony verification
+//
+// Relying-party checks from the WebAuthn Level 2 spec, kept free of storage
+// so they can be driven by a software authenticator in tests. Registration
+// asks for `none` attestation, so the attestation statement is not checked:
+// a passkey proves possession of its key, not the make of the device.
+// Challenges and credentials are stored by `AuthService` in
+// `services/passkeys.rs`.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ciborium::value::Value;
+use rand::RngCore;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+pub const COSE_ALG_ES256: i64 = -7;
+pub const COSE_ALG_EDDSA: i64 = -8;
+pub const COSE_ALG_RS256: i64 = -257;
+/// Algorithms offered at registration, most preferred first
+pub const SUPPORTED_ALGORITHMS: [i64; 3] = [COSE_ALG_ES256, COSE_ALG_EDDSA, COSE_ALG_RS256];
+
+/// How long a ceremony may take between options and verify
+pub const CHALLENGE_TTL_SECONDS: i64 = 300;
+
+const CLIENT_DATA_CREATE: &str = "webauthn.create";
+const CLIENT_DATA_GET: &str = "webauthn.get";
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+
+/// Spec limit on credential id length
+const MAX_CREDENTIAL_ID_LEN: usize = 1023;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WebauthnError {
+    #[error("Malformed {0}")]
+    Malformed(&'static str),
+    #[error("Unexpected ceremony type")]
+    CeremonyType,
+    #[error("Challenge does not match")]
+    Challenge,
+    #[error("Origin is not allowed")]
+    Origin,
+    #[error("Credential was created for a different relying party")]
+    RpId,
+    #[error("User presence was not asserted")]
+    UserNotPresent,
+    #[error("Unsupported algorithm {0}")]
+    UnsupportedAlgorithm(i64),
+    #[error("Invalid signature")]
+    Signature,
+    #[error("Signature counter went backwards; the authenticator may be cloned")]
+    CounterRegression,
+}
+
+/// The site passkeys are scoped to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelyingParty {
+    /// Registrable domain, e.g. `example.com`
+    pub id: String,
+    /// Display name shown by the browser
+    pub name: String,
+    /// Exact origin ceremonies must come from, e.g. `https://app.example.com`
+    pub origin: String,
+}
+
+impl RelyingParty {
+    /// Origin and id from the frontend URL; `id` overrides the host, e.g. to
+    /// share passkeys across subdomains
+    pub fn from_frontend_url(frontend_url: &str, name: &str, id: Option<&str>) -> Option<Self> {
+        let url = url::Url::parse(frontend_url).ok()?;
+        let origin = url.origin();
+        if !origin.is_tuple() {
+            return None;
+        }
+        Some(Self {
+            id: id.or(url.host_str())?.to_string(),
+            name: name.to_string(),
+            origin: origin.ascii_serialization(),
+        })
+    }
+}
+
+/// Credential accepted by `verify_registration`
+#[derive(Debug, Clone)]
+pub struct VerifiedRegistration {
+    pub credential_id: Vec<u8>,
+    /// COSE_Key exactly as the authenticator sent it
+    pub public_key: Vec<u8>,
+    pub algorithm: i64,
+    pub sign_count: u32,
+    pub aaguid: [u8; 16],
+    pub user_verified: bool,
+}
+
+/// Assertion accepted by `verify_authentication`
+#[derive(Debug, Clone, Copy)]
+pub struct VerifiedAuthentication {
+    pub sign_count: u32,
+    pub user_verified: bool,
+}
+
+pub fn generate_challenge() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    encode(&bytes)
+}
+
+/// base64url without padding, as used throughout WebAuthn JSON
+pub fn encode(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Decode base64url, tolerating padding some clients add
+pub fn decode(value: &str, what: &'static str) -> Result<Vec<u8>, WebauthnError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| WebauthnError::Malformed(what))
+}
+
+/// `PublicKeyCredentialCreationOptions` for `navigator.credentials.create`
+pub fn registration_options(
+    rp: &RelyingParty,
+    challenge: &str,
+    user_handle: &[u8],
+    user_name: &str,
+    user_display_name: &str,
+    exclude_credentials: &[String],
+) -> serde_json::Value {
+    json!({
+        "rp": { "id": rp.id, "name": rp.name },
+        "user": {
+            "id": encode(user_handle),
+            "name": user_name,
+            "displayName": user_display_name,
+        },
+        "challenge": challenge,
+        "pubKeyCredParams": SUPPORTED_ALGORITHMS
+            .iter()
+            .map(|alg| json!({ "type": "public-key", "alg": alg }))
+            .collect::<Vec<_>>(),
+        "timeout": CHALLENGE_TTL_SECONDS * 1000,
+        "excludeCredentials": credential_descriptors(exclude_credentials),
+        "authenticatorSelection": {
+            "residentKey": "required",
+            "requireResidentKey": true,
+            "userVerification": "required",
+        },
+        "attestation": "none",
+    })
+}
+
+/// `PublicKeyCredentialRequestOptions` for `navigator.credentials.get`
+///
+/// An empty `allow_credentials` lets the browser offer any discoverable
+/// passkey for the site.
+pub fn authentication_options(
+    rp: &RelyingParty,
+    challenge: &str,
+    allow_credentials: &[String],
+) -> serde_json::Value {
+    json!({
+        "rpId": rp.id,
+        "challenge": challenge,
+        "timeout": CHALLENGE_TTL_SECONDS * 1000,
+        "allowCredentials": credential_descriptors(allow_credentials),
+        "userVerification": "required",
+    })
+}
+
+fn credential_descriptors(credential_ids: &[String]) -> Vec<serde_json::Value> {
+    credential_ids
+        .iter()
+        .map(|id| json!({ "type": "public-key", "id": id }))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+    #[serde(rename = "crossOrigin", default)]
+    cross_origin: bool,
+}
+
+impl ClientData {
+    fn parse(client_data_json: &[u8]) -> Result<Self, WebauthnError> {
+        serde_json::from_slice(client_data_json)
+            .map_err(|_| WebauthnError::Malformed("clientDataJSON"))
+    }
+
+    fn check(
+        &self,
+        rp: &RelyingParty,
+        kind: &str,
+        expected_challenge: &str,
+    ) -> Result<(), WebauthnError> {
+        if self.kind != kind {
+            return Err(WebauthnError::CeremonyType);
+        }
+        if self.challenge != expected_challenge {
+            return Err(WebauthnError::Challenge);
+        }
+        if self.origin != rp.origin || self.cross_origin {
+            return Err(WebauthnError::Origin);
+        }
+        Ok(())
+    }
+}
+
+/// Challenge echoed in `clientDataJSON`, used to look up the ceremony
+pub fn client_data_challenge(client_data_json: &[u8]) -> Result<String, WebauthnError> {
+    Ok(ClientData::parse(client_data_json)?.challenge)
+}
+
+struct AttestedCredential {
+    aaguid: [u8; 16],
+    credential_id: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+struct AuthenticatorData {
+    rp_id_hash: [u8; 32],
+    flags: u8,
+    sign_count: u32,
+    attested: Option<AttestedCredential>,
+}
+
+impl AuthenticatorData {
+    fn parse(bytes: &[u8]) -> Result<Self, WebauthnError> {
+        let malformed = || WebauthnError::Malformed("authenticatorData");
+        if bytes.len() < 37 {
+            return Err(malformed());
+        }
+
+        let rp_id_hash: [u8; 32] = bytes[..32].try_into().map_err(|_| malformed())?;
+        let flags = bytes[32];
+        let sign_count = u32::from_be_bytes(bytes[33..37].try_into().map_err(|_| malformed())?);
+
+        let attested = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+            let rest = &bytes[37..];
+            if rest.len() < 18 {
+                return Err(malformed());
+            }
+            let aaguid: [u8; 16] = rest[..16].try_into().map_err(|_| malformed())?;
+            let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+            if id_len > MAX_CREDENTIAL_ID_LEN || rest.len() < 18 + id_len {
+                return Err(malformed());
+            }
+            let credential_id = rest[18..18 + id_len].to_vec();
+
+            // The key is one CBOR item; extensions may follow it
+            let key_bytes = &rest[18 + id_len..];
+            let mut reader = key_bytes;
+            let _: Value = ciborium::from_reader(&mut reader).map_err(|_| malformed())?;
+            let public_key = key_bytes[..key_bytes.len() - reader.len()].to_vec();
+
+            Some(AttestedCredential {
+                aaguid,
+                credential_id,
+                public_key,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested,
+        })
+    }
+
+    fn check(&self, rp: &RelyingParty) -> Result<(), WebauthnError> {
+        if self.rp_id_hash[..] != Sha256::digest(rp.id.as_bytes())[..] {
+            return Err(WebauthnError::RpId);
+        }
+        if self.flags & FLAG_USER_PRESENT == 0 {
+            return Err(WebauthnError::UserNotPresent);
+        }
+        Ok(())
+    }
+
+    fn user_verified(&self) -> bool {
+        self.flags & FLAG_USER_VERIFIED != 0
+    }
+}
+
+/// Public key decoded from a COSE_Key
+enum PublicKey {
+    /// Uncompressed SEC1 point
+    Es256(Vec<u8>),
+    Ed25519(Vec<u8>),
+    Rs256 {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+}
+
+impl PublicKey {
+    fn from_cose(cose_key: &[u8]) -> Result<(Self, i64), WebauthnError> {
+        let malformed = || WebauthnError::Malformed("public key");
+        let Value::Map(entries) =
+            ciborium::from_reader::<Value, _>(cose_key).map_err(|_| malformed())?
+        else {
+            return Err(malformed());
+        };
+
+        let int = |label: i64| {
+            entries.iter().find_map(|(k, v)| match (k, v) {
+                (Value::Integer(k), Value::Integer(v)) if i128::from(*k) == label as i128 => {
+                    i64::try_from(i128::from(*v)).ok()
+                }
+                _ => None,
+            })
+        };
+        let bytes = |label: i64| {
+            entries.iter().find_map(|(k, v)| match (k, v) {
+                (Value::Integer(k), Value::Bytes(v)) if i128::from(*k) == label as i128 => {
+                    Some(v.clone())
+                }
+                _ => None,
+            })
+        };
+
+        // COSE labels: 1 kty, 3 alg, -1 crv (or RSA n), -2 x (or RSA e), -3 y
+        let alg = int(3).ok_or_else(malformed)?;
+        let key = match (alg, int(1)) {
+            (COSE_ALG_ES256, Some(2)) => {
+                if int(-1) != Some(1) {
+                    return Err(malformed());
+                }
+                let (x, y) = (
+                    bytes(-2).ok_or_else(malformed)?,
+                    bytes(-3).ok_or_else(malformed)?,
+                );
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(malformed());
+                }
+                PublicKey::Es256([&[0x04][..], &x, &y].concat())
+            }
+            (COSE_ALG_EDDSA, Some(1)) => {
+                if int(-1) != Some(6) {
+                    return Err(malformed());
+                }
+                let x = bytes(-2).ok_or_else(malformed)?;
+                if x.len() != 32 {
+                    return Err(malformed());
+                }
+                PublicKey::Ed25519(x)
+            }
+            (COSE_ALG_RS256, Some(3)) => PublicKey::Rs256 {
+                n: bytes(-1).ok_or_else(malformed)?,
+                e: bytes(-2).ok_or_else(malformed)?,
+            },
+            (COSE_ALG_ES256 | COSE_ALG_EDDSA | COSE_ALG_RS256, _) => return Err(malformed()),
+            (other, _) => return Err(WebauthnError::UnsupportedAlgorithm(other)),
+        };
+        Ok((key, alg))
+    }
+
+    fn verify(&self, message: &[u8], sig: &[u8]) -> Result<(), WebauthnError> {
+        let result = match self {
+            PublicKey::Es256(point) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+                    .verify(message, sig)
+            }
+            PublicKey::Ed25519(key) => {
+                UnparsedPublicKey::new(&signature::ED25519, key).verify(message, sig)
+            }
+            PublicKey::Rs256 { n, e } => RsaPublicKeyComponents { n, e }.verify(
+                &signature::RSA_PKCS1_2048_8192_SHA256,
+                message,
+                sig,
+            ),
+        };
+        result.map_err(|_| WebauthnError::Signature)
+    }
+}
+
+/// Check a `navigator.credentials.create` response
+pub fn verify_registration(
+    rp: &RelyingParty,
+    expected_challenge: &str,
+    client_data_json: &[u8],
+    attestation_object: &[u8],
+) -> Result<VerifiedRegistration, WebauthnError> {
+    ClientData::parse(client_data_json)?.check(rp, CLIENT_DATA_CREATE, expected_challenge)?;
+
+    let malformed = || WebauthnError::Malformed("attestationObject");
+    let Value::Map(entries) =
+        ciborium::from_reader::<Value, _>(attestation_object).map_err(|_| malformed())?
+    else {
+        return Err(malformed());
+    };
+    let auth_data = entries
+        .iter()
+        .find_map(|(k, v)| match (k, v) {
+            (Value::Text(k), Value::Bytes(v)) if k == "authData" => Some(v),
+            _ => None,
+        })
+        .ok_or_else(malformed)?;
+
+    let auth_data = AuthenticatorData::parse(auth_data)?;
+    auth_data.check(rp)?;
+    let user_verified = auth_data.user_verified();
+    let credential = auth_data
+        .attested
+        .ok_or(WebauthnError::Malformed("attested credential data"))?;
+    let (_, algorithm) = PublicKey::from_cose(&credential.public_key)?;
+
+    Ok(VerifiedRegistration {
+        credential_id: credential.credential_id,
+        public_key: credential.public_key,
+        algorithm,
+        sign_count: auth_data.sign_count,
+        aaguid: credential.aaguid,
+        user_verified,
+    })
+}
+
+/// Check a `navigator.credentials.get` response against a stored credential
+///
+/// Authenticators that keep a counter must report a higher value than
+/// `stored_sign_count` each time; ones that always report zero are allowed.
+pub fn verify_authentication(
+    rp: &RelyingParty,
+    expected_challenge: &str,
+    client_data_json: &[u8],
+    authenticator_data: &[u8],
+    sig: &[u8],
+    public_key: &[u8],
+    stored_sign_count: u32,
+) -> Result<VerifiedAuthentication, WebauthnError> {
+    ClientData::parse(client_data_json)?.check(rp, CLIENT_DATA_GET, expected_challenge)?;
+
+    let auth_data = AuthenticatorData::parse(authenticator_data)?;
+    auth_data.check(rp)?;
+
+    let (key, _) = PublicKey::from_cose(public_key)?;
+    let message = [authenticator_data, &Sha256::digest(client_data_json)[..]].concat();
+    key.verify(&message, sig)?;
+
+    if (auth_data.sign_count != 0 || stored_sign_count != 0)
+        && auth_data.sign_count <= stored_sign_count
+    {
+        return Err(WebauthnError::CounterRegression);
+    }
+
+    Ok(VerifiedAuthentication {
+        sign_count: auth_data.sign_count,
+        user_verified: auth_data.user_verified(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair};
+
+    /// Authenticator that keeps its key in memory
+    struct SoftAuthenticator {
+        key: SoftKey,
+        credential_id: Vec<u8>,
+        sign_count: u32,
+    }
+
+    enum SoftKey {
+        Es256(EcdsaKeyPair),
+        Ed25519(Ed25519KeyPair),
+    }
+
+    impl SoftAuthenticator {
+        fn es256() -> Self {
+            let rng = SystemRandom::new();
+            let alg = &signature::ECDSA_P256_SHA256_ASN1_SIGNING;
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(alg, &rng).unwrap();
+            Self::new(SoftKey::Es256(
+                EcdsaKeyPair::from_pkcs8(alg, pkcs8.as_ref(), &rng).unwrap(),
+            ))
+        }
+
+        fn ed25519() -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            Self::new(SoftKey::Ed25519(
+                Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap(),
+            ))
+        }
+
+        fn new(key: SoftKey) -> Self {
+            let mut credential_id = vec![0u8; 16];
+            rand::thread_rng().fill_bytes(&mut credential_id);
+            Self {
+                key,
+                credential_id,
+                sign_count: 0,
+            }
+        }
+
+        fn cose_key(&self) -> Vec<u8> {
+            let int = |i: i64| Value::Integer(i.into());
+            let entries = match &self.key {
+                SoftKey::Es256(pair) => {
+                    let point = pair.public_key().as_ref();
+                    vec![
+                        (int(1), int(2)),
+                        (int(3), int(COSE_ALG_ES256)),
+                        (int(-1), int(1)),
+                        (int(-2), Value::Bytes(point[1..33].to_vec())),
+                        (int(-3), Value::Bytes(point[33..].to_vec())),
+                    ]
+                }
+                SoftKey::Ed25519(pair) => vec![
+                    (int(1), int(1)),
+                    (int(3), int(COSE_ALG_EDDSA)),
+                    (int(-1), int(6)),
+                    (int(-2), Value::Bytes(pair.public_key().as_ref().to_vec())),
+                ],
+            };
+            let mut out = Vec::new();
+            ciborium::into_writer(&Value::Map(entries), &mut out).unwrap();
+            out
+        }
+
+        fn authenticator_data(&self, rp_id: &str, flags: u8, attested: bool) -> Vec<u8> {
+            let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+            data.push(flags);
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            if attested {
+                data.extend_from_slice(&[7u8; 16]);
+                data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                data.extend_from_slice(&self.cose_key());
+            }
+            data
+        }
+
+        /// (clientDataJSON, attestationObject)
+        fn create(&self, rp_id: &str, origin: &str, challenge: &str) -> (Vec<u8>, Vec<u8>) {
+            let client_data = client_data(CLIENT_DATA_CREATE, challenge, origin);
+            let auth_data = self.authenticator_data(
+                rp_id,
+                FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL,
+                true,
+            );
+            let attestation = Value::Map(vec![
+                (Value::Text("fmt".into()), Value::Text("none".into())),
+                (Value::Text("attStmt".into()), Value::Map(vec![])),
+                (Value::Text("authData".into()), Value::Bytes(auth_data)),
+            ]);
+            let mut out = Vec::new();
+            ciborium::into_writer(&attestation, &mut out).unwrap();
+            (client_data, out)
+        }
+
+        /// (clientDataJSON, authenticatorData, signature)
+        fn get(
+            &mut self,
+            rp_id: &str,
+            origin: &str,
+            challenge: &str,
+        ) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+            self.sign_count += 1;
+            let client_data = client_data(CLIENT_DATA_GET, challenge, origin);
+            let auth_data =
+                self.authenticator_data(rp_id, FLAG_USER_PRESENT | FLAG_USER_VERIFIED, false);
+            let message = [&auth_data[..], &Sha256::digest(&client_data)[..]].concat();
+            let sig = match &self.key {
+                SoftKey::Es256(pair) => pair
+                    .sign(&SystemRandom::new(), &message)
+                    .unwrap()
+                    .as_ref()
+                    .to_vec(),
+                SoftKey::Ed25519(pair) => pair.sign(&message).as_ref().to_vec(),
+            };
+            (client_data, auth_data, sig)
+        }
+    }
+
+    fn client_data(kind: &str, challenge: &str, origin: &str) -> Vec<u8> {
+        serde_json::to_vec(&json!({ "type": kind, "challenge": challenge, "origin": origin }))
+            .unwrap()
+    }
+
+    fn rp() -> RelyingParty {
+        RelyingParty::from_frontend_url("http://localhost:5173/app", "Kaleido", None).unwrap()
+    }
+
+    fn register(rp: &RelyingParty, authenticator: &SoftAuthenticator) -> VerifiedRegistration {
+        let challenge = generate_challenge();
+        let (client_data, attestation) = authenticator.create(&rp.id, &rp.origin, &challenge);
+        verify_registration(rp, &challenge, &client_data, &attestation).unwrap()
+    }
+
+    #[test]
+    fn test_relying_party_from_frontend_url() {
+        let rp = rp();
+        assert_eq!(rp.id, "localhost");
+        assert_eq!(rp.origin, "http://localhost:5173");
+
+        let shared = RelyingParty::from_frontend_url(
+            "https://app.example.com",
+            "Kaleido",
+            Some("example.com"),
+        )
+        .unwrap();
+        assert_eq!(shared.id, "example.com");
+        assert_eq!(shared.origin, "https://app.example.com");
+    }
+
+    #[test]
+    fn test_register_and_authenticate() {
+        let rp = rp();
+        for mut authenticator in [SoftAuthenticator::es256(), SoftAuthenticator::ed25519()] {
+            let registered = register(&rp, &authenticator);
+            assert_eq!(registered.credential_id, authenticator.credential_id);
+            assert_eq!(registered.public_key, authenticator.cose_key());
+            assert!(registered.user_verified);
+
+            let challenge = generate_challenge();
+            let (client_data, auth_data, sig) = authenticator.get(&rp.id, &rp.origin, &challenge);
+            let verified = verify_authentication(
+                &rp,
+                &challenge,
+                &client_data,
+                &auth_data,
+                &sig,
+                &registered.public_key,
+                registered.sign_count,
+            )
+            .unwrap();
+            assert_eq!(verified.sign_count, 1);
+
+            // Same assertion again: the counter did not advance
+            let replay = verify_authentication(
+                &rp,
+                &challenge,
+                &client_data,
+                &auth_data,
+                &sig,
+                &registered.public_key,
+                verified.sign_count,
+            );
+            assert_eq!(replay.unwrap_err(), WebauthnError::CounterRegression);
+        }
+    }
+
+    #[test]
+    fn test_registration_rejects_wrong_context() {
+        let rp = rp();
+        let authenticator = SoftAuthenticator::es256();
+        let challenge = generate_challenge();
+
+        let (client_data, attestation) = authenticator.create(&rp.id, &rp.origin, &challenge);
+        assert_eq!(
+            verify_registration(&rp, &generate_challenge(), &client_data, &attestation)
+                .unwrap_err(),
+            WebauthnError::Challenge
+        );
+
+        let (client_data, attestation) =
+            authenticator.create(&rp.id, "https://evil.example", &challenge);
+        assert_eq!(
+            verify_registration(&rp, &challenge, &client_data, &attestation).unwrap_err(),
+            WebauthnError::Origin
+        );
+
+        let (client_data, attestation) =
+            authenticator.create("evil.example", &rp.origin, &challenge);
+        assert_eq!(
+            verify_registration(&rp, &challenge, &client_data, &attestation).unwrap_err(),
+            WebauthnError::RpId
+        );
+    }
+
+    #[test]
+    fn test_authentication_rejects_bad_signature() {
+        let rp = rp();
+        let mut authenticator = SoftAuthenticator::es256();
+        let registered = register(&rp, &authenticator);
+        let other = register(&rp, &SoftAuthenticator::es256());
+
+        let challenge = generate_challenge();
+        let (client_data, auth_data, sig) = authenticator.get(&rp.id, &rp.origin, &challenge);
+
+        // Signed by a different key
+        assert_eq!(
+            verify_authentication(
+                &rp,
+                &challenge,
+                &client_data,
+                &auth_data,
+                &sig,
+                &other.public_key,
+                0
+            )
+            .unwrap_err(),
+            WebauthnError::Signature
+        );
+
+        // A registration response is not an assertion
+        let (create_data, _) = authenticator.create(&rp.id, &rp.origin, &challenge);
+        assert_eq!(
+            verify_authentication(
+                &rp,
+                &challenge,
+                &create_data,
+                &auth_data,
+                &sig,
+                &registered.public_key,
+                0
+            )
+            .unwrap_err(),
+            WebauthnError::CeremonyType
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::background_jobs::worker::{TaskProcessor, TaskWorker, WorkerError};
+use crate::background_jobs::worker::{ProcessError, TaskProcessor, TaskWorker, WorkerError};
 use async_trait::async_trait;
 use handlebars::Handlebars;
 use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
@@ -9,7 +9,7 @@ use std::collections::HashMap;
 
 pub mod tasks;
 
-use self::tasks::{EmailPasswordResetTask, EmailRegistrationTask};
+use self::tasks::{EmailMagicLinkTask, EmailPasswordResetTask, EmailRegistrationTask};
 
 #[derive(Debug, Clone)]
 pub struct AuthWorkerConfig {
@@ -113,8 +113,9 @@ impl AuthEmailProcessorRuntime {
     ) -> Result<(), WorkerError> {
         let to_mailbox = Mailbox::new(
             None,
-            to.parse()
-                .map_err(|e| format!("invalid recipient email '{}': {}", to, e))?,
+            to.parse().map_err(|e| {
+                ProcessError::permanent(format!("invalid recipient email '{}': {}", to, e))
+            })?,
         );
 
         let email = Message::builder()
@@ -134,12 +135,19 @@ impl AuthEmailProcessorRuntime {
                             .body(html_body),
                     ),
             )
-            .map_err(|e| format!("failed to build email message: {}", e))?;
-
-        self.mailer
-            .send(email)
-            .await
-            .map_err(|e| format!("failed to send email: {}", e))?;
+            .map_err(|e| {
+                ProcessError::permanent(format!("failed to build email message: {}", e))
+            })?;
+
+        self.mailer.send(email).await.map_err(|e| {
+            // 5xx responses won't succeed on a retry
+            let message = format!("failed to send email: {}", e);
+            if e.is_permanent() {
+                ProcessError::permanent(message)
+            } else {
+                ProcessError::retry(message)
+            }
+        })?;
 
         Ok(())
     }
@@ -165,8 +173,9 @@ impl TaskProcessor for EmailRegistrationProcessor {
 
     async fn process(&self, _task_id: i32, payload: serde_json::Value) -> Result<(), WorkerError> {
         let data = payload.get("data").unwrap_or(&payload);
-        let task: EmailRegistrationTask = serde_json::from_value(data.clone())
-            .map_err(|e| format!("invalid email_registration payload: {}", e))?;
+        let task: EmailRegistrationTask = serde_json::from_value(data.clone()).map_err(|e| {
+            ProcessError::permanent(format!("invalid email_registration payload: {}", e))
+        })?;
 
         let template_data = json!({
             "app_name": self.runtime.app_name,
@@ -206,8 +215,9 @@ impl TaskProcessor for EmailPasswordResetProcessor {
 
     async fn process(&self, _task_id: i32, payload: serde_json::Value) -> Result<(), WorkerError> {
         let data = payload.get("data").unwrap_or(&payload);
-        let task: EmailPasswordResetTask = serde_json::from_value(data.clone())
-            .map_err(|e| format!("invalid email_password_reset payload: {}", e))?;
+        let task: EmailPasswordResetTask = serde_json::from_value(data.clone()).map_err(|e| {
+            ProcessError::permanent(format!("invalid email_password_reset payload: {}", e))
+        })?;
 
         let template_data = json!({
             "app_name": self.runtime.app_name,
@@ -228,16 +238,61 @@ impl TaskProcessor for EmailPasswordResetProcessor {
     }
 }
 
+pub struct EmailMagicLinkProcessor {
+    runtime: AuthEmailProcessorRuntime,
+}
+
+impl EmailMagicLinkProcessor {
+    pub fn new(config: &AuthWorkerConfig) -> Result<Self, WorkerError> {
+        Ok(Self {
+            runtime: AuthEmailProcessorRuntime::new(config)?,
+        })
+    }
+}
+
+#[async_trait]
+impl TaskProcessor for EmailMagicLinkProcessor {
+    fn task_type(&self) -> &str {
+        "email_magic_link"
+    }
+
+    async fn process(&self, _task_id: i32, payload: serde_json::Value) -> Result<(), WorkerError> {
+        let data = payload.get("data").unwrap_or(&payload);
+        let task: EmailMagicLinkTask = serde_json::from_value(data.clone()).map_err(|e| {
+            ProcessError::permanent(format!("invalid email_magic_link payload: {}", e))
+        })?;
+
+        let template_data = json!({
+            "app_name": self.runtime.app_name,
+            "name": task.name,
+            "login_url": task.login_url,
+            "expiry_minutes": task.expiry_minutes,
+        });
+
+        let text_body = self.runtime.render("magic_link_text", &template_data)?;
+        let html_body = self.runtime.render("magic_link_html", &template_data)?;
+        let subject = format!("Sign in to {}", self.runtime.app_name);
+
+        self.runtime
+            .send_email(&task.to, &subject, text_body, html_body)
+            .await?;
+
+        Ok(())
+    }
+}
+
 pub fn register_auth_email_processors(
     worker: TaskWorker,
     config: &AuthWorkerConfig,
 ) -> Result<TaskWorker, WorkerError> {
     let email_registration = std::sync::Arc::new(EmailRegistrationProcessor::new(config)?);
     let email_password_reset = std::sync::Arc::new(EmailPasswordResetProcessor::new(config)?);
+    let email_magic_link = std::sync::Arc::new(EmailMagicLinkProcessor::new(config)?);
 
     Ok(worker
         .register_processor(email_registration)
-        .register_processor(email_password_reset))
+        .register_processor(email_password_reset)
+        .register_processor(email_magic_link))
 }
 
 /// Register all auth-related task processors on the provided worker.
@@ -276,6 +331,12 @@ fn register_default_templates(registry: &mut Handlebars<'static>) -> Result<(),
             include_str!("templates/password_reset.html"),
         )
         .map_err(|e| format!("failed to register password_reset_html: {}", e))?;
+    registry
+        .register_template_string("magic_link_text", include_str!("templates/magic_link.txt"))
+        .map_err(|e| format!("failed to register magic_link_text: {}", e))?;
+    registry
+        .register_template_string("magic_link_html", include_str!("templates/magic_link.html"))
+        .map_err(|e| format!("failed to register magic_link_html: {}", e))?;
 
     Ok(())
 }
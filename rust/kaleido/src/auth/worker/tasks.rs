@@ -1,16 +1,23 @@
-use crate::background_jobs::storage::TaskStorage;
+use crate::background_jobs::storage::{EnqueueOptions, TaskStorage};
 use crate::background_jobs::TaskQueue;
 use serde::{Deserialize, Serialize};
 
 pub const EMAIL_REGISTRATION_TASK_TYPE: &str = "email_registration";
 pub const EMAIL_PASSWORD_RESET_TASK_TYPE: &str = "email_password_reset";
 pub const EMAIL_NOTIFICATION_TASK_TYPE: &str = "email_notification";
+pub const EMAIL_MAGIC_LINK_TASK_TYPE: &str = "email_magic_link";
+
+/// Registration emails that sit in the queue longer than this are dropped.
+pub const EMAIL_REGISTRATION_EXPIRY_HOURS: i64 = 24;
+/// Password reset emails that sit in the queue longer than this are dropped.
+pub const EMAIL_PASSWORD_RESET_EXPIRY_MINUTES: i64 = 60;
 
 /// All auth-related task types (for easy registration/checking).
 pub const AUTH_TASK_TYPES: &[&str] = &[
     EMAIL_REGISTRATION_TASK_TYPE,
     EMAIL_PASSWORD_RESET_TASK_TYPE,
     EMAIL_NOTIFICATION_TASK_TYPE,
+    EMAIL_MAGIC_LINK_TASK_TYPE,
 ];
 
 /// Return a static slice with all auth task type strings.
@@ -33,6 +40,14 @@ pub struct EmailPasswordResetTask {
     pub expiry_hours: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailMagicLinkTask {
+    pub to: String,
+    pub name: String,
+    pub login_url: String,
+    pub expiry_minutes: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmailNotificationTask {
     pub to: String,
@@ -47,13 +62,15 @@ pub async fn enqueue_email_registration<S: TaskStorage>(
     verification_url: String,
 ) {
     let _ = queue
-        .enqueue(
+        .enqueue_with(
             EMAIL_REGISTRATION_TASK_TYPE.to_string(),
             EmailRegistrationTask {
                 to,
                 name,
                 verification_url,
             },
+            EnqueueOptions::default()
+                .with_ttl(chrono::Duration::hours(EMAIL_REGISTRATION_EXPIRY_HOURS)),
         )
         .await;
 }
@@ -66,7 +83,7 @@ pub async fn enqueue_email_password_reset<S: TaskStorage>(
     expiry_hours: u32,
 ) {
     let _ = queue
-        .enqueue(
+        .enqueue_with(
             EMAIL_PASSWORD_RESET_TASK_TYPE.to_string(),
             EmailPasswordResetTask {
                 to,
@@ -74,6 +91,31 @@ pub async fn enqueue_email_password_reset<S: TaskStorage>(
                 reset_url,
                 expiry_hours,
             },
+            EnqueueOptions::default().with_ttl(chrono::Duration::minutes(
+                EMAIL_PASSWORD_RESET_EXPIRY_MINUTES,
+            )),
+        )
+        .await;
+}
+
+/// Magic-link emails expire with the link they carry.
+pub async fn enqueue_email_magic_link<S: TaskStorage>(
+    queue: &TaskQueue<S>,
+    to: String,
+    name: String,
+    login_url: String,
+    expiry_minutes: i64,
+) {
+    let _ = queue
+        .enqueue_with(
+            EMAIL_MAGIC_LINK_TASK_TYPE.to_string(),
+            EmailMagicLinkTask {
+                to,
+                name,
+                login_url,
+                expiry_minutes,
+            },
+            EnqueueOptions::default().with_ttl(chrono::Duration::minutes(expiry_minutes)),
         )
         .await;
 }
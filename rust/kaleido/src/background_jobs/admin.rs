@@ -1,14 +1,25 @@
+use crate::background_jobs::batches::BatchService;
+use crate::background_jobs::cipher::PayloadCipher;
+use crate::background_jobs::durable::DurableStorage;
 use crate::background_jobs::entities::background_tasks;
+use crate::background_jobs::error::TaskError;
+use crate::background_jobs::events::{publish_task_event, TaskEvent, TaskEventBus, TaskEventKind};
+use crate::background_jobs::queue::TaskQueue;
+use crate::background_jobs::stats::{StatsWindow, TaskStatsResponse};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::{IntoResponse, Response},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     routing::{get, post},
     Json, Router,
 };
 use chrono::{DateTime, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, JsonValue, NotSet,
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, JsonValue, NotSet,
     PaginatorTrait, QueryFilter, QueryOrder, Set,
 };
 use serde::{Deserialize, Serialize};
@@ -18,6 +29,20 @@ use utoipa::{IntoParams, ToSchema};
 /// Storage trait for background tasks admin routes.
 pub trait BackgroundTasksStorage: Send + Sync + 'static {
     fn db(&self) -> &DatabaseConnection;
+
+    /// Event bus backing the `/events` stream; `None` disables the stream.
+    ///
+    /// On Postgres, feed it from worker processes with
+    /// [`TaskEventBus::spawn_postgres_listener`].
+    fn task_events(&self) -> Option<&TaskEventBus> {
+        None
+    }
+
+    /// Queue for batch callbacks enqueued when an admin cancel finishes a batch
+    ///
+    /// Return the application's configured queue, so callbacks of sensitive
+    /// task types are encrypted with its cipher.
+    fn task_queue(&self) -> TaskQueue<DurableStorage>;
 }
 
 /// Marker trait for types that verify admin authorization.
@@ -59,9 +84,18 @@ where
 {
     Router::new()
         .route("/", get(list_tasks::<S, A>))
+        .route("/events", get(task_events::<S, A>))
+        .route("/stats", get(task_stats::<S, A>))
+        .route("/bulk/cancel", post(bulk_cancel_tasks::<S, A>))
+        .route("/bulk/run-now", post(bulk_run_tasks_now::<S, A>))
+        .route("/bulk/reschedule", post(bulk_reschedule_tasks::<S, A>))
+        .route("/bulk/max-attempts", post(bulk_set_max_attempts::<S, A>))
         .route("/:id", get(get_task::<S, A>))
         .route("/:id/rerun", post(rerun_task::<S, A>))
         .route("/:id/cancel", post(cancel_task::<S, A>))
+        .route("/:id/run-now", post(run_task_now::<S, A>))
+        .route("/:id/reschedule", post(reschedule_task::<S, A>))
+        .route("/:id/max-attempts", post(set_max_attempts::<S, A>))
 }
 
 /// Returns the background task admin routes with their standard API path.
@@ -76,7 +110,16 @@ where
     A: AdminVerified + axum::extract::FromRequestParts<Arc<S>> + Send + 'static,
     <A as axum::extract::FromRequestParts<Arc<S>>>::Rejection: IntoResponse,
 {
-    Router::new().nest("/admin/tasks", admin_routes::<S, A>())
+    Router::new()
+        .nest("/admin/tasks", admin_routes::<S, A>())
+        .nest(
+            "/admin/schedules",
+            crate::background_jobs::schedules::admin_routes::<S, A>(),
+        )
+        .nest(
+            "/admin/batches",
+            crate::background_jobs::batches::admin_routes::<S, A>(),
+        )
 }
 
 #[derive(Debug)]
@@ -86,19 +129,33 @@ pub struct AdminTaskError {
 }
 
 impl AdminTaskError {
-    fn not_found(message: impl Into<String>) -> Self {
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
         Self {
             code: StatusCode::NOT_FOUND,
             message: message.into(),
         }
     }
 
-    fn bad_request(message: impl Into<String>) -> Self {
+    pub(crate) fn bad_request(message: impl Into<String>) -> Self {
         Self {
             code: StatusCode::BAD_REQUEST,
             message: message.into(),
         }
     }
+
+    pub(crate) fn conflict(message: impl Into<String>) -> Self {
+        Self {
+            code: StatusCode::CONFLICT,
+            message: message.into(),
+        }
+    }
+
+    fn service_unavailable(message: impl Into<String>) -> Self {
+        Self {
+            code: StatusCode::SERVICE_UNAVAILABLE,
+            message: message.into(),
+        }
+    }
 }
 
 impl From<sea_orm::DbErr> for AdminTaskError {
@@ -110,6 +167,15 @@ impl From<sea_orm::DbErr> for AdminTaskError {
     }
 }
 
+impl From<TaskError> for AdminTaskError {
+    fn from(e: TaskError) -> Self {
+        Self {
+            code: StatusCode::INTERNAL_SERVER_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
 impl IntoResponse for AdminTaskError {
     fn into_response(self) -> Response {
         let body = Json(serde_json::json!({ "error": self.message }));
@@ -131,6 +197,67 @@ pub struct TaskListQuery {
     pub per_page: u64,
 }
 
+impl TaskListQuery {
+    fn filter(&self) -> TaskFilter {
+        TaskFilter {
+            task_type: self.task_type.clone(),
+            status: self.status.clone(),
+            error: self.error.clone(),
+            from_date: self.from_date.clone(),
+            to_date: self.to_date.clone(),
+        }
+    }
+}
+
+/// Task selection shared by the list endpoint and bulk actions
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskFilter {
+    pub task_type: Option<String>,
+    pub status: Option<String>,
+    pub error: Option<String>,
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+}
+
+impl TaskFilter {
+    pub fn is_empty(&self) -> bool {
+        self.task_type.is_none()
+            && self.status.is_none()
+            && self.error.is_none()
+            && self.from_date.is_none()
+            && self.to_date.is_none()
+    }
+
+    pub fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+
+        if let Some(ref t) = self.task_type {
+            condition = condition.add(background_tasks::Column::TaskType.eq(t.clone()));
+        }
+        if let Some(ref s) = self.status {
+            condition = condition.add(background_tasks::Column::Status.eq(s.clone()));
+        }
+        if let Some(ref e) = self.error {
+            condition = condition.add(background_tasks::Column::Error.contains(e.as_str()));
+        }
+        if let Some(ref d) = self.from_date {
+            if let Ok(parsed) = DateTime::parse_from_rfc3339(d) {
+                condition = condition
+                    .add(background_tasks::Column::CreatedAt.gte(parsed.with_timezone(&Utc)));
+            }
+        }
+        if let Some(ref d) = self.to_date {
+            if let Ok(parsed) = DateTime::parse_from_rfc3339(d) {
+                condition = condition
+                    .add(background_tasks::Column::CreatedAt.lte(parsed.with_timezone(&Utc)));
+            }
+        }
+
+        condition
+    }
+}
+
 fn default_page() -> u64 {
     1
 }
@@ -153,7 +280,7 @@ pub struct PaginatedResponse<T> {
 }
 
 impl<T> PaginatedResponse<T> {
-    fn new(data: Vec<T>, page: i64, per_page: i64, total: i64) -> Self {
+    pub(crate) fn new(data: Vec<T>, page: i64, per_page: i64, total: i64) -> Self {
         let total_pages = if per_page > 0 {
             (total as f64 / per_page as f64).ceil() as i64
         } else {
@@ -175,14 +302,19 @@ impl<T> PaginatedResponse<T> {
 pub struct TaskResponse {
     pub id: i32,
     pub task_type: String,
+    pub concurrency_key: Option<String>,
+    pub batch_id: Option<i32>,
     pub status: String,
     pub attempts: i32,
     pub max_attempts: i32,
     pub error: Option<String>,
+    /// `permanent`, `retry` or `snooze` for the most recent failure
+    pub error_kind: Option<String>,
     pub result: Option<String>,
     pub created_at: String,
     pub updated_at: String,
     pub scheduled_for: Option<String>,
+    pub expires_at: Option<String>,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
 }
@@ -192,14 +324,18 @@ impl From<background_tasks::Model> for TaskResponse {
         Self {
             id: m.id,
             task_type: m.task_type,
+            concurrency_key: m.concurrency_key,
+            batch_id: m.batch_id,
             status: m.status,
             attempts: m.attempts,
             max_attempts: m.max_attempts,
             error: m.error,
+            error_kind: m.error_kind,
             result: m.result,
             created_at: m.created_at.to_rfc3339(),
             updated_at: m.updated_at.to_rfc3339(),
             scheduled_for: m.scheduled_for.map(|d| d.to_rfc3339()),
+            expires_at: m.expires_at.map(|d| d.to_rfc3339()),
             started_at: m.started_at.map(|d| d.to_rfc3339()),
             completed_at: m.completed_at.map(|d| d.to_rfc3339()),
         }
@@ -210,15 +346,25 @@ impl From<background_tasks::Model> for TaskResponse {
 pub struct TaskDetailResponse {
     pub id: i32,
     pub task_type: String,
+    pub concurrency_key: Option<String>,
+    pub batch_id: Option<i32>,
     pub status: String,
     pub attempts: i32,
     pub max_attempts: i32,
     pub error: Option<String>,
+    /// `permanent`, `retry` or `snooze` for the most recent failure
+    pub error_kind: Option<String>,
     pub result: Option<String>,
+    /// Redacted when the task type is marked sensitive
     pub payload: Option<JsonValue>,
+    pub payload_encrypted: bool,
+    pub payload_version: i32,
+    /// Propagated context such as the enqueuing trace's `traceparent`
+    pub metadata: Option<JsonValue>,
     pub created_at: String,
     pub updated_at: String,
     pub scheduled_for: Option<String>,
+    pub expires_at: Option<String>,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
 }
@@ -228,15 +374,26 @@ impl From<background_tasks::Model> for TaskDetailResponse {
         Self {
             id: m.id,
             task_type: m.task_type,
+            concurrency_key: m.concurrency_key,
+            batch_id: m.batch_id,
             status: m.status,
             attempts: m.attempts,
             max_attempts: m.max_attempts,
             error: m.error,
+            error_kind: m.error_kind,
             result: m.result,
-            payload: Some(m.payload),
+            payload_encrypted: PayloadCipher::is_encrypted(&m.payload),
+            payload: Some(if PayloadCipher::is_encrypted(&m.payload) {
+                PayloadCipher::redact(&m.payload)
+            } else {
+                m.payload
+            }),
+            payload_version: m.payload_version,
+            metadata: m.metadata,
             created_at: m.created_at.to_rfc3339(),
             updated_at: m.updated_at.to_rfc3339(),
             scheduled_for: m.scheduled_for.map(|d| d.to_rfc3339()),
+            expires_at: m.expires_at.map(|d| d.to_rfc3339()),
             started_at: m.started_at.map(|d| d.to_rfc3339()),
             completed_at: m.completed_at.map(|d| d.to_rfc3339()),
         }
@@ -269,30 +426,9 @@ where
     let page = params.page.max(1);
     let per_page = params.per_page.clamp(1, 100);
 
-    let mut query =
-        background_tasks::Entity::find().order_by_desc(background_tasks::Column::CreatedAt);
-
-    if let Some(ref t) = params.task_type {
-        query = query.filter(background_tasks::Column::TaskType.eq(t.clone()));
-    }
-    if let Some(ref s) = params.status {
-        query = query.filter(background_tasks::Column::Status.eq(s.clone()));
-    }
-    if let Some(ref e) = params.error {
-        query = query.filter(background_tasks::Column::Error.contains(e.as_str()));
-    }
-    if let Some(ref d) = params.from_date {
-        if let Ok(parsed) = DateTime::parse_from_rfc3339(d) {
-            query =
-                query.filter(background_tasks::Column::CreatedAt.gte(parsed.with_timezone(&Utc)));
-        }
-    }
-    if let Some(ref d) = params.to_date {
-        if let Ok(parsed) = DateTime::parse_from_rfc3339(d) {
-            query =
-                query.filter(background_tasks::Column::CreatedAt.lte(parsed.with_timezone(&Utc)));
-        }
-    }
+    let query = background_tasks::Entity::find()
+        .filter(params.filter().condition())
+        .order_by_desc(background_tasks::Column::CreatedAt);
 
     let paginator = query.paginate(db, per_page);
     let total = paginator.num_items().await? as i64;
@@ -377,12 +513,18 @@ where
         id: NotSet,
         task_type: Set(task.task_type),
         payload: Set(task.payload),
+        payload_version: Set(task.payload_version),
+        concurrency_key: Set(task.concurrency_key),
+        batch_id: Set(None),
+        metadata: Set(task.metadata),
         status: Set("pending".to_string()),
         attempts: Set(0),
         max_attempts: Set(task.max_attempts),
         error: Set(None),
+        error_kind: Set(None),
         result: Set(None),
         scheduled_for: Set(None),
+        expires_at: Set(None),
         created_at: Set(now),
         updated_at: Set(now),
         started_at: Set(None),
@@ -391,9 +533,182 @@ where
     .insert(db)
     .await?;
 
+    publish_task_event(
+        db,
+        state.task_events(),
+        TaskEvent::from_model(TaskEventKind::Enqueued, &created),
+    )
+    .await;
+
     Ok(Json(TaskResponse::from(created)))
 }
 
+/// A change an admin can apply to one task or every task matching a filter
+enum TaskAction {
+    Cancel,
+    RunNow,
+    Reschedule(DateTime<Utc>),
+    SetMaxAttempts(i32),
+}
+
+impl TaskAction {
+    fn allowed_statuses(&self) -> &'static [&'static str] {
+        match self {
+            TaskAction::Cancel | TaskAction::SetMaxAttempts(_) => &["pending", "processing"],
+            TaskAction::RunNow | TaskAction::Reschedule(_) => &["pending"],
+        }
+    }
+
+    fn rejection(&self) -> &'static str {
+        match self {
+            TaskAction::Cancel => "Only pending or processing tasks can be canceled",
+            TaskAction::RunNow => "Only pending tasks can be run now",
+            TaskAction::Reschedule(_) => "Only pending tasks can be rescheduled",
+            TaskAction::SetMaxAttempts(_) => {
+                "Only pending or processing tasks can change max attempts"
+            }
+        }
+    }
+
+    /// Apply the action to matching tasks still in an allowed status
+    ///
+    /// The status check is part of the UPDATE, so a task a worker claims or
+    /// finishes concurrently is left alone.
+    async fn apply<S: BackgroundTasksStorage>(
+        &self,
+        state: &S,
+        condition: Condition,
+    ) -> Result<Vec<background_tasks::Model>, AdminTaskError> {
+        let db = state.db();
+        let now = Utc::now();
+        let update = background_tasks::Entity::update_many()
+            .col_expr(background_tasks::Column::UpdatedAt, Expr::value(now))
+            .filter(condition)
+            .filter(
+                background_tasks::Column::Status.is_in(self.allowed_statuses().iter().copied()),
+            );
+
+        let update = match self {
+            TaskAction::Cancel => update
+                .col_expr(background_tasks::Column::Status, Expr::value("canceled"))
+                .col_expr(
+                    background_tasks::Column::Error,
+                    Expr::value("Canceled by admin"),
+                )
+                .col_expr(background_tasks::Column::CompletedAt, Expr::value(now)),
+            TaskAction::RunNow => update.col_expr(
+                background_tasks::Column::ScheduledFor,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            ),
+            TaskAction::Reschedule(scheduled_for) => update.col_expr(
+                background_tasks::Column::ScheduledFor,
+                Expr::value(*scheduled_for),
+            ),
+            TaskAction::SetMaxAttempts(max_attempts) => update.col_expr(
+                background_tasks::Column::MaxAttempts,
+                Expr::value(*max_attempts),
+            ),
+        };
+
+        let updated = update.exec_with_returning(db).await?;
+
+        if let TaskAction::Cancel = self {
+            let queue = state.task_queue();
+            for task in &updated {
+                publish_task_event(
+                    db,
+                    state.task_events(),
+                    TaskEvent::from_model(TaskEventKind::Canceled, task),
+                )
+                .await;
+                if let Some(batch_id) = task.batch_id {
+                    BatchService::record_finished(&queue, batch_id).await?;
+                }
+            }
+        }
+
+        Ok(updated)
+    }
+}
+
+async fn apply_to_task<S: BackgroundTasksStorage>(
+    state: &S,
+    id: i32,
+    action: TaskAction,
+) -> Result<Json<TaskResponse>, AdminTaskError> {
+    let db = state.db();
+    let task = background_tasks::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| AdminTaskError::not_found("Task not found"))?;
+
+    if !action.allowed_statuses().contains(&task.status.as_str()) {
+        return Err(AdminTaskError::bad_request(action.rejection()));
+    }
+
+    let updated = action
+        .apply(
+            state,
+            Condition::all().add(background_tasks::Column::Id.eq(id)),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AdminTaskError::conflict("Task changed status, try again"))?;
+
+    Ok(Json(TaskResponse::from(updated)))
+}
+
+async fn apply_to_matching<S: BackgroundTasksStorage>(
+    state: &S,
+    filter: TaskFilter,
+    action: TaskAction,
+) -> Result<Json<BulkActionResponse>, AdminTaskError> {
+    if filter.is_empty() {
+        return Err(AdminTaskError::bad_request(
+            "Bulk actions require at least one filter",
+        ));
+    }
+
+    let updated = action.apply(state, filter.condition()).await?;
+
+    Ok(Json(BulkActionResponse {
+        updated: updated.len() as u64,
+    }))
+}
+
+fn parse_scheduled_for(value: &str) -> Result<DateTime<Utc>, AdminTaskError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|d| d.with_timezone(&Utc))
+        .map_err(|_| AdminTaskError::bad_request("scheduled_for must be an RFC 3339 timestamp"))
+}
+
+fn validate_max_attempts(max_attempts: i32) -> Result<i32, AdminTaskError> {
+    if max_attempts < 1 {
+        return Err(AdminTaskError::bad_request(
+            "max_attempts must be at least 1",
+        ));
+    }
+    Ok(max_attempts)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RescheduleTaskRequest {
+    /// RFC 3339 timestamp
+    pub scheduled_for: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetMaxAttemptsRequest {
+    pub max_attempts: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkActionResponse {
+    /// Number of tasks the action was applied to
+    pub updated: u64,
+}
+
 #[utoipa::path(
     post,
     path = "/admin/tasks/{id}/cancel",
@@ -403,10 +718,11 @@ where
     ),
     responses(
         (status = 200, description = "Task canceled", body = TaskResponse),
-        (status = 400, description = "Task is not processing"),
+        (status = 400, description = "Task is not pending or processing"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Forbidden"),
         (status = 404, description = "Task not found"),
+        (status = 409, description = "Task changed status concurrently"),
     ),
     security(("bearer_auth" = [])),
     tag = "admin",
@@ -416,29 +732,434 @@ pub async fn cancel_task<S, A>(
     State(state): State<Arc<S>>,
     Path(id): Path<i32>,
 ) -> Result<Json<TaskResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    apply_to_task(&*state, id, TaskAction::Cancel).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tasks/{id}/run-now",
+    operation_id = "admin_run_task_now",
+    params(
+        ("id" = i32, Path, description = "Task ID")
+    ),
+    responses(
+        (status = 200, description = "Task schedule cleared", body = TaskResponse),
+        (status = 400, description = "Task is not pending"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Task not found"),
+        (status = 409, description = "Task changed status concurrently"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn run_task_now<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+) -> Result<Json<TaskResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    apply_to_task(&*state, id, TaskAction::RunNow).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tasks/{id}/reschedule",
+    operation_id = "admin_reschedule_task",
+    params(
+        ("id" = i32, Path, description = "Task ID")
+    ),
+    request_body = RescheduleTaskRequest,
+    responses(
+        (status = 200, description = "Task rescheduled", body = TaskResponse),
+        (status = 400, description = "Task is not pending or the timestamp is invalid"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Task not found"),
+        (status = 409, description = "Task changed status concurrently"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn reschedule_task<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+    Json(request): Json<RescheduleTaskRequest>,
+) -> Result<Json<TaskResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let scheduled_for = parse_scheduled_for(&request.scheduled_for)?;
+    apply_to_task(&*state, id, TaskAction::Reschedule(scheduled_for)).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tasks/{id}/max-attempts",
+    operation_id = "admin_set_task_max_attempts",
+    params(
+        ("id" = i32, Path, description = "Task ID")
+    ),
+    request_body = SetMaxAttemptsRequest,
+    responses(
+        (status = 200, description = "Task max attempts updated", body = TaskResponse),
+        (status = 400, description = "Task is not pending or processing, or max_attempts is invalid"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Task not found"),
+        (status = 409, description = "Task changed status concurrently"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn set_max_attempts<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+    Json(request): Json<SetMaxAttemptsRequest>,
+) -> Result<Json<TaskResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let max_attempts = validate_max_attempts(request.max_attempts)?;
+    apply_to_task(&*state, id, TaskAction::SetMaxAttempts(max_attempts)).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tasks/bulk/cancel",
+    operation_id = "admin_bulk_cancel_tasks",
+    params(TaskFilter),
+    responses(
+        (status = 200, description = "Matching pending and processing tasks canceled", body = BulkActionResponse),
+        (status = 400, description = "No filter given"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn bulk_cancel_tasks<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Query(filter): Query<TaskFilter>,
+) -> Result<Json<BulkActionResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    apply_to_matching(&*state, filter, TaskAction::Cancel).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tasks/bulk/run-now",
+    operation_id = "admin_bulk_run_tasks_now",
+    params(TaskFilter),
+    responses(
+        (status = 200, description = "Schedules cleared on matching pending tasks", body = BulkActionResponse),
+        (status = 400, description = "No filter given"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn bulk_run_tasks_now<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Query(filter): Query<TaskFilter>,
+) -> Result<Json<BulkActionResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    apply_to_matching(&*state, filter, TaskAction::RunNow).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tasks/bulk/reschedule",
+    operation_id = "admin_bulk_reschedule_tasks",
+    params(TaskFilter),
+    request_body = RescheduleTaskRequest,
+    responses(
+        (status = 200, description = "Matching pending tasks rescheduled", body = BulkActionResponse),
+        (status = 400, description = "No filter given or the timestamp is invalid"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn bulk_reschedule_tasks<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Query(filter): Query<TaskFilter>,
+    Json(request): Json<RescheduleTaskRequest>,
+) -> Result<Json<BulkActionResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let scheduled_for = parse_scheduled_for(&request.scheduled_for)?;
+    apply_to_matching(&*state, filter, TaskAction::Reschedule(scheduled_for)).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tasks/bulk/max-attempts",
+    operation_id = "admin_bulk_set_max_attempts",
+    params(TaskFilter),
+    request_body = SetMaxAttemptsRequest,
+    responses(
+        (status = 200, description = "Max attempts updated on matching pending and processing tasks", body = BulkActionResponse),
+        (status = 400, description = "No filter given or max_attempts is invalid"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn bulk_set_max_attempts<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Query(filter): Query<TaskFilter>,
+    Json(request): Json<SetMaxAttemptsRequest>,
+) -> Result<Json<BulkActionResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let max_attempts = validate_max_attempts(request.max_attempts)?;
+    apply_to_matching(&*state, filter, TaskAction::SetMaxAttempts(max_attempts)).await
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskStatsQuery {
+    /// One of `1h`, `24h`, `7d`, `30d`; defaults to `24h`
+    #[serde(default)]
+    #[param(value_type = Option<StatsWindow>)]
+    pub window: StatsWindow,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/tasks/stats",
+    operation_id = "admin_task_stats",
+    params(TaskStatsQuery),
+    responses(
+        (status = 200, description = "Per-task-type throughput, failure rate, duration and queue lag", body = TaskStatsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn task_stats<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Query(params): Query<TaskStatsQuery>,
+) -> Result<Json<TaskStatsResponse>, AdminTaskError>
 where
     S: BackgroundTasksStorage,
     A: AdminVerified,
 {
     let db = BackgroundTasksStorage::db(&*state);
-    let task = background_tasks::Entity::find_by_id(id)
-        .one(db)
-        .await?
-        .ok_or_else(|| AdminTaskError::not_found("Task not found"))?;
+    Ok(Json(TaskStatsResponse::collect(db, params.window).await?))
+}
 
-    if task.status != "processing" {
-        return Err(AdminTaskError::bad_request(
-            "Only processing tasks can be canceled",
-        ));
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskEventsQuery {
+    pub task_type: Option<String>,
+    /// Task status after the transition, e.g. `completed`
+    pub status: Option<String>,
+}
+
+impl TaskEventsQuery {
+    fn matches(&self, event: &TaskEvent) -> bool {
+        self.task_type
+            .as_ref()
+            .is_none_or(|t| *t == event.task_type)
+            && self.status.as_ref().is_none_or(|s| *s == event.status)
     }
+}
 
-    let now = Utc::now();
-    let mut active: background_tasks::ActiveModel = task.into();
-    active.status = Set("canceled".to_string());
-    active.error = Set(Some("Canceled by admin".to_string()));
-    active.completed_at = Set(Some(now));
-    active.updated_at = Set(now);
+#[utoipa::path(
+    get,
+    path = "/admin/tasks/events",
+    operation_id = "admin_task_events",
+    params(TaskEventsQuery),
+    responses(
+        (status = 200, description = "Server-sent stream of task status changes", body = TaskEvent, content_type = "text/event-stream"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 503, description = "Task events are not enabled"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn task_events<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Query(params): Query<TaskEventsQuery>,
+) -> Result<Sse<impl futures::Stream<Item = Result<Event, std::convert::Infallible>>>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let receiver = state
+        .task_events()
+        .ok_or_else(|| AdminTaskError::service_unavailable("Task events are not enabled"))?
+        .subscribe();
 
-    let updated = active.update(db).await?;
-    Ok(Json(TaskResponse::from(updated)))
+    let stream = futures::stream::unfold((receiver, params), |(mut receiver, params)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if params.matches(&event) => {
+                    let sse_event = Event::default()
+                        .event("task")
+                        .json_data(&event)
+                        .unwrap_or_else(|_| Event::default().comment("unserializable event"));
+                    return Some((Ok(sse_event), (receiver, params)));
+                }
+                Ok(_) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Task event stream lagged");
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    struct EventsState {
+        db: DatabaseConnection,
+        events: TaskEventBus,
+    }
+
+    impl BackgroundTasksStorage for EventsState {
+        fn db(&self) -> &DatabaseConnection {
+            &self.db
+        }
+
+        fn task_events(&self) -> Option<&TaskEventBus> {
+            Some(&self.events)
+        }
+
+        fn task_queue(&self) -> TaskQueue<DurableStorage> {
+            TaskQueue::new(DurableStorage::new(self.db.clone()).with_event_bus(self.events.clone()))
+        }
+    }
+
+    struct Admin;
+
+    impl AdminVerified for Admin {}
+
+    fn event(task_type: &str, status: &str) -> TaskEvent {
+        TaskEvent {
+            task_id: 1,
+            task_type: task_type.to_string(),
+            event: TaskEventKind::Completed,
+            status: status.to_string(),
+            attempts: 1,
+            error: None,
+            occurred_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_task_events_query_matches() {
+        let all = TaskEventsQuery {
+            task_type: None,
+            status: None,
+        };
+        assert!(all.matches(&event("report", "completed")));
+
+        let filtered = TaskEventsQuery {
+            task_type: Some("report".to_string()),
+            status: Some("failed".to_string()),
+        };
+        assert!(filtered.matches(&event("report", "failed")));
+        assert!(!filtered.matches(&event("report", "completed")));
+        assert!(!filtered.matches(&event("digest", "failed")));
+    }
+
+    #[tokio::test]
+    async fn test_task_events_streams_matching_events() {
+        let state = Arc::new(EventsState {
+            db: DatabaseConnection::default(),
+            events: TaskEventBus::default(),
+        });
+        let sse = task_events::<_, Admin>(
+            Admin,
+            State(Arc::clone(&state)),
+            Query(TaskEventsQuery {
+                task_type: Some("report".to_string()),
+                status: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let mut body = sse.into_response().into_body().into_data_stream();
+
+        state.events.publish(event("digest", "completed"));
+        state.events.publish(event("report", "completed"));
+
+        let chunk = body.next().await.unwrap().unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(text.starts_with("event: task\n"));
+        assert!(text.contains("\"task_type\":\"report\""));
+    }
+
+    #[tokio::test]
+    async fn test_task_events_unavailable_without_bus() {
+        struct NoEvents(DatabaseConnection);
+
+        impl BackgroundTasksStorage for NoEvents {
+            fn db(&self) -> &DatabaseConnection {
+                &self.0
+            }
+
+            fn task_queue(&self) -> TaskQueue<DurableStorage> {
+                TaskQueue::new(DurableStorage::new(self.0.clone()))
+            }
+        }
+
+        let result = task_events::<_, Admin>(
+            Admin,
+            State(Arc::new(NoEvents(DatabaseConnection::default()))),
+            Query(TaskEventsQuery {
+                task_type: None,
+                status: None,
+            }),
+        )
+        .await;
+        let Err(error) = result else {
+            panic!("expected the stream to be unavailable");
+        };
+        assert_eq!(
+            error.into_response().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
 }
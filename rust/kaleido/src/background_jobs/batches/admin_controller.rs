@@ -0,0 +1,162 @@
+use super::service::BatchService;
+use crate::background_jobs::admin::{
+    AdminTaskError, AdminVerified, BackgroundTasksStorage, PaginatedResponse,
+};
+use crate::background_jobs::entities::task_batches;
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+pub fn routes<S, A>() -> Router<Arc<S>>
+where
+    S: BackgroundTasksStorage + 'static,
+    A: AdminVerified + axum::extract::FromRequestParts<Arc<S>> + Send + 'static,
+    <A as axum::extract::FromRequestParts<Arc<S>>>::Rejection: IntoResponse,
+{
+    Router::new()
+        .route("/", get(list_batches::<S, A>))
+        .route("/:id", get(get_batch::<S, A>))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BatchListQuery {
+    #[serde(default = "default_page")]
+    pub page: u64,
+    #[serde(default = "default_per_page")]
+    pub per_page: u64,
+}
+
+fn default_page() -> u64 {
+    1
+}
+fn default_per_page() -> u64 {
+    20
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResponse {
+    pub id: i32,
+    pub name: Option<String>,
+    /// `open` while tasks may still be added, then `running` and `finished`
+    pub state: String,
+    pub total: i32,
+    pub completed: i32,
+    pub failed: i32,
+    pub remaining: i32,
+    /// Share of members in a terminal state, 0-100
+    pub progress_percent: f64,
+    pub callback_task_type: Option<String>,
+    pub callback_task_id: Option<i32>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub finished_at: Option<String>,
+}
+
+impl From<task_batches::Model> for BatchResponse {
+    fn from(m: task_batches::Model) -> Self {
+        let state = if m.finished_at.is_some() {
+            "finished"
+        } else if m.sealed {
+            "running"
+        } else {
+            "open"
+        };
+        let progress_percent = if m.total > 0 {
+            f64::from(m.completed + m.failed) * 100.0 / f64::from(m.total)
+        } else {
+            0.0
+        };
+
+        Self {
+            id: m.id,
+            name: m.name.clone(),
+            state: state.to_string(),
+            total: m.total,
+            completed: m.completed,
+            failed: m.failed,
+            remaining: m.remaining(),
+            progress_percent,
+            callback_task_type: m.callback_task_type,
+            callback_task_id: m.callback_task_id,
+            created_at: m.created_at.to_rfc3339(),
+            updated_at: m.updated_at.to_rfc3339(),
+            finished_at: m.finished_at.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/batches",
+    operation_id = "admin_list_batches",
+    params(BatchListQuery),
+    responses(
+        (status = 200, description = "Task batches with progress", body = PaginatedResponse<BatchResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn list_batches<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Query(params): Query<BatchListQuery>,
+) -> Result<Json<PaginatedResponse<BatchResponse>>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let db = BackgroundTasksStorage::db(&*state);
+    let page = params.page.max(1);
+    let per_page = params.per_page.clamp(1, 100);
+
+    let (items, total) = BatchService::list(db, page, per_page).await?;
+    let data: Vec<BatchResponse> = items.into_iter().map(BatchResponse::from).collect();
+
+    Ok(Json(PaginatedResponse::new(
+        data,
+        page as i64,
+        per_page as i64,
+        total as i64,
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/batches/{id}",
+    operation_id = "admin_get_batch",
+    params(
+        ("id" = i32, Path, description = "Batch ID")
+    ),
+    responses(
+        (status = 200, description = "Task batch with progress", body = BatchResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Batch not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn get_batch<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+) -> Result<Json<BatchResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let db = BackgroundTasksStorage::db(&*state);
+    let batch = BatchService::get(db, id)
+        .await?
+        .ok_or_else(|| AdminTaskError::not_found("Batch not found"))?;
+    Ok(Json(BatchResponse::from(batch)))
+}
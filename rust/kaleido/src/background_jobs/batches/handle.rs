@@ -0,0 +1,49 @@
+use crate::background_jobs::error::TaskError;
+use crate::background_jobs::queue::TaskQueue;
+use crate::background_jobs::storage::{EnqueueOptions, TaskRecord, TaskStorage};
+
+/// Enqueues tasks into a batch opened by [`TaskQueue::start_batch`]
+///
+/// Call [`BatchHandle::seal`] after the last task is enqueued; a batch that
+/// is never sealed never finishes.
+pub struct BatchHandle<S: TaskStorage> {
+    queue: TaskQueue<S>,
+    id: i32,
+}
+
+impl<S: TaskStorage> BatchHandle<S> {
+    pub(crate) fn new(queue: TaskQueue<S>, id: i32) -> Self {
+        Self { queue, id }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Enqueue a member task
+    pub async fn enqueue<T: serde::Serialize>(
+        &self,
+        task_type: String,
+        task: T,
+    ) -> Result<TaskRecord, TaskError> {
+        self.enqueue_with(task_type, task, EnqueueOptions::default())
+            .await
+    }
+
+    /// Enqueue a member task with custom options
+    pub async fn enqueue_with<T: serde::Serialize>(
+        &self,
+        task_type: String,
+        task: T,
+        options: EnqueueOptions,
+    ) -> Result<TaskRecord, TaskError> {
+        self.queue
+            .enqueue_with(task_type, task, options.with_batch_id(self.id))
+            .await
+    }
+
+    /// Stop adding tasks; the batch finishes once every member is done
+    pub async fn seal(self) -> Result<(), TaskError> {
+        self.queue.seal_batch(self.id).await
+    }
+}
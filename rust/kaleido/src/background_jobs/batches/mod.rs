@@ -0,0 +1,16 @@
+// Batches of related tasks
+//
+// A batch is opened with `TaskQueue::start_batch`, members are tagged with
+// its id as they are enqueued, and the batch is sealed once the caller is
+// done adding work. Members reaching a terminal state bump the aggregate
+// counts; the first update that sees a sealed batch with nothing remaining
+// marks it finished and enqueues the optional callback task, so the
+// callback fires exactly once even with several workers.
+
+pub mod admin_controller;
+pub mod handle;
+pub mod service;
+
+pub use admin_controller::routes as admin_routes;
+pub use handle::BatchHandle;
+pub use service::BatchService;
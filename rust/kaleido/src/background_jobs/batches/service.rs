@@ -0,0 +1,250 @@
+use crate::background_jobs::durable::DurableStorage;
+use crate::background_jobs::entities::{background_tasks, task_batches};
+use crate::background_jobs::error::TaskError;
+use crate::background_jobs::events::{publish_task_event, TaskEvent, TaskEventKind};
+use crate::background_jobs::queue::TaskQueue;
+use crate::background_jobs::storage::{BatchOptions, EnqueueOptions};
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityTrait, ExprTrait, NotSet, PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde_json::json;
+
+pub struct BatchService;
+
+impl BatchService {
+    pub async fn create(
+        db: &DatabaseConnection,
+        options: BatchOptions,
+    ) -> Result<task_batches::Model, DbErr> {
+        let now = Utc::now();
+        task_batches::ActiveModel {
+            id: NotSet,
+            name: Set(options.name),
+            total: Set(0),
+            completed: Set(0),
+            failed: Set(0),
+            sealed: Set(false),
+            callback_task_type: Set(options.callback_task_type),
+            callback_payload: Set(options.callback_payload),
+            callback_task_id: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+            finished_at: Set(None),
+        }
+        .insert(db)
+        .await
+    }
+
+    pub async fn get(
+        db: &DatabaseConnection,
+        id: i32,
+    ) -> Result<Option<task_batches::Model>, DbErr> {
+        task_batches::Entity::find_by_id(id).one(db).await
+    }
+
+    /// Newest batches first, with the total number of batches
+    pub async fn list(
+        db: &DatabaseConnection,
+        page: u64,
+        per_page: u64,
+    ) -> Result<(Vec<task_batches::Model>, u64), DbErr> {
+        let paginator = task_batches::Entity::find()
+            .order_by_desc(task_batches::Column::CreatedAt)
+            .paginate(db, per_page);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(page.saturating_sub(1)).await?;
+        Ok((items, total))
+    }
+
+    /// Count a newly enqueued member
+    ///
+    /// Called by `TaskQueue` in the transaction that inserts the member.
+    pub async fn record_enqueued<C: ConnectionTrait>(conn: &C, batch_id: i32) -> Result<(), DbErr> {
+        task_batches::Entity::update_many()
+            .col_expr(
+                task_batches::Column::Total,
+                Expr::col(task_batches::Column::Total).add(1),
+            )
+            .col_expr(task_batches::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(task_batches::Column::Id.eq(batch_id))
+            .exec(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Stop accepting members and finish the batch if they are all done
+    ///
+    /// The callback, if any, is enqueued through `queue`.
+    pub async fn seal(queue: &TaskQueue<DurableStorage>, batch_id: i32) -> Result<(), TaskError> {
+        let txn = queue.storage().db().begin().await.map_err(storage_error)?;
+        task_batches::Entity::update_many()
+            .col_expr(task_batches::Column::Sealed, Expr::value(true))
+            .col_expr(task_batches::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(task_batches::Column::Id.eq(batch_id))
+            .exec(&txn)
+            .await
+            .map_err(storage_error)?;
+
+        let callback = Self::finish_if_done(&txn, queue, batch_id).await?;
+        txn.commit().await.map_err(storage_error)?;
+        Self::publish_callback(queue, callback).await;
+        Ok(())
+    }
+
+    /// Recount a batch after a member reached a terminal status
+    ///
+    /// Completed members count as completed; failed, expired and canceled
+    /// members count as failed. The counts are read from `background_tasks`
+    /// rather than incremented, so a call lost after a member's status
+    /// changed is made up by the next one or by [`Self::finish_stalled`].
+    pub async fn record_finished(
+        queue: &TaskQueue<DurableStorage>,
+        batch_id: i32,
+    ) -> Result<(), TaskError> {
+        let txn = queue.storage().db().begin().await.map_err(storage_error)?;
+        let callback = Self::finish_if_done(&txn, queue, batch_id).await?;
+        txn.commit().await.map_err(storage_error)?;
+        Self::publish_callback(queue, callback).await;
+        Ok(())
+    }
+
+    /// Finish sealed batches whose members are all done but which were
+    /// never marked finished, e.g. because a worker died after its last
+    /// member's status change; returns how many were finished
+    pub async fn finish_stalled(queue: &TaskQueue<DurableStorage>) -> Result<usize, TaskError> {
+        let stalled = task_batches::Entity::find()
+            .filter(task_batches::Column::Sealed.eq(true))
+            .filter(task_batches::Column::FinishedAt.is_null())
+            .filter(Expr::cust(
+                "NOT EXISTS (SELECT 1 FROM background_tasks AS member \
+                 WHERE member.batch_id = task_batches.id \
+                 AND member.status IN ('pending', 'processing'))",
+            ))
+            .all(queue.storage().db())
+            .await
+            .map_err(storage_error)?;
+
+        let mut finished = 0;
+        for batch in stalled {
+            Self::record_finished(queue, batch.id).await?;
+            if Self::get(queue.storage().db(), batch.id)
+                .await
+                .map_err(storage_error)?
+                .is_some_and(|batch| batch.finished_at.is_some())
+            {
+                finished += 1;
+            }
+        }
+        Ok(finished)
+    }
+
+    /// Recount the batch, then mark it finished and enqueue its callback,
+    /// all on `txn`
+    ///
+    /// Returns the callback task so it can be announced after the commit.
+    async fn finish_if_done(
+        txn: &DatabaseTransaction,
+        queue: &TaskQueue<DurableStorage>,
+        batch_id: i32,
+    ) -> Result<Option<background_tasks::Model>, TaskError> {
+        let now = Utc::now();
+        task_batches::Entity::update_many()
+            .col_expr(
+                task_batches::Column::Completed,
+                Expr::cust(
+                    "(SELECT COUNT(*) FROM background_tasks AS member \
+                     WHERE member.batch_id = task_batches.id \
+                     AND member.status = 'completed')",
+                ),
+            )
+            .col_expr(
+                task_batches::Column::Failed,
+                Expr::cust(
+                    "(SELECT COUNT(*) FROM background_tasks AS member \
+                     WHERE member.batch_id = task_batches.id \
+                     AND member.status IN ('failed', 'expired', 'canceled'))",
+                ),
+            )
+            .col_expr(task_batches::Column::UpdatedAt, Expr::value(now))
+            .filter(task_batches::Column::Id.eq(batch_id))
+            .filter(task_batches::Column::FinishedAt.is_null())
+            .exec(txn)
+            .await
+            .map_err(storage_error)?;
+
+        // Only the update that flips finished_at goes on to enqueue the callback
+        let claimed = task_batches::Entity::update_many()
+            .col_expr(task_batches::Column::FinishedAt, Expr::value(now))
+            .col_expr(task_batches::Column::UpdatedAt, Expr::value(now))
+            .filter(task_batches::Column::Id.eq(batch_id))
+            .filter(task_batches::Column::Sealed.eq(true))
+            .filter(task_batches::Column::FinishedAt.is_null())
+            .filter(Expr::cust("\"completed\" + \"failed\" >= \"total\""))
+            .exec(txn)
+            .await
+            .map_err(storage_error)?;
+        if claimed.rows_affected == 0 {
+            return Ok(None);
+        }
+
+        let Some(batch) = task_batches::Entity::find_by_id(batch_id)
+            .one(txn)
+            .await
+            .map_err(storage_error)?
+        else {
+            return Ok(None);
+        };
+        tracing::info!(
+            batch_id,
+            total = batch.total,
+            completed = batch.completed,
+            failed = batch.failed,
+            "Background task batch finished"
+        );
+
+        let Some(callback_task_type) = batch.callback_task_type.clone() else {
+            return Ok(None);
+        };
+        let callback = queue
+            .enqueue_in(
+                txn,
+                callback_task_type,
+                json!({
+                    "batch_id": batch.id,
+                    "total": batch.total,
+                    "completed": batch.completed,
+                    "failed": batch.failed,
+                    "data": batch.callback_payload.clone().unwrap_or_else(|| json!({})),
+                }),
+                EnqueueOptions::default(),
+            )
+            .await?;
+
+        let mut active: task_batches::ActiveModel = batch.into();
+        active.callback_task_id = Set(Some(callback.id));
+        active.update(txn).await.map_err(storage_error)?;
+
+        Ok(Some(callback))
+    }
+
+    async fn publish_callback(
+        queue: &TaskQueue<DurableStorage>,
+        callback: Option<background_tasks::Model>,
+    ) {
+        if let Some(callback) = callback {
+            publish_task_event(
+                queue.storage().db(),
+                queue.storage().events(),
+                TaskEvent::from_model(TaskEventKind::Enqueued, &callback),
+            )
+            .await;
+        }
+    }
+}
+
+fn storage_error(e: DbErr) -> TaskError {
+    TaskError::Storage(e.to_string())
+}
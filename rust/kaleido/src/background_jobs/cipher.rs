@@ -0,0 +1,240 @@
+// Encryption at rest for sensitive task payloads
+//
+// Payloads of task types marked sensitive are sealed with AES-256-GCM before
+// they reach storage and opened again by the worker. Every envelope records
+// the id of the key that sealed it, so keys can be rotated by adding a new
+// active key while keeping the old one around for tasks already queued.
+
+use crate::background_jobs::error::TaskError;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::RngCore;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+const ALGORITHM: &str = "aes-256-gcm";
+const NONCE_LEN: usize = 12;
+
+/// Seals and opens task payloads with a set of AEAD keys
+#[derive(Clone)]
+pub struct PayloadCipher {
+    active_key_id: String,
+    keys: HashMap<String, Aes256Gcm>,
+}
+
+impl fmt::Debug for PayloadCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PayloadCipher")
+            .field("active_key_id", &self.active_key_id)
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl PayloadCipher {
+    /// Create a cipher that seals new payloads with `key`
+    pub fn new(key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        let key_id = key_id.into();
+        let mut keys = HashMap::new();
+        keys.insert(
+            key_id.clone(),
+            Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        );
+        Self {
+            active_key_id: key_id,
+            keys,
+        }
+    }
+
+    /// Keep a retired key around so payloads sealed with it can still be opened
+    pub fn with_key(mut self, key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        self.keys.insert(
+            key_id.into(),
+            Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        );
+        self
+    }
+
+    /// Load keys from `TASK_PAYLOAD_KEYS` (`kid:base64key,kid:base64key`)
+    ///
+    /// The first key seals new payloads unless `TASK_PAYLOAD_ACTIVE_KEY_ID`
+    /// names another one. Returns `Ok(None)` when no keys are configured.
+    pub fn from_env() -> Result<Option<Self>, TaskError> {
+        let Ok(raw) = std::env::var("TASK_PAYLOAD_KEYS") else {
+            return Ok(None);
+        };
+        let active = std::env::var("TASK_PAYLOAD_ACTIVE_KEY_ID").ok();
+        Self::parse_keys(&raw, active.as_deref())
+    }
+
+    fn parse_keys(raw: &str, active_key_id: Option<&str>) -> Result<Option<Self>, TaskError> {
+        let mut cipher: Option<Self> = None;
+
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key_id, encoded) = entry.split_once(':').ok_or_else(|| {
+                TaskError::Encryption("payload keys must be formatted as kid:base64key".into())
+            })?;
+            let key: [u8; 32] = STANDARD
+                .decode(encoded.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| {
+                    TaskError::Encryption(format!(
+                        "payload key {} must be 32 base64-encoded bytes",
+                        key_id
+                    ))
+                })?;
+
+            cipher = Some(match cipher {
+                Some(cipher) => cipher.with_key(key_id.trim(), key),
+                None => Self::new(key_id.trim(), key),
+            });
+        }
+
+        let Some(mut cipher) = cipher else {
+            return Ok(None);
+        };
+        if let Some(active_key_id) = active_key_id {
+            if !cipher.keys.contains_key(active_key_id) {
+                return Err(TaskError::Encryption(format!(
+                    "active payload key {} is not configured",
+                    active_key_id
+                )));
+            }
+            cipher.active_key_id = active_key_id.to_string();
+        }
+
+        Ok(Some(cipher))
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    /// Whether `payload` is a sealed envelope rather than plain task data
+    pub fn is_encrypted(payload: &Value) -> bool {
+        payload.get("enc").and_then(Value::as_str) == Some(ALGORITHM)
+    }
+
+    /// Seal `payload` with the active key, bound to `task_type`
+    pub fn encrypt(&self, task_type: &str, payload: &Value) -> Result<Value, TaskError> {
+        let cipher = &self.keys[&self.active_key_id];
+        let plaintext = serde_json::to_vec(payload)?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: task_type.as_bytes(),
+                },
+            )
+            .map_err(|_| TaskError::Encryption("failed to encrypt task payload".into()))?;
+
+        Ok(json!({
+            "enc": ALGORITHM,
+            "kid": self.active_key_id,
+            "nonce": STANDARD.encode(nonce),
+            "ciphertext": STANDARD.encode(ciphertext),
+        }))
+    }
+
+    /// Open an envelope produced by [`PayloadCipher::encrypt`]
+    pub fn decrypt(&self, task_type: &str, envelope: &Value) -> Result<Value, TaskError> {
+        let field = |name: &str| {
+            envelope
+                .get(name)
+                .and_then(Value::as_str)
+                .ok_or_else(|| TaskError::Encryption(format!("encrypted payload missing {}", name)))
+        };
+
+        let key_id = field("kid")?;
+        let cipher = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| TaskError::Encryption(format!("unknown payload key id {}", key_id)))?;
+        let nonce = STANDARD
+            .decode(field("nonce")?)
+            .ok()
+            .filter(|nonce| nonce.len() == NONCE_LEN)
+            .ok_or_else(|| TaskError::Encryption("invalid payload nonce".into()))?;
+        let ciphertext = STANDARD
+            .decode(field("ciphertext")?)
+            .map_err(|_| TaskError::Encryption("invalid payload ciphertext".into()))?;
+
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: task_type.as_bytes(),
+                },
+            )
+            .map_err(|_| TaskError::Encryption("failed to decrypt task payload".into()))?;
+
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Safe-to-display stand-in for an encrypted payload
+    pub fn redact(envelope: &Value) -> Value {
+        json!({
+            "redacted": true,
+            "kid": envelope.get("kid").cloned().unwrap_or(Value::Null),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let cipher = PayloadCipher::new("k1", [7u8; 32]);
+        let payload = json!({"reset_url": "https://example.com/reset?token=secret"});
+
+        let envelope = cipher.encrypt("email_password_reset", &payload).unwrap();
+        assert!(PayloadCipher::is_encrypted(&envelope));
+        assert!(!envelope.to_string().contains("secret"));
+        assert_eq!(
+            cipher.decrypt("email_password_reset", &envelope).unwrap(),
+            payload
+        );
+        assert!(cipher.decrypt("email_registration", &envelope).is_err());
+        assert_eq!(PayloadCipher::redact(&envelope)["kid"], "k1");
+    }
+
+    #[test]
+    fn test_rotated_key_still_decrypts() {
+        let old = PayloadCipher::new("k1", [1u8; 32]);
+        let envelope = old.encrypt("t", &json!({"a": 1})).unwrap();
+
+        let rotated = PayloadCipher::new("k2", [2u8; 32]).with_key("k1", [1u8; 32]);
+        assert_eq!(rotated.decrypt("t", &envelope).unwrap(), json!({"a": 1}));
+        assert_eq!(rotated.encrypt("t", &json!({})).unwrap()["kid"], "k2");
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let k1 = STANDARD.encode([1u8; 32]);
+        let k2 = STANDARD.encode([2u8; 32]);
+        let raw = format!("k1:{},k2:{}", k1, k2);
+
+        let cipher = PayloadCipher::parse_keys(&raw, None).unwrap().unwrap();
+        assert_eq!(cipher.active_key_id(), "k1");
+        let cipher = PayloadCipher::parse_keys(&raw, Some("k2"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(cipher.active_key_id(), "k2");
+
+        assert!(PayloadCipher::parse_keys("", None).unwrap().is_none());
+        assert!(PayloadCipher::parse_keys("k1:short", None).is_err());
+        assert!(PayloadCipher::parse_keys(&raw, Some("k3")).is_err());
+    }
+}
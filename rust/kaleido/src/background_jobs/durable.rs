@@ -1,15 +1,22 @@
-// PostgreSQL-backed durable task storage
+// Database-backed durable task storage (Postgres or SQLite)
 //
 // This implementation stores tasks in a database table for persistence
 // and durability. Tasks survive application restarts.
 
+use crate::background_jobs::batches::BatchService;
+use crate::background_jobs::entities::background_tasks;
 use crate::background_jobs::error::TaskError;
-use crate::background_jobs::storage::{TaskRecord, TaskStatus, TaskStorage};
+use crate::background_jobs::events::{publish_task_event, TaskEvent, TaskEventBus, TaskEventKind};
+use crate::background_jobs::queue::TaskQueue;
+use crate::background_jobs::storage::{
+    BatchOptions, EnqueueOptions, TaskRecord, TaskStatus, TaskStorage,
+};
 use async_trait::async_trait;
 use chrono::Utc;
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, NotSet, QueryFilter,
-    QueryOrder, QuerySelect, Set,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 
 // Re-export the background_tasks entity
@@ -26,11 +33,16 @@ mod background_tasks_entity {
         pub id: i32,
         pub task_type: String,
         pub payload: Json,
+        pub payload_version: i32,
+        pub concurrency_key: Option<String>,
+        pub batch_id: Option<i32>,
+        pub metadata: Option<Json>,
         pub status: String,
         pub attempts: i32,
         pub max_attempts: i32,
         pub error: Option<String>,
         pub scheduled_for: Option<DateTime<Utc>>,
+        pub expires_at: Option<DateTime<Utc>>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
         pub started_at: Option<DateTime<Utc>>,
@@ -43,56 +55,136 @@ mod background_tasks_entity {
     impl ActiveModelBehavior for ActiveModel {}
 }
 
-/// Durable storage backed by PostgreSQL
+/// Durable storage backed by Postgres or SQLite
 #[derive(Clone)]
 pub struct DurableStorage {
     db: DatabaseConnection,
+    events: Option<TaskEventBus>,
 }
 
 impl DurableStorage {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self { db, events: None }
+    }
+
+    pub fn db(&self) -> &DatabaseConnection {
+        &self.db
+    }
+
+    pub fn events(&self) -> Option<&TaskEventBus> {
+        self.events.as_ref()
+    }
+
+    /// Count a member that reached a terminal status toward its batch
+    ///
+    /// A callback enqueued from here uses a plain queue over this storage;
+    /// workers and the admin API enqueue it through their configured queue.
+    async fn record_batch_member(&self, task: &Model) -> Result<(), TaskError> {
+        let Some(batch_id) = task.batch_id else {
+            return Ok(());
+        };
+        if task.status == TaskStatus::Pending.as_str() {
+            return Ok(());
+        }
+
+        BatchService::record_finished(&TaskQueue::new(self.clone()), batch_id).await
+    }
+
+    /// Also deliver enqueue events to an in-process bus
+    ///
+    /// Postgres NOTIFY is sent either way unless turned off with
+    /// [`set_postgres_notify`](crate::background_jobs::events::set_postgres_notify).
+    pub fn with_event_bus(mut self, events: TaskEventBus) -> Self {
+        self.events = Some(events);
+        self
     }
 }
 
+/// Insert a pending task, counting it toward its batch
+///
+/// Run on a transaction so the batch total and the task commit together.
+pub(crate) async fn insert_task<C: ConnectionTrait>(
+    conn: &C,
+    task_type: String,
+    payload: serde_json::Value,
+    options: EnqueueOptions,
+) -> Result<background_tasks::Model, DbErr> {
+    if let Some(batch_id) = options.batch_id {
+        BatchService::record_enqueued(conn, batch_id).await?;
+    }
+
+    let now = Utc::now();
+    background_tasks::ActiveModel {
+        id: NotSet,
+        task_type: Set(task_type),
+        payload: Set(payload),
+        payload_version: Set(options.payload_version()),
+        concurrency_key: Set(options.concurrency_key),
+        batch_id: Set(options.batch_id),
+        metadata: Set(options.metadata),
+        status: Set(TaskStatus::Pending.as_str().to_string()),
+        attempts: Set(0),
+        max_attempts: Set(options.max_attempts),
+        error: Set(None),
+        error_kind: Set(None),
+        scheduled_for: Set(options.scheduled_for),
+        expires_at: Set(options.expires_at),
+        created_at: Set(now),
+        updated_at: Set(now),
+        started_at: Set(None),
+        completed_at: Set(None),
+        result: Set(None),
+    }
+    .insert(conn)
+    .await
+}
+
 #[async_trait]
 impl TaskStorage for DurableStorage {
     async fn enqueue(
         &self,
         task_type: String,
         payload: serde_json::Value,
-        scheduled_for: Option<chrono::DateTime<Utc>>,
-        max_attempts: i32,
+        options: EnqueueOptions,
     ) -> Result<TaskRecord, TaskError> {
-        let active_model = ActiveModel {
-            id: NotSet,
-            task_type: Set(task_type),
-            payload: Set(payload.clone().into()),
-            status: Set(TaskStatus::Pending.as_str().to_string()),
-            attempts: Set(0),
-            max_attempts: Set(max_attempts),
-            error: Set(None),
-            scheduled_for: Set(scheduled_for),
-            created_at: Set(Utc::now()),
-            updated_at: Set(Utc::now()),
-            started_at: Set(None),
-            completed_at: Set(None),
-        };
-
-        let model = active_model
-            .insert(&self.db)
-            .await
-            .map_err(|e| TaskError::Storage(e.to_string()))?;
+        let model = async {
+            let txn = self.db.begin().await?;
+            let model = insert_task(&txn, task_type, payload.clone(), options).await?;
+            txn.commit().await?;
+            Ok::<_, DbErr>(model)
+        }
+        .await
+        .map_err(|e| TaskError::Storage(e.to_string()))?;
+
+        publish_task_event(
+            &self.db,
+            self.events.as_ref(),
+            TaskEvent {
+                task_id: model.id,
+                task_type: model.task_type.clone(),
+                event: TaskEventKind::Enqueued,
+                status: model.status.clone(),
+                attempts: model.attempts,
+                error: None,
+                occurred_at: model.created_at,
+            },
+        )
+        .await;
 
         Ok(TaskRecord {
             id: model.id.to_string(),
             task_type: model.task_type,
             payload,
+            payload_version: model.payload_version,
+            concurrency_key: model.concurrency_key,
+            batch_id: model.batch_id,
+            metadata: model.metadata,
             status: TaskStatus::from_str(&model.status).unwrap_or(TaskStatus::Pending),
             attempts: model.attempts,
             max_attempts: model.max_attempts,
             error: model.error,
             scheduled_for: model.scheduled_for,
+            expires_at: model.expires_at,
             created_at: model.created_at,
             updated_at: model.updated_at,
             started_at: model.started_at,
@@ -120,11 +212,16 @@ impl TaskStorage for DurableStorage {
                 id: m.id.to_string(),
                 task_type: m.task_type,
                 payload: m.payload.clone(),
+                payload_version: m.payload_version,
+                concurrency_key: m.concurrency_key,
+                batch_id: m.batch_id,
+                metadata: m.metadata,
                 status: TaskStatus::from_str(&m.status).unwrap_or(TaskStatus::Pending),
                 attempts: m.attempts,
                 max_attempts: m.max_attempts,
                 error: m.error,
                 scheduled_for: m.scheduled_for,
+                expires_at: m.expires_at,
                 created_at: m.created_at,
                 updated_at: m.updated_at,
                 started_at: m.started_at,
@@ -159,11 +256,16 @@ impl TaskStorage for DurableStorage {
             id: updated.id.to_string(),
             task_type: updated.task_type,
             payload: updated.payload.clone(),
+            payload_version: updated.payload_version,
+            concurrency_key: updated.concurrency_key,
+            batch_id: updated.batch_id,
+            metadata: updated.metadata,
             status: TaskStatus::from_str(&updated.status).unwrap_or(TaskStatus::Processing),
             attempts: updated.attempts,
             max_attempts: updated.max_attempts,
             error: updated.error,
             scheduled_for: updated.scheduled_for,
+            expires_at: updated.expires_at,
             created_at: updated.created_at,
             updated_at: updated.updated_at,
             started_at: updated.started_at,
@@ -191,16 +293,22 @@ impl TaskStorage for DurableStorage {
             .update(&self.db)
             .await
             .map_err(|e| TaskError::Storage(e.to_string()))?;
+        self.record_batch_member(&updated).await?;
 
         Ok(TaskRecord {
             id: updated.id.to_string(),
             task_type: updated.task_type,
             payload: updated.payload.clone(),
+            payload_version: updated.payload_version,
+            concurrency_key: updated.concurrency_key,
+            batch_id: updated.batch_id,
+            metadata: updated.metadata,
             status: TaskStatus::from_str(&updated.status).unwrap_or(TaskStatus::Completed),
             attempts: updated.attempts,
             max_attempts: updated.max_attempts,
             error: updated.error,
             scheduled_for: updated.scheduled_for,
+            expires_at: updated.expires_at,
             created_at: updated.created_at,
             updated_at: updated.updated_at,
             started_at: updated.started_at,
@@ -234,16 +342,74 @@ impl TaskStorage for DurableStorage {
             .update(&self.db)
             .await
             .map_err(|e| TaskError::Storage(e.to_string()))?;
+        self.record_batch_member(&updated).await?;
 
         Ok(TaskRecord {
             id: updated.id.to_string(),
             task_type: updated.task_type,
             payload: updated.payload.clone(),
+            payload_version: updated.payload_version,
+            concurrency_key: updated.concurrency_key,
+            batch_id: updated.batch_id,
+            metadata: updated.metadata,
             status: TaskStatus::from_str(&updated.status).unwrap_or(TaskStatus::Failed),
             attempts: updated.attempts,
             max_attempts: updated.max_attempts,
             error: updated.error,
             scheduled_for: updated.scheduled_for,
+            expires_at: updated.expires_at,
+            created_at: updated.created_at,
+            updated_at: updated.updated_at,
+            started_at: updated.started_at,
+            completed_at: updated.completed_at,
+        })
+    }
+
+    async fn mark_expired(&self, id: &str) -> Result<TaskRecord, TaskError> {
+        let id_int: i32 = id
+            .parse()
+            .map_err(|_| TaskError::Storage("Invalid task ID".to_string()))?;
+
+        // Only a task still pending expires; one claimed or canceled in the
+        // meantime is returned unchanged and not counted toward its batch again
+        let now = Utc::now();
+        let result = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(TaskStatus::Expired.as_str()))
+            .col_expr(
+                Column::Error,
+                Expr::value("Task expired before it could be processed"),
+            )
+            .col_expr(Column::CompletedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(id_int))
+            .filter(Column::Status.eq(TaskStatus::Pending.as_str()))
+            .exec(&self.db)
+            .await
+            .map_err(|e| TaskError::Storage(e.to_string()))?;
+
+        let updated = Entity::find_by_id(id_int)
+            .one(&self.db)
+            .await
+            .map_err(|e| TaskError::Storage(e.to_string()))?
+            .ok_or(TaskError::NotFound)?;
+        if result.rows_affected == 1 {
+            self.record_batch_member(&updated).await?;
+        }
+
+        Ok(TaskRecord {
+            id: updated.id.to_string(),
+            task_type: updated.task_type,
+            payload: updated.payload.clone(),
+            payload_version: updated.payload_version,
+            concurrency_key: updated.concurrency_key,
+            batch_id: updated.batch_id,
+            metadata: updated.metadata,
+            status: TaskStatus::from_str(&updated.status).unwrap_or(TaskStatus::Expired),
+            attempts: updated.attempts,
+            max_attempts: updated.max_attempts,
+            error: updated.error,
+            scheduled_for: updated.scheduled_for,
+            expires_at: updated.expires_at,
             created_at: updated.created_at,
             updated_at: updated.updated_at,
             started_at: updated.started_at,
@@ -265,15 +431,31 @@ impl TaskStorage for DurableStorage {
             id: m.id.to_string(),
             task_type: m.task_type,
             payload: m.payload.clone(),
+            payload_version: m.payload_version,
+            concurrency_key: m.concurrency_key,
+            batch_id: m.batch_id,
+            metadata: m.metadata,
             status: TaskStatus::from_str(&m.status).unwrap_or(TaskStatus::Pending),
             attempts: m.attempts,
             max_attempts: m.max_attempts,
             error: m.error,
             scheduled_for: m.scheduled_for,
+            expires_at: m.expires_at,
             created_at: m.created_at,
             updated_at: m.updated_at,
             started_at: m.started_at,
             completed_at: m.completed_at,
         }))
     }
+
+    async fn create_batch(&self, options: BatchOptions) -> Result<i32, TaskError> {
+        BatchService::create(&self.db, options)
+            .await
+            .map(|batch| batch.id)
+            .map_err(|e| TaskError::Storage(e.to_string()))
+    }
+
+    async fn seal_batch(&self, batch_id: i32) -> Result<(), TaskError> {
+        BatchService::seal(&TaskQueue::new(self.clone()), batch_id).await
+    }
 }
@@ -1,6 +1,10 @@
 use chrono::{DateTime, Utc};
 use sea_orm::entity::prelude::*;
-use sea_orm::{Condition, DatabaseConnection, DbErr, QueryFilter, QueryOrder, QuerySelect, Set};
+use sea_orm::sea_query::SimpleExpr;
+use sea_orm::{
+    Condition, DatabaseConnection, DbErr, ExprTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    SqlErr, UpdateMany,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
@@ -10,11 +14,21 @@ pub struct Model {
     pub id: i32,
     pub task_type: String,
     pub payload: Json,
+    pub payload_version: i32,
+    /// Tasks sharing a key never run concurrently
+    pub concurrency_key: Option<String>,
+    /// Batch this task was enqueued into, if any
+    pub batch_id: Option<i32>,
+    /// Propagated context such as the enqueuing trace's `traceparent`
+    pub metadata: Option<Json>,
     pub status: String,
     pub attempts: i32,
     pub max_attempts: i32,
     pub error: Option<String>,
+    /// How the last failure was classified: `permanent`, `retry` or `snooze`
+    pub error_kind: Option<String>,
     pub scheduled_for: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
@@ -34,6 +48,7 @@ pub enum TaskStatus {
     Canceled,
     Completed,
     Failed,
+    Expired,
 }
 
 impl TaskStatus {
@@ -44,6 +59,7 @@ impl TaskStatus {
             TaskStatus::Canceled => "canceled",
             TaskStatus::Completed => "completed",
             TaskStatus::Failed => "failed",
+            TaskStatus::Expired => "expired",
         }
     }
 
@@ -54,11 +70,51 @@ impl TaskStatus {
             "canceled" => Some(TaskStatus::Canceled),
             "completed" => Some(TaskStatus::Completed),
             "failed" => Some(TaskStatus::Failed),
+            "expired" => Some(TaskStatus::Expired),
             _ => None,
         }
     }
 }
 
+/// How often a worker refreshes `updated_at` on the task it is running
+pub const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Processing tasks without a heartbeat for this long are assumed abandoned
+/// by a crashed worker
+pub const HEARTBEAT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+fn heartbeat_cutoff() -> DateTime<Utc> {
+    Utc::now() - chrono::Duration::from_std(HEARTBEAT_TIMEOUT).unwrap_or(chrono::Duration::zero())
+}
+
+/// No other task with the same concurrency key is currently processing
+///
+/// Tasks whose heartbeat timed out don't hold their key.
+fn concurrency_key_free() -> Condition {
+    Condition::any()
+        .add(Column::ConcurrencyKey.is_null())
+        .add(Expr::cust_with_values(
+            "NOT EXISTS (SELECT 1 FROM background_tasks AS running \
+             WHERE running.status = 'processing' \
+             AND running.concurrency_key = background_tasks.concurrency_key \
+             AND running.updated_at >= ?)",
+            [heartbeat_cutoff()],
+        ))
+}
+
+/// Failed once out of attempts, otherwise back to pending
+fn retry_status() -> SimpleExpr {
+    Expr::cust("CASE WHEN \"attempts\" >= \"max_attempts\" THEN 'failed' ELSE 'pending' END")
+}
+
+/// `now` for tasks `retry_status` fails, like any other failure path
+fn completed_at_if_out_of_attempts(now: DateTime<Utc>) -> SimpleExpr {
+    Expr::cust_with_values(
+        "CASE WHEN \"attempts\" >= \"max_attempts\" THEN ? ELSE \"completed_at\" END",
+        [now],
+    )
+}
+
 impl Model {
     /// Find pending tasks ready to be processed
     pub async fn find_pending(db: &DatabaseConnection, limit: u64) -> Result<Vec<Self>, DbErr> {
@@ -69,12 +125,95 @@ impl Model {
                     .add(Column::ScheduledFor.is_null())
                     .add(Column::ScheduledFor.lte(Utc::now())),
             )
+            .filter(concurrency_key_free())
             .order_by_asc(Column::CreatedAt)
             .limit(limit)
             .all(db)
             .await
     }
 
+    /// Whether the task missed its deadline and should no longer be processed
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    /// Mark a pending task as expired without processing it
+    ///
+    /// Returns `None` when the task already left `pending`, e.g. another
+    /// worker claimed it or an admin canceled it.
+    pub async fn mark_expired(&self, db: &DatabaseConnection) -> Result<Option<Model>, DbErr> {
+        let now = Utc::now();
+        let result = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(TaskStatus::Expired.as_str()))
+            .col_expr(
+                Column::Error,
+                Expr::value("Task expired before it could be processed"),
+            )
+            .col_expr(Column::CompletedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(self.id))
+            .filter(Column::Status.eq(TaskStatus::Pending.as_str()))
+            .exec(db)
+            .await?;
+
+        if result.rows_affected != 1 {
+            return Ok(None);
+        }
+        Entity::find_by_id(self.id).one(db).await
+    }
+
+    /// Atomically move a pending task to processing
+    ///
+    /// Returns `None` when another worker claimed the task first or a task
+    /// with the same concurrency key is already processing.
+    pub async fn claim(&self, db: &DatabaseConnection) -> Result<Option<Model>, DbErr> {
+        let now = Utc::now();
+        let result = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(TaskStatus::Processing.as_str()))
+            .col_expr(Column::StartedAt, Expr::value(now))
+            .col_expr(Column::Attempts, Expr::col(Column::Attempts).add(1))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(self.id))
+            .filter(Column::Status.eq(TaskStatus::Pending.as_str()))
+            .filter(concurrency_key_free())
+            .exec(db)
+            .await;
+
+        match result {
+            Ok(result) if result.rows_affected == 0 => Ok(None),
+            Ok(_) => Entity::find_by_id(self.id).one(db).await,
+            // Lost a race for the key against another worker; the partial
+            // unique index on processing keys rejected the second claim
+            Err(err) if matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Release processing tasks whose worker stopped sending heartbeats
+    ///
+    /// The attempt the lost worker used counts: tasks with attempts left
+    /// return to pending, the rest fail. Either way the concurrency key is
+    /// free again.
+    pub async fn release_abandoned(db: &DatabaseConnection) -> Result<Vec<Model>, DbErr> {
+        let now = Utc::now();
+        Entity::update_many()
+            .col_expr(Column::Status, retry_status())
+            .col_expr(Column::CompletedAt, completed_at_if_out_of_attempts(now))
+            .col_expr(
+                Column::Error,
+                Expr::value("Worker stopped sending heartbeats"),
+            )
+            .col_expr(Column::ErrorKind, Expr::value("retry"))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Status.eq(TaskStatus::Processing.as_str()))
+            .filter(Column::UpdatedAt.lt(heartbeat_cutoff()))
+            .exec_with_returning(db)
+            .await
+    }
+
     /// Mark task as processing
     pub async fn mark_processing(&self, db: &DatabaseConnection) -> Result<Model, DbErr> {
         let mut active: ActiveModel = self.clone().into();
@@ -104,7 +243,10 @@ impl Model {
     }
 
     /// Mark task as completed
-    pub async fn mark_completed(&self, db: &DatabaseConnection) -> Result<Model, DbErr> {
+    ///
+    /// Like every outcome a worker records, this only applies while the task
+    /// is still processing and returns `None` once an admin canceled it.
+    pub async fn mark_completed(&self, db: &DatabaseConnection) -> Result<Option<Model>, DbErr> {
         self.mark_completed_with_result(db, None).await
     }
 
@@ -113,13 +255,14 @@ impl Model {
         &self,
         db: &DatabaseConnection,
         result: Option<String>,
-    ) -> Result<Model, DbErr> {
-        let mut active: ActiveModel = self.clone().into();
-        active.status = Set(TaskStatus::Completed.as_str().to_string());
-        active.completed_at = Set(Some(Utc::now()));
-        active.updated_at = Set(Utc::now());
-        active.result = Set(result);
-        active.update(db).await
+    ) -> Result<Option<Model>, DbErr> {
+        let now = Utc::now();
+        let update = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(TaskStatus::Completed.as_str()))
+            .col_expr(Column::CompletedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .col_expr(Column::Result, Expr::value(result));
+        self.finish_processing(db, update).await
     }
 
     /// Mark task as failed
@@ -127,19 +270,89 @@ impl Model {
         &self,
         db: &DatabaseConnection,
         error: String,
-    ) -> Result<Model, DbErr> {
-        let mut active: ActiveModel = self.clone().into();
-        active.error = Set(Some(error));
-        active.updated_at = Set(Utc::now());
+    ) -> Result<Option<Model>, DbErr> {
+        self.mark_retry(db, error, None).await
+    }
 
-        // If max attempts reached, mark as failed permanently
-        if self.attempts >= self.max_attempts {
-            active.status = Set(TaskStatus::Failed.as_str().to_string());
-        } else {
-            // Otherwise, set back to pending for retry
-            active.status = Set(TaskStatus::Pending.as_str().to_string());
+    /// Record a retryable failure
+    ///
+    /// The task returns to pending, optionally not before `delay`, until it
+    /// runs out of attempts. The attempt limit is read in the UPDATE so a
+    /// change an admin made while the task ran is respected.
+    pub async fn mark_retry(
+        &self,
+        db: &DatabaseConnection,
+        error: String,
+        delay: Option<std::time::Duration>,
+    ) -> Result<Option<Model>, DbErr> {
+        let now = Utc::now();
+        let mut update = Entity::update_many()
+            .col_expr(Column::Status, retry_status())
+            .col_expr(Column::CompletedAt, completed_at_if_out_of_attempts(now))
+            .col_expr(Column::Error, Expr::value(error))
+            .col_expr(Column::ErrorKind, Expr::value("retry"))
+            .col_expr(Column::UpdatedAt, Expr::value(now));
+        if let Some(delay) = delay.and_then(|d| chrono::Duration::from_std(d).ok()) {
+            update = update.col_expr(Column::ScheduledFor, Expr::value(now + delay));
         }
+        self.finish_processing(db, update).await
+    }
 
-        active.update(db).await
+    /// Fail the task without using its remaining attempts
+    pub async fn mark_failed_permanently(
+        &self,
+        db: &DatabaseConnection,
+        error: String,
+    ) -> Result<Option<Model>, DbErr> {
+        let now = Utc::now();
+        let update = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(TaskStatus::Failed.as_str()))
+            .col_expr(Column::Error, Expr::value(error))
+            .col_expr(Column::ErrorKind, Expr::value("permanent"))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .col_expr(Column::CompletedAt, Expr::value(now));
+        self.finish_processing(db, update).await
+    }
+
+    /// Put the task back to pending after `delay` without consuming an attempt
+    pub async fn snooze(
+        &self,
+        db: &DatabaseConnection,
+        delay: std::time::Duration,
+    ) -> Result<Option<Model>, DbErr> {
+        let now = Utc::now();
+        let delay = chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero());
+        let update = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(TaskStatus::Pending.as_str()))
+            .col_expr(
+                Column::Attempts,
+                Expr::value(Ord::max(self.attempts - 1, 0)),
+            )
+            .col_expr(Column::ErrorKind, Expr::value("snooze"))
+            .col_expr(Column::ScheduledFor, Expr::value(now + delay))
+            .col_expr(
+                Column::StartedAt,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(now));
+        self.finish_processing(db, update).await
+    }
+
+    /// Apply a worker's outcome only if the task is still processing
+    async fn finish_processing(
+        &self,
+        db: &DatabaseConnection,
+        update: UpdateMany<Entity>,
+    ) -> Result<Option<Model>, DbErr> {
+        let result = update
+            .filter(Column::Id.eq(self.id))
+            .filter(Column::Status.eq(TaskStatus::Processing.as_str()))
+            .exec(db)
+            .await?;
+
+        if result.rows_affected != 1 {
+            return Ok(None);
+        }
+        Entity::find_by_id(self.id).one(db).await
     }
 }
@@ -1 +1,3 @@
 pub mod background_tasks;
+pub mod task_batches;
+pub mod task_schedules;
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "task_batches")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: Option<String>,
+    /// Number of tasks enqueued into the batch
+    pub total: i32,
+    pub completed: i32,
+    /// Members that ended failed, expired or canceled
+    pub failed: i32,
+    /// No more tasks will be added; the batch can finish
+    pub sealed: bool,
+    pub callback_task_type: Option<String>,
+    pub callback_payload: Option<Json>,
+    pub callback_task_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Members that have not reached a terminal state yet
+    pub fn remaining(&self) -> i32 {
+        (self.total - self.completed - self.failed).max(0)
+    }
+}
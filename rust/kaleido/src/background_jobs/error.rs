@@ -14,6 +14,9 @@ pub enum TaskError {
     #[error("Task processing error: {0}")]
     Processing(String),
 
+    #[error("Payload encryption error: {0}")]
+    Encryption(String),
+
     #[error("Max attempts reached")]
     MaxAttemptsReached,
 }
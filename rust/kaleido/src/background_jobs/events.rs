@@ -0,0 +1,270 @@
+// Task status change events
+//
+// Publishers send each transition to an optional in-process `TaskEventBus`
+// and, on Postgres, to `NOTIFY background_task_events` so that API
+// processes see transitions made by separate worker processes. A listener
+// forwards notifications into the local bus, skipping ones this process
+// already delivered itself. NOTIFY can be turned off with
+// `set_postgres_notify`, and long errors are truncated to fit its payload
+// limit.
+
+use crate::background_jobs::entities::background_tasks;
+use chrono::{DateTime, Utc};
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, Statement};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// Postgres channel used for cross-process task events
+pub const TASK_EVENTS_CHANNEL: &str = "background_task_events";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskEventKind {
+    Enqueued,
+    Processing,
+    Completed,
+    Failed,
+    Retrying,
+    Snoozed,
+    Canceled,
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TaskEvent {
+    pub task_id: i32,
+    pub task_type: String,
+    pub event: TaskEventKind,
+    /// Task status after the transition
+    pub status: String,
+    pub attempts: i32,
+    pub error: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl TaskEvent {
+    pub fn from_model(event: TaskEventKind, task: &background_tasks::Model) -> Self {
+        Self {
+            task_id: task.id,
+            task_type: task.task_type.clone(),
+            event,
+            status: task.status.clone(),
+            attempts: task.attempts,
+            error: task.error.clone(),
+            occurred_at: task.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    origin: String,
+    event: TaskEvent,
+}
+
+fn process_origin() -> &'static str {
+    static ORIGIN: OnceLock<String> = OnceLock::new();
+    ORIGIN.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// In-process fan-out of task events to subscribers such as SSE streams
+#[derive(Debug, Clone)]
+pub struct TaskEventBus {
+    sender: broadcast::Sender<TaskEvent>,
+}
+
+impl TaskEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: TaskEvent) {
+        // No subscribers is not an error
+        let _ = self.sender.send(event);
+    }
+
+    /// Forward Postgres notifications on [`TASK_EVENTS_CHANNEL`] into this bus
+    ///
+    /// Returns `None` when `db` is not a Postgres connection.
+    pub fn spawn_postgres_listener(
+        &self,
+        db: &DatabaseConnection,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        if db.get_database_backend() != DatabaseBackend::Postgres {
+            return None;
+        }
+
+        let pool = db.get_postgres_connection_pool().clone();
+        let bus = self.clone();
+        Some(tokio::spawn(async move {
+            let mut listener = match sea_orm::sqlx::postgres::PgListener::connect_with(&pool).await
+            {
+                Ok(listener) => listener,
+                Err(error) => {
+                    tracing::error!(%error, "Failed to connect task event listener");
+                    return;
+                }
+            };
+            if let Err(error) = listener.listen(TASK_EVENTS_CHANNEL).await {
+                tracing::error!(%error, "Failed to listen for task events");
+                return;
+            }
+
+            loop {
+                match listener.recv().await {
+                    Ok(notification) => {
+                        match serde_json::from_str::<Envelope>(notification.payload()) {
+                            Ok(envelope) if envelope.origin != process_origin() => {
+                                bus.publish(envelope.event)
+                            }
+                            Ok(_) => {}
+                            Err(error) => {
+                                tracing::warn!(%error, "Ignoring malformed task event")
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        tracing::warn!(%error, "Task event listener error");
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        }))
+    }
+}
+
+impl Default for TaskEventBus {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+/// Deliver `event` to `bus` and, on Postgres, to other processes via NOTIFY
+///
+/// Delivery is best effort; failures are logged and never fail the caller.
+pub async fn publish_task_event<C: ConnectionTrait>(
+    db: &C,
+    bus: Option<&TaskEventBus>,
+    event: TaskEvent,
+) {
+    if postgres_notify_enabled() && db.get_database_backend() == DatabaseBackend::Postgres {
+        match notify_payload(event.clone()) {
+            Ok(payload) => {
+                let statement = Statement::from_sql_and_values(
+                    DatabaseBackend::Postgres,
+                    "SELECT pg_notify($1, $2)",
+                    [TASK_EVENTS_CHANNEL.into(), payload.into()],
+                );
+                if let Err(error) = db.execute_raw(statement).await {
+                    tracing::warn!(%error, "Failed to notify task event");
+                }
+            }
+            Err(error) => tracing::warn!(%error, "Failed to serialize task event"),
+        }
+    }
+
+    if let Some(bus) = bus {
+        bus.publish(event);
+    }
+}
+
+/// Postgres rejects NOTIFY payloads of 8000 bytes or more
+const MAX_NOTIFY_PAYLOAD_BYTES: usize = 8000;
+
+const TRUNCATED_MARKER: &str = "...";
+
+static POSTGRES_NOTIFY: OnceLock<AtomicBool> = OnceLock::new();
+
+fn postgres_notify() -> &'static AtomicBool {
+    POSTGRES_NOTIFY.get_or_init(|| {
+        let enabled = std::env::var("TASK_EVENTS_NOTIFY")
+            .map(|value| !matches!(value.as_str(), "0" | "false"))
+            .unwrap_or(true);
+        AtomicBool::new(enabled)
+    })
+}
+
+/// Turn NOTIFY of task events on or off for this process
+///
+/// On by default; `TASK_EVENTS_NOTIFY=false` turns it off at startup.
+/// Without it, API processes only see events published in-process, so the
+/// `/events` stream misses transitions made by separate workers.
+pub fn set_postgres_notify(enabled: bool) {
+    postgres_notify().store(enabled, Ordering::Relaxed);
+}
+
+pub fn postgres_notify_enabled() -> bool {
+    postgres_notify().load(Ordering::Relaxed)
+}
+
+/// Serialize `event` for NOTIFY, truncating its error so the payload fits
+fn notify_payload(event: TaskEvent) -> Result<String, serde_json::Error> {
+    let mut envelope = Envelope {
+        origin: process_origin().to_string(),
+        event,
+    };
+
+    loop {
+        let payload = serde_json::to_string(&envelope)?;
+        if payload.len() < MAX_NOTIFY_PAYLOAD_BYTES {
+            return Ok(payload);
+        }
+        let Some(error) = envelope.event.error.as_mut() else {
+            return Ok(payload);
+        };
+
+        let excess = payload.len() + TRUNCATED_MARKER.len() + 1 - MAX_NOTIFY_PAYLOAD_BYTES;
+        let mut keep = error.len().saturating_sub(excess + TRUNCATED_MARKER.len());
+        while !error.is_char_boundary(keep) {
+            keep -= 1;
+        }
+        if keep == 0 {
+            envelope.event.error = None;
+            continue;
+        }
+        error.truncate(keep);
+        error.push_str(TRUNCATED_MARKER);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(error: Option<String>) -> TaskEvent {
+        TaskEvent {
+            task_id: 1,
+            task_type: "report".to_string(),
+            event: TaskEventKind::Failed,
+            status: "failed".to_string(),
+            attempts: 3,
+            error,
+            occurred_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_notify_payload_truncates_long_errors() {
+        let short = notify_payload(event(Some("boom".to_string()))).unwrap();
+        let envelope: Envelope = serde_json::from_str(&short).unwrap();
+        assert_eq!(envelope.event.error.as_deref(), Some("boom"));
+
+        // Multi-byte characters and characters JSON escapes both count
+        let long = format!("{}{}", "é".repeat(3000), "\"".repeat(3000));
+        let payload = notify_payload(event(Some(long))).unwrap();
+        assert!(payload.len() < MAX_NOTIFY_PAYLOAD_BYTES);
+        let envelope: Envelope = serde_json::from_str(&payload).unwrap();
+        let error = envelope.event.error.unwrap();
+        assert!(error.starts_with("éé"));
+        assert!(error.ends_with(TRUNCATED_MARKER));
+        assert_eq!(envelope.event.task_id, 1);
+    }
+}
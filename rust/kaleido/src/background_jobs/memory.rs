@@ -4,7 +4,7 @@
 // and testing. Tasks are stored in memory and will be lost on restart.
 
 use crate::background_jobs::error::TaskError;
-use crate::background_jobs::storage::{TaskRecord, TaskStatus, TaskStorage};
+use crate::background_jobs::storage::{EnqueueOptions, TaskRecord, TaskStatus, TaskStorage};
 use async_trait::async_trait;
 use chrono::Utc;
 use std::sync::Arc;
@@ -36,18 +36,22 @@ impl TaskStorage for InMemoryStorage {
         &self,
         task_type: String,
         payload: serde_json::Value,
-        scheduled_for: Option<chrono::DateTime<Utc>>,
-        max_attempts: i32,
+        options: EnqueueOptions,
     ) -> Result<TaskRecord, TaskError> {
         let task = TaskRecord {
             id: Uuid::new_v4().to_string(),
             task_type,
             payload,
+            payload_version: options.payload_version(),
+            concurrency_key: options.concurrency_key,
+            batch_id: options.batch_id,
+            metadata: options.metadata,
             status: TaskStatus::Pending,
             attempts: 0,
-            max_attempts,
+            max_attempts: options.max_attempts,
             error: None,
-            scheduled_for,
+            scheduled_for: options.scheduled_for,
+            expires_at: options.expires_at,
             created_at: Utc::now(),
             updated_at: Utc::now(),
             started_at: None,
@@ -64,11 +68,20 @@ impl TaskStorage for InMemoryStorage {
         let tasks = self.tasks.read().await;
         let now = Utc::now();
 
+        let held_keys: Vec<&str> = tasks
+            .iter()
+            .filter(|t| t.status == TaskStatus::Processing)
+            .filter_map(|t| t.concurrency_key.as_deref())
+            .collect();
+
         let pending: Vec<TaskRecord> = tasks
             .iter()
             .filter(|t| {
                 t.status == TaskStatus::Pending
                     && (t.scheduled_for.is_none() || t.scheduled_for.unwrap() <= now)
+                    && t.concurrency_key
+                        .as_deref()
+                        .is_none_or(|key| !held_keys.contains(&key))
             })
             .take(limit)
             .cloned()
@@ -126,6 +139,21 @@ impl TaskStorage for InMemoryStorage {
         Ok(task.clone())
     }
 
+    async fn mark_expired(&self, id: &str) -> Result<TaskRecord, TaskError> {
+        let mut tasks = self.tasks.write().await;
+        let task = tasks
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or(TaskError::NotFound)?;
+
+        task.status = TaskStatus::Expired;
+        task.error = Some("Task expired before it could be processed".to_string());
+        task.completed_at = Some(Utc::now());
+        task.updated_at = Utc::now();
+
+        Ok(task.clone())
+    }
+
     async fn get_task(&self, id: &str) -> Result<Option<TaskRecord>, TaskError> {
         let tasks = self.tasks.read().await;
         Ok(tasks.iter().find(|t| t.id == id).cloned())
@@ -142,7 +170,11 @@ mod tests {
         let storage = InMemoryStorage::new();
 
         let task = storage
-            .enqueue("test_task".to_string(), json!({"foo": "bar"}), None, 3)
+            .enqueue(
+                "test_task".to_string(),
+                json!({"foo": "bar"}),
+                EnqueueOptions::default(),
+            )
             .await
             .unwrap();
 
@@ -160,7 +192,11 @@ mod tests {
         let storage = InMemoryStorage::new();
 
         let task = storage
-            .enqueue("test_task".to_string(), json!({"foo": "bar"}), None, 3)
+            .enqueue(
+                "test_task".to_string(),
+                json!({"foo": "bar"}),
+                EnqueueOptions::default(),
+            )
             .await
             .unwrap();
 
@@ -175,7 +211,11 @@ mod tests {
         let storage = InMemoryStorage::new();
 
         let task = storage
-            .enqueue("test_task".to_string(), json!({"foo": "bar"}), None, 3)
+            .enqueue(
+                "test_task".to_string(),
+                json!({"foo": "bar"}),
+                EnqueueOptions::default(),
+            )
             .await
             .unwrap();
 
@@ -191,7 +231,11 @@ mod tests {
         let storage = InMemoryStorage::new();
 
         let task = storage
-            .enqueue("test_task".to_string(), json!({"foo": "bar"}), None, 3)
+            .enqueue(
+                "test_task".to_string(),
+                json!({"foo": "bar"}),
+                EnqueueOptions::default(),
+            )
             .await
             .unwrap();
 
@@ -211,7 +255,11 @@ mod tests {
         let storage = InMemoryStorage::new();
 
         let task = storage
-            .enqueue("test_task".to_string(), json!({"foo": "bar"}), None, 1)
+            .enqueue(
+                "test_task".to_string(),
+                json!({"foo": "bar"}),
+                EnqueueOptions::default().with_max_attempts(1),
+            )
             .await
             .unwrap();
 
@@ -224,4 +272,63 @@ mod tests {
         // Should be failed since attempts (1) >= max_attempts (1)
         assert_eq!(updated.status, TaskStatus::Failed);
     }
+
+    #[tokio::test]
+    async fn test_mark_expired() {
+        let storage = InMemoryStorage::new();
+
+        let task = storage
+            .enqueue(
+                "test_task".to_string(),
+                json!({"foo": "bar"}),
+                EnqueueOptions::default().with_ttl(chrono::Duration::seconds(-1)),
+            )
+            .await
+            .unwrap();
+
+        assert!(task.is_expired());
+
+        let updated = storage.mark_expired(&task.id).await.unwrap();
+        assert_eq!(updated.status, TaskStatus::Expired);
+        assert!(updated.completed_at.is_some());
+        assert!(storage.find_pending(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_pending_skips_busy_concurrency_key() {
+        let storage = InMemoryStorage::new();
+        let options = EnqueueOptions::default().with_concurrency_key("feed:1");
+
+        let first = storage
+            .enqueue("sync_feed".to_string(), json!({}), options.clone())
+            .await
+            .unwrap();
+        let second = storage
+            .enqueue("sync_feed".to_string(), json!({}), options)
+            .await
+            .unwrap();
+        let other = storage
+            .enqueue(
+                "sync_feed".to_string(),
+                json!({}),
+                EnqueueOptions::default().with_concurrency_key("feed:2"),
+            )
+            .await
+            .unwrap();
+
+        storage.mark_processing(&first.id).await.unwrap();
+
+        let pending: Vec<String> = storage
+            .find_pending(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(pending, vec![other.id]);
+
+        storage.mark_completed(&first.id).await.unwrap();
+        let pending = storage.find_pending(10).await.unwrap();
+        assert_eq!(pending[0].id, second.id);
+    }
 }
@@ -2,27 +2,39 @@
 //
 // This crate provides a flexible task queue system with:
 // - In-memory task queue (default, no persistence)
-// - Durable task queue (PostgreSQL-backed)
+// - Durable task queue (Postgres or SQLite backed)
 // - Trait-based interface for custom implementations
 // - Strongly-typed task definitions
 
+pub mod batches;
+pub mod cipher;
 pub mod entities;
 pub mod error;
+pub mod events;
 pub mod memory;
 pub mod openapi;
 pub mod queue;
+pub mod schedules;
+pub mod stats;
 pub mod storage;
 pub mod task;
+pub mod trace_context;
 
 pub mod admin;
 pub mod durable;
 pub mod worker;
 
+pub use batches::BatchHandle;
+pub use cipher::PayloadCipher;
 pub use entities::background_tasks;
 pub use error::TaskError;
+pub use events::{TaskEvent, TaskEventBus, TaskEventKind};
 pub use memory::InMemoryStorage;
 pub use queue::TaskQueue;
-pub use storage::{TaskRecord, TaskStatus, TaskStorage};
+pub use storage::{
+    register_payload_version, BatchOptions, EnqueueOptions, TaskRecord, TaskStatus, TaskStorage,
+    DEFAULT_PAYLOAD_VERSION,
+};
 pub use task::Task;
 
 pub use durable::DurableStorage;
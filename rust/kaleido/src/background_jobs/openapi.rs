@@ -2,16 +2,47 @@
 // Exposes commonly useful schema types that services may wish to include.
 
 pub mod paths {
-    pub use crate::background_jobs::admin::{cancel_task, get_task, list_tasks, rerun_task};
+    pub use crate::background_jobs::admin::{
+        bulk_cancel_tasks, bulk_reschedule_tasks, bulk_run_tasks_now, bulk_set_max_attempts,
+        cancel_task, get_task, list_tasks, rerun_task, reschedule_task, run_task_now,
+        set_max_attempts, task_events, task_stats,
+    };
 
     pub use crate::background_jobs::admin::{
-        __path_cancel_task, __path_get_task, __path_list_tasks, __path_rerun_task,
+        __path_bulk_cancel_tasks, __path_bulk_reschedule_tasks, __path_bulk_run_tasks_now,
+        __path_bulk_set_max_attempts, __path_cancel_task, __path_get_task, __path_list_tasks,
+        __path_rerun_task, __path_reschedule_task, __path_run_task_now, __path_set_max_attempts,
+        __path_task_events, __path_task_stats,
+    };
+
+    pub use crate::background_jobs::batches::admin_controller::{get_batch, list_batches};
+
+    pub use crate::background_jobs::batches::admin_controller::{
+        __path_get_batch, __path_list_batches,
+    };
+
+    pub use crate::background_jobs::schedules::admin_controller::{
+        create_schedule, delete_schedule, get_schedule, list_schedules, update_schedule,
+    };
+
+    pub use crate::background_jobs::schedules::admin_controller::{
+        __path_create_schedule, __path_delete_schedule, __path_get_schedule, __path_list_schedules,
+        __path_update_schedule,
     };
 }
 
 pub mod schemas {
     pub use crate::background_jobs::admin::{
-        PaginatedResponse, PaginationMetadata, TaskDetailResponse, TaskResponse,
+        BulkActionResponse, PaginatedResponse, PaginationMetadata, RescheduleTaskRequest,
+        SetMaxAttemptsRequest, TaskDetailResponse, TaskResponse,
+    };
+    pub use crate::background_jobs::batches::admin_controller::BatchResponse;
+    pub use crate::background_jobs::events::{TaskEvent, TaskEventKind};
+    pub use crate::background_jobs::schedules::admin_controller::{
+        CreateScheduleRequest, ScheduleResponse, UpdateScheduleRequest,
+    };
+    pub use crate::background_jobs::stats::{
+        DurationStats, StatsWindow, TaskStatsResponse, TaskTypeStats,
     };
     pub use crate::background_jobs::storage::{TaskRecord, TaskStatus};
 }
@@ -1,28 +1,68 @@
+use crate::background_jobs::batches::BatchHandle;
+use crate::background_jobs::cipher::PayloadCipher;
+use crate::background_jobs::durable::{self, DurableStorage};
+use crate::background_jobs::entities::background_tasks;
 use crate::background_jobs::error::TaskError;
-use crate::background_jobs::storage::{TaskRecord, TaskStorage};
+use crate::background_jobs::storage::{
+    self, BatchOptions, EnqueueOptions, TaskRecord, TaskStorage,
+};
+use crate::background_jobs::trace_context;
 use chrono::Utc;
+use sea_orm::ConnectionTrait;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::debug;
 
 /// TaskQueue provides a high-level interface for enqueuing and managing background tasks
 pub struct TaskQueue<S: TaskStorage> {
     storage: Arc<S>,
+    cipher: Option<Arc<PayloadCipher>>,
+    sensitive_task_types: Arc<HashSet<String>>,
 }
 
 impl<S: TaskStorage> TaskQueue<S> {
     pub fn new(storage: S) -> Self {
         Self {
             storage: Arc::new(storage),
+            cipher: None,
+            sensitive_task_types: Arc::new(HashSet::new()),
         }
     }
 
+    /// Encrypt payloads of sensitive task types with `cipher`
+    pub fn with_cipher(mut self, cipher: Arc<PayloadCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Mark a task type as sensitive so its payload is encrypted at rest
+    ///
+    /// Enqueuing a sensitive task without a cipher configured is an error.
+    pub fn with_sensitive_task_type(mut self, task_type: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.sensitive_task_types).insert(task_type.into());
+        self
+    }
+
+    /// Version stamped on new tasks of `task_type`
+    ///
+    /// Read from the process-wide registry; see
+    /// [`register_payload_version`](storage::register_payload_version).
+    pub fn payload_version(&self, task_type: &str) -> i32 {
+        storage::payload_version(task_type)
+    }
+
+    pub fn is_sensitive(&self, task_type: &str) -> bool {
+        self.sensitive_task_types.contains(task_type)
+    }
+
     /// Enqueue a task to be processed in the background
     pub async fn enqueue<T: serde::Serialize>(
         &self,
         task_type: String,
         task: T,
     ) -> Result<TaskRecord, TaskError> {
-        self.enqueue_with_options(task_type, task, None, 3).await
+        self.enqueue_with(task_type, task, EnqueueOptions::default())
+            .await
     }
 
     /// Enqueue a task with custom options
@@ -33,11 +73,26 @@ impl<S: TaskStorage> TaskQueue<S> {
         scheduled_for: Option<chrono::DateTime<Utc>>,
         max_attempts: i32,
     ) -> Result<TaskRecord, TaskError> {
-        let payload = serde_json::to_value(&task)?;
+        let options = EnqueueOptions {
+            scheduled_for,
+            max_attempts,
+            ..Default::default()
+        };
+        self.enqueue_with(task_type, task, options).await
+    }
+
+    /// Enqueue a task with a full set of options, including an expiration deadline
+    pub async fn enqueue_with<T: serde::Serialize>(
+        &self,
+        task_type: String,
+        task: T,
+        options: EnqueueOptions,
+    ) -> Result<TaskRecord, TaskError> {
+        let (payload, options) = self.prepare(&task_type, task, options)?;
 
         let task_record = self
             .storage
-            .enqueue(task_type.clone(), payload, scheduled_for, max_attempts)
+            .enqueue(task_type.clone(), payload, options)
             .await?;
 
         debug!(
@@ -48,6 +103,55 @@ impl<S: TaskStorage> TaskQueue<S> {
         Ok(task_record)
     }
 
+    /// Serialize the payload, encrypting it for sensitive task types, and
+    /// attach the current trace context
+    fn prepare<T: serde::Serialize>(
+        &self,
+        task_type: &str,
+        task: T,
+        mut options: EnqueueOptions,
+    ) -> Result<(serde_json::Value, EnqueueOptions), TaskError> {
+        trace_context::inject(&mut options.metadata);
+        options
+            .payload_version
+            .get_or_insert_with(|| self.payload_version(task_type));
+        let mut payload = serde_json::to_value(&task)?;
+        if self.is_sensitive(task_type) {
+            let cipher = self.cipher.as_ref().ok_or_else(|| {
+                TaskError::Encryption(format!(
+                    "no payload cipher configured for sensitive task type {}",
+                    task_type
+                ))
+            })?;
+            payload = cipher.encrypt(task_type, &payload)?;
+        }
+        Ok((payload, options))
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Open a batch whose handle tags every task enqueued through it
+    pub async fn start_batch(&self) -> Result<BatchHandle<S>, TaskError> {
+        self.start_batch_with(BatchOptions::default()).await
+    }
+
+    /// Open a batch with a name and an optional completion callback
+    pub async fn start_batch_with(
+        &self,
+        options: BatchOptions,
+    ) -> Result<BatchHandle<S>, TaskError> {
+        let batch_id = self.storage.create_batch(options).await?;
+        debug!("Task batch started: id={}", batch_id);
+        Ok(BatchHandle::new(self.clone(), batch_id))
+    }
+
+    /// Seal a batch so it finishes once its members are done
+    pub async fn seal_batch(&self, batch_id: i32) -> Result<(), TaskError> {
+        self.storage.seal_batch(batch_id).await
+    }
+
     /// Find pending tasks ready to be processed
     pub async fn find_pending(&self, limit: usize) -> Result<Vec<TaskRecord>, TaskError> {
         self.storage.find_pending(limit).await
@@ -68,6 +172,11 @@ impl<S: TaskStorage> TaskQueue<S> {
         self.storage.mark_failed(id, error).await
     }
 
+    /// Mark task as expired
+    pub async fn mark_expired(&self, id: &str) -> Result<TaskRecord, TaskError> {
+        self.storage.mark_expired(id).await
+    }
+
     /// Get task by ID
     pub async fn get_task(&self, id: &str) -> Result<Option<TaskRecord>, TaskError> {
         self.storage.get_task(id).await
@@ -78,6 +187,27 @@ impl<S: TaskStorage> Clone for TaskQueue<S> {
     fn clone(&self) -> Self {
         Self {
             storage: Arc::clone(&self.storage),
+            cipher: self.cipher.clone(),
+            sensitive_task_types: Arc::clone(&self.sensitive_task_types),
         }
     }
 }
+
+impl TaskQueue<DurableStorage> {
+    /// Enqueue on `conn`, typically a transaction the task must commit with
+    ///
+    /// No event is published; the caller does that once the transaction
+    /// commits.
+    pub async fn enqueue_in<C: ConnectionTrait, T: serde::Serialize>(
+        &self,
+        conn: &C,
+        task_type: String,
+        task: T,
+        options: EnqueueOptions,
+    ) -> Result<background_tasks::Model, TaskError> {
+        let (payload, options) = self.prepare(&task_type, task, options)?;
+        durable::insert_task(conn, task_type, payload, options)
+            .await
+            .map_err(|e| TaskError::Storage(e.to_string()))
+    }
+}
@@ -0,0 +1,277 @@
+use super::service::{NewSchedule, ScheduleChanges, ScheduleError, ScheduleService};
+use crate::background_jobs::admin::{
+    AdminTaskError, AdminVerified, BackgroundTasksStorage, PaginatedResponse,
+};
+use crate::background_jobs::entities::task_schedules;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+pub fn routes<S, A>() -> Router<Arc<S>>
+where
+    S: BackgroundTasksStorage + 'static,
+    A: AdminVerified + axum::extract::FromRequestParts<Arc<S>> + Send + 'static,
+    <A as axum::extract::FromRequestParts<Arc<S>>>::Rejection: IntoResponse,
+{
+    Router::new()
+        .route(
+            "/",
+            get(list_schedules::<S, A>).post(create_schedule::<S, A>),
+        )
+        .route(
+            "/:id",
+            get(get_schedule::<S, A>)
+                .patch(update_schedule::<S, A>)
+                .delete(delete_schedule::<S, A>),
+        )
+}
+
+impl From<ScheduleError> for AdminTaskError {
+    fn from(e: ScheduleError) -> Self {
+        match e {
+            ScheduleError::NotFound => AdminTaskError::not_found("Schedule not found"),
+            ScheduleError::DuplicateName(_) => AdminTaskError::conflict(e.to_string()),
+            ScheduleError::InvalidCron(_) | ScheduleError::InvalidTimezone(_) => {
+                AdminTaskError::bad_request(e.to_string())
+            }
+            ScheduleError::Database(db_err) => db_err.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScheduleResponse {
+    pub id: i32,
+    pub name: String,
+    pub task_type: String,
+    pub cron_expression: String,
+    pub timezone: String,
+    pub payload: JsonValue,
+    pub enabled: bool,
+    pub source: String,
+    pub last_run_at: Option<String>,
+    pub next_run_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<task_schedules::Model> for ScheduleResponse {
+    fn from(m: task_schedules::Model) -> Self {
+        Self {
+            id: m.id,
+            name: m.name,
+            task_type: m.task_type,
+            cron_expression: m.cron_expression,
+            timezone: m.timezone,
+            payload: m.payload,
+            enabled: m.enabled,
+            source: m.source,
+            last_run_at: m.last_run_at.map(|d| d.to_rfc3339()),
+            next_run_at: m.next_run_at.map(|d| d.to_rfc3339()),
+            created_at: m.created_at.to_rfc3339(),
+            updated_at: m.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateScheduleRequest {
+    pub name: String,
+    pub task_type: String,
+    pub cron_expression: String,
+    pub timezone: Option<String>,
+    pub payload: Option<JsonValue>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateScheduleRequest {
+    pub cron_expression: Option<String>,
+    pub timezone: Option<String>,
+    pub payload: Option<JsonValue>,
+    pub enabled: Option<bool>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/schedules",
+    operation_id = "admin_list_schedules",
+    responses(
+        (status = 200, description = "Recurring task schedules", body = PaginatedResponse<ScheduleResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn list_schedules<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+) -> Result<Json<PaginatedResponse<ScheduleResponse>>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let db = BackgroundTasksStorage::db(&*state);
+    let data: Vec<ScheduleResponse> = ScheduleService::list(db)
+        .await?
+        .into_iter()
+        .map(ScheduleResponse::from)
+        .collect();
+
+    let total = data.len() as i64;
+    Ok(Json(PaginatedResponse::new(data, 1, total, total)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/schedules",
+    operation_id = "admin_create_schedule",
+    request_body = CreateScheduleRequest,
+    responses(
+        (status = 201, description = "Schedule created", body = ScheduleResponse),
+        (status = 400, description = "Invalid cron expression or timezone"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 409, description = "A schedule with this name already exists"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn create_schedule<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Json(request): Json<CreateScheduleRequest>,
+) -> Result<(StatusCode, Json<ScheduleResponse>), AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let db = BackgroundTasksStorage::db(&*state);
+    let schedule = ScheduleService::create(
+        db,
+        NewSchedule {
+            name: request.name,
+            task_type: request.task_type,
+            cron_expression: request.cron_expression,
+            timezone: request.timezone.unwrap_or_else(|| "UTC".to_string()),
+            payload: request.payload.unwrap_or_else(|| serde_json::json!({})),
+            enabled: request.enabled.unwrap_or(true),
+        },
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(ScheduleResponse::from(schedule))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/schedules/{id}",
+    operation_id = "admin_get_schedule",
+    params(
+        ("id" = i32, Path, description = "Schedule ID")
+    ),
+    responses(
+        (status = 200, description = "Recurring task schedule", body = ScheduleResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Schedule not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn get_schedule<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+) -> Result<Json<ScheduleResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let db = BackgroundTasksStorage::db(&*state);
+    let schedule = ScheduleService::get(db, id).await?;
+    Ok(Json(ScheduleResponse::from(schedule)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/admin/schedules/{id}",
+    operation_id = "admin_update_schedule",
+    params(
+        ("id" = i32, Path, description = "Schedule ID")
+    ),
+    request_body = UpdateScheduleRequest,
+    responses(
+        (status = 200, description = "Schedule updated", body = ScheduleResponse),
+        (status = 400, description = "Invalid cron expression or timezone"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Schedule not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn update_schedule<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+    Json(request): Json<UpdateScheduleRequest>,
+) -> Result<Json<ScheduleResponse>, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let db = BackgroundTasksStorage::db(&*state);
+    let schedule = ScheduleService::update(
+        db,
+        id,
+        ScheduleChanges {
+            cron_expression: request.cron_expression,
+            timezone: request.timezone,
+            payload: request.payload,
+            enabled: request.enabled,
+        },
+    )
+    .await?;
+
+    Ok(Json(ScheduleResponse::from(schedule)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/schedules/{id}",
+    operation_id = "admin_delete_schedule",
+    params(
+        ("id" = i32, Path, description = "Schedule ID")
+    ),
+    responses(
+        (status = 204, description = "Schedule deleted"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Schedule not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "admin",
+)]
+pub async fn delete_schedule<S, A>(
+    _admin: A,
+    State(state): State<Arc<S>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AdminTaskError>
+where
+    S: BackgroundTasksStorage,
+    A: AdminVerified,
+{
+    let db = BackgroundTasksStorage::db(&*state);
+    ScheduleService::delete(db, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
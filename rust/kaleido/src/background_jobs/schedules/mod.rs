@@ -0,0 +1,17 @@
+// Database-backed recurring schedules
+//
+// Schedules live in the `task_schedules` table so they can be inspected and
+// edited at runtime. Workers opt in to the schedule runner with
+// `TaskWorker::with_schedule_runner`; every replica that does polls the
+// table, but each due run is claimed with a conditional update on
+// `next_run_at`, so only one replica enqueues the task.
+
+pub mod admin_controller;
+pub mod runner;
+pub mod service;
+
+pub use admin_controller::routes as admin_routes;
+pub use runner::spawn_schedule_runner;
+pub use service::{
+    next_run_after, DeclaredSchedule, NewSchedule, ScheduleChanges, ScheduleError, ScheduleService,
+};
@@ -0,0 +1,23 @@
+use crate::background_jobs::durable::DurableStorage;
+use crate::background_jobs::queue::TaskQueue;
+use crate::background_jobs::schedules::service::ScheduleService;
+use std::time::Duration;
+
+/// Poll `task_schedules` and enqueue due runs through `queue` until the task is aborted
+pub fn spawn_schedule_runner(
+    queue: TaskQueue<DurableStorage>,
+    poll_interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(poll_interval);
+
+        loop {
+            ticker.tick().await;
+            match ScheduleService::fire_due(&queue, chrono::Utc::now()).await {
+                Ok(0) => {}
+                Ok(fired) => tracing::debug!(fired, "Fired due task schedules"),
+                Err(error) => tracing::error!(%error, "Failed to fire task schedules"),
+            }
+        }
+    })
+}
@@ -0,0 +1,370 @@
+use crate::background_jobs::durable::DurableStorage;
+use crate::background_jobs::entities::task_schedules::{self, SOURCE_ADMIN, SOURCE_CODE};
+use crate::background_jobs::events::{publish_task_event, TaskEvent, TaskEventKind};
+use crate::background_jobs::queue::TaskQueue;
+use crate::background_jobs::storage::EnqueueOptions;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, NotSet, QueryFilter,
+    QueryOrder, Set, SqlErr, TransactionTrait,
+};
+use serde_json::Value;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ScheduleError {
+    #[error("Invalid cron expression: {0}")]
+    InvalidCron(String),
+
+    #[error("Unknown timezone: {0}")]
+    InvalidTimezone(String),
+
+    #[error("Schedule not found")]
+    NotFound,
+
+    #[error("A schedule named {0} already exists")]
+    DuplicateName(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+}
+
+/// Next time `cron_expression` fires after `after`, evaluated in `timezone`
+pub fn next_run_after(
+    cron_expression: &str,
+    timezone: &str,
+    after: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, ScheduleError> {
+    let schedule = Schedule::from_str(cron_expression)
+        .map_err(|e| ScheduleError::InvalidCron(format!("{}: {}", cron_expression, e)))?;
+    let tz =
+        Tz::from_str(timezone).map_err(|_| ScheduleError::InvalidTimezone(timezone.to_string()))?;
+
+    Ok(schedule
+        .after(&after.with_timezone(&tz))
+        .next()
+        .map(|next| next.with_timezone(&Utc)))
+}
+
+/// A schedule created through the admin API
+#[derive(Debug, Clone)]
+pub struct NewSchedule {
+    pub name: String,
+    pub task_type: String,
+    pub cron_expression: String,
+    pub timezone: String,
+    pub payload: Value,
+    pub enabled: bool,
+}
+
+/// A schedule declared in code by a processor
+#[derive(Debug, Clone)]
+pub struct DeclaredSchedule {
+    pub task_type: String,
+    pub cron_expression: String,
+    /// Payload of each run
+    pub payload: Value,
+}
+
+/// Fields an admin may change on an existing schedule
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleChanges {
+    pub cron_expression: Option<String>,
+    pub timezone: Option<String>,
+    pub payload: Option<Value>,
+    pub enabled: Option<bool>,
+}
+
+pub struct ScheduleService;
+
+impl ScheduleService {
+    pub async fn list(
+        db: &DatabaseConnection,
+    ) -> Result<Vec<task_schedules::Model>, ScheduleError> {
+        Ok(task_schedules::Entity::find()
+            .order_by_asc(task_schedules::Column::Name)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn get(
+        db: &DatabaseConnection,
+        id: i32,
+    ) -> Result<task_schedules::Model, ScheduleError> {
+        task_schedules::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(ScheduleError::NotFound)
+    }
+
+    pub async fn create(
+        db: &DatabaseConnection,
+        schedule: NewSchedule,
+    ) -> Result<task_schedules::Model, ScheduleError> {
+        let now = Utc::now();
+        let next_run_at = next_run_after(&schedule.cron_expression, &schedule.timezone, now)?;
+        let name = schedule.name.clone();
+
+        task_schedules::ActiveModel {
+            id: NotSet,
+            name: Set(schedule.name),
+            task_type: Set(schedule.task_type),
+            cron_expression: Set(schedule.cron_expression),
+            timezone: Set(schedule.timezone),
+            payload: Set(schedule.payload),
+            enabled: Set(schedule.enabled),
+            source: Set(SOURCE_ADMIN.to_string()),
+            last_run_at: Set(None),
+            next_run_at: Set(next_run_at),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(db)
+        .await
+        .map_err(|err| match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => ScheduleError::DuplicateName(name),
+            _ => err.into(),
+        })
+    }
+
+    pub async fn update(
+        db: &DatabaseConnection,
+        id: i32,
+        changes: ScheduleChanges,
+    ) -> Result<task_schedules::Model, ScheduleError> {
+        let schedule = Self::get(db, id).await?;
+        let now = Utc::now();
+
+        let cron_expression = changes
+            .cron_expression
+            .unwrap_or_else(|| schedule.cron_expression.clone());
+        let timezone = changes
+            .timezone
+            .unwrap_or_else(|| schedule.timezone.clone());
+        let next_run_at = next_run_after(&cron_expression, &timezone, now)?;
+
+        let mut active: task_schedules::ActiveModel = schedule.into();
+        active.cron_expression = Set(cron_expression);
+        active.timezone = Set(timezone);
+        active.next_run_at = Set(next_run_at);
+        if let Some(payload) = changes.payload {
+            active.payload = Set(payload);
+        }
+        if let Some(enabled) = changes.enabled {
+            active.enabled = Set(enabled);
+        }
+        active.updated_at = Set(now);
+
+        Ok(active.update(db).await?)
+    }
+
+    pub async fn delete(db: &DatabaseConnection, id: i32) -> Result<(), ScheduleError> {
+        let result = task_schedules::Entity::delete_by_id(id).exec(db).await?;
+        if result.rows_affected == 0 {
+            return Err(ScheduleError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Upsert schedules declared in code via `TaskProcessor::schedule`
+    ///
+    /// Declared schedules are keyed by task type. Code schedules follow the
+    /// declaration: cron and payload changes are applied and a schedule that
+    /// was disabled, e.g. by a worker from an older deploy, is enabled again.
+    /// Admin edits to `timezone` are kept. Code schedules no longer declared
+    /// are disabled, so every worker that runs the schedule runner must
+    /// register the same scheduled processors.
+    pub async fn sync_declared(
+        db: &DatabaseConnection,
+        declared: &[DeclaredSchedule],
+    ) -> Result<(), ScheduleError> {
+        let now = Utc::now();
+
+        for schedule in declared {
+            // Workers starting together race to insert; the loser keeps going
+            task_schedules::Entity::insert(task_schedules::ActiveModel {
+                id: NotSet,
+                name: Set(schedule.task_type.clone()),
+                task_type: Set(schedule.task_type.clone()),
+                cron_expression: Set(schedule.cron_expression.clone()),
+                timezone: Set("UTC".to_string()),
+                payload: Set(schedule.payload.clone()),
+                enabled: Set(true),
+                source: Set(SOURCE_CODE.to_string()),
+                last_run_at: Set(None),
+                next_run_at: Set(next_run_after(&schedule.cron_expression, "UTC", now)?),
+                created_at: Set(now),
+                updated_at: Set(now),
+            })
+            .on_conflict(
+                OnConflict::column(task_schedules::Column::Name)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+
+            let Some(existing) = task_schedules::Entity::find()
+                .filter(task_schedules::Column::Name.eq(schedule.task_type.as_str()))
+                .filter(task_schedules::Column::Source.eq(SOURCE_CODE))
+                .one(db)
+                .await?
+            else {
+                continue;
+            };
+            if existing.enabled
+                && existing.cron_expression == schedule.cron_expression
+                && existing.payload == schedule.payload
+            {
+                continue;
+            }
+
+            let next_run_at =
+                if existing.enabled && existing.cron_expression == schedule.cron_expression {
+                    existing.next_run_at
+                } else {
+                    next_run_after(&schedule.cron_expression, &existing.timezone, now)?
+                };
+            let mut active: task_schedules::ActiveModel = existing.into();
+            active.cron_expression = Set(schedule.cron_expression.clone());
+            active.payload = Set(schedule.payload.clone());
+            active.enabled = Set(true);
+            active.next_run_at = Set(next_run_at);
+            active.updated_at = Set(now);
+            active.update(db).await?;
+        }
+
+        let removed = task_schedules::Entity::update_many()
+            .col_expr(task_schedules::Column::Enabled, Expr::value(false))
+            .col_expr(task_schedules::Column::UpdatedAt, Expr::value(now))
+            .filter(task_schedules::Column::Source.eq(SOURCE_CODE))
+            .filter(task_schedules::Column::Enabled.eq(true))
+            .filter(
+                task_schedules::Column::Name
+                    .is_not_in(declared.iter().map(|schedule| schedule.task_type.as_str())),
+            )
+            .exec(db)
+            .await?;
+        if removed.rows_affected > 0 {
+            tracing::info!(
+                disabled = removed.rows_affected,
+                "Disabled task schedules no longer declared in code"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Enqueue a task for every enabled schedule that is due at `now`
+    ///
+    /// Each run is claimed by moving `next_run_at` forward only if it still
+    /// holds the value this replica read. The claim and the enqueue share a
+    /// transaction, so a run is enqueued exactly once across replicas. Tasks
+    /// go through `queue`, which sets their payload version, attempts and
+    /// encryption.
+    pub async fn fire_due(
+        queue: &TaskQueue<DurableStorage>,
+        now: DateTime<Utc>,
+    ) -> Result<usize, ScheduleError> {
+        let db = queue.storage().db();
+        let due = task_schedules::Entity::find()
+            .filter(task_schedules::Column::Enabled.eq(true))
+            .filter(task_schedules::Column::NextRunAt.lte(now))
+            .all(db)
+            .await?;
+
+        let mut fired = 0;
+        for schedule in due {
+            let Some(previous_run_at) = schedule.next_run_at else {
+                continue;
+            };
+            let next_run_at =
+                match next_run_after(&schedule.cron_expression, &schedule.timezone, now) {
+                    Ok(next_run_at) => next_run_at,
+                    Err(error) => {
+                        tracing::error!(schedule = schedule.name, %error, "invalid task schedule");
+                        continue;
+                    }
+                };
+
+            let txn = db.begin().await?;
+            let claimed = task_schedules::Entity::update_many()
+                .col_expr(task_schedules::Column::LastRunAt, Expr::value(now))
+                .col_expr(task_schedules::Column::NextRunAt, Expr::value(next_run_at))
+                .col_expr(task_schedules::Column::UpdatedAt, Expr::value(now))
+                .filter(task_schedules::Column::Id.eq(schedule.id))
+                .filter(task_schedules::Column::NextRunAt.eq(previous_run_at))
+                .exec(&txn)
+                .await?;
+
+            if claimed.rows_affected != 1 {
+                txn.rollback().await?;
+                continue;
+            }
+
+            let task = match queue
+                .enqueue_in(
+                    &txn,
+                    schedule.task_type.clone(),
+                    &schedule.payload,
+                    EnqueueOptions::default(),
+                )
+                .await
+            {
+                Ok(task) => task,
+                Err(error) => {
+                    txn.rollback().await?;
+                    tracing::error!(schedule = schedule.name, %error, "Failed to enqueue scheduled task");
+                    continue;
+                }
+            };
+            txn.commit().await?;
+            publish_task_event(
+                db,
+                queue.storage().events(),
+                TaskEvent::from_model(TaskEventKind::Enqueued, &task),
+            )
+            .await;
+
+            tracing::info!(
+                schedule = schedule.name,
+                task_type = schedule.task_type,
+                "Enqueued scheduled task"
+            );
+            fired += 1;
+        }
+
+        Ok(fired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_next_run_after_uses_timezone() {
+        let after = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+
+        // 09:00 in New York is 14:00 UTC during standard time
+        let next = next_run_after("0 0 9 * * *", "America/New_York", after).unwrap();
+        assert_eq!(
+            next,
+            Some(Utc.with_ymd_and_hms(2026, 1, 15, 14, 0, 0).unwrap())
+        );
+
+        assert!(matches!(
+            next_run_after("0 0 9 * * *", "Mars/Olympus", after),
+            Err(ScheduleError::InvalidTimezone(_))
+        ));
+        assert!(matches!(
+            next_run_after("not cron", "UTC", after),
+            Err(ScheduleError::InvalidCron(_))
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+// Per-task-type performance statistics for the admin API
+//
+// Built from `background_tasks` rows so the numbers cover every worker
+// process, unlike the per-process Prometheus histograms.
+
+use crate::background_jobs::entities::background_tasks::{Column, Entity, TaskStatus};
+use crate::glass::aggregator::Aggregator;
+pub use crate::glass::aggregator::DurationStats;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, DbErr};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use utoipa::ToSchema;
+
+/// Look-back window for task statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum StatsWindow {
+    #[serde(rename = "1h")]
+    Hour,
+    #[default]
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+}
+
+impl StatsWindow {
+    pub fn duration(&self) -> Duration {
+        match self {
+            StatsWindow::Hour => Duration::hours(1),
+            StatsWindow::Day => Duration::hours(24),
+            StatsWindow::Week => Duration::days(7),
+            StatsWindow::Month => Duration::days(30),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskTypeStats {
+    pub task_type: String,
+    pub completed: u64,
+    pub failed: u64,
+    pub expired: u64,
+    /// Currently pending, regardless of window
+    pub pending: u64,
+    /// Completed tasks per hour over the window
+    pub throughput_per_hour: f64,
+    /// Share of finished tasks that completed; `None` when none finished
+    pub success_rate: Option<f64>,
+    pub failure_rate: Option<f64>,
+    /// `started_at` to `completed_at` of completed tasks
+    pub duration: DurationStats,
+    /// `created_at` to `started_at` of tasks started in the window
+    pub queue_lag: DurationStats,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TaskStatsResponse {
+    pub window: StatsWindow,
+    pub since: String,
+    pub generated_at: String,
+    pub task_types: Vec<TaskTypeStats>,
+}
+
+fn status_is(status: TaskStatus) -> Condition {
+    Condition::all().add(Column::Status.eq(status.as_str()))
+}
+
+impl TaskStatsResponse {
+    pub async fn collect(
+        db: &DatabaseConnection,
+        window: StatsWindow,
+    ) -> Result<TaskStatsResponse, DbErr> {
+        let now = Utc::now();
+        let since = now - window.duration();
+
+        let (completed, failed, expired, pending, durations, lags) = tokio::join!(
+            Aggregator::grouped_count_since::<Entity>(
+                db,
+                Column::TaskType,
+                Column::UpdatedAt,
+                since,
+                status_is(TaskStatus::Completed),
+            ),
+            Aggregator::grouped_count_since::<Entity>(
+                db,
+                Column::TaskType,
+                Column::UpdatedAt,
+                since,
+                status_is(TaskStatus::Failed),
+            ),
+            Aggregator::grouped_count_since::<Entity>(
+                db,
+                Column::TaskType,
+                Column::UpdatedAt,
+                since,
+                status_is(TaskStatus::Expired),
+            ),
+            Aggregator::grouped_count_since::<Entity>(
+                db,
+                Column::TaskType,
+                Column::CreatedAt,
+                DateTime::<Utc>::UNIX_EPOCH,
+                status_is(TaskStatus::Pending),
+            ),
+            Aggregator::grouped_durations_since::<Entity>(
+                db,
+                Column::TaskType,
+                Column::StartedAt,
+                Column::CompletedAt,
+                since,
+                status_is(TaskStatus::Completed),
+            ),
+            Aggregator::grouped_durations_since::<Entity>(
+                db,
+                Column::TaskType,
+                Column::CreatedAt,
+                Column::StartedAt,
+                since,
+                Condition::all(),
+            ),
+        );
+        let (completed, failed, expired, pending, durations, lags) =
+            (completed?, failed?, expired?, pending?, durations?, lags?);
+
+        let task_types: BTreeSet<&String> = completed
+            .keys()
+            .chain(failed.keys())
+            .chain(expired.keys())
+            .chain(pending.keys())
+            .chain(lags.keys())
+            .collect();
+
+        let count = |counts: &HashMap<String, u64>, task_type: &str| {
+            counts.get(task_type).copied().unwrap_or(0)
+        };
+        let window_hours = window.duration().num_seconds() as f64 / 3600.0;
+
+        let task_types = task_types
+            .into_iter()
+            .map(|task_type| {
+                let completed = count(&completed, task_type);
+                let failed = count(&failed, task_type);
+                let finished = completed + failed;
+                TaskTypeStats {
+                    task_type: task_type.clone(),
+                    completed,
+                    failed,
+                    expired: count(&expired, task_type),
+                    pending: count(&pending, task_type),
+                    throughput_per_hour: completed as f64 / window_hours,
+                    success_rate: (finished > 0).then(|| completed as f64 / finished as f64),
+                    failure_rate: (finished > 0).then(|| failed as f64 / finished as f64),
+                    duration: durations.get(task_type).cloned().unwrap_or_default(),
+                    queue_lag: lags.get(task_type).cloned().unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Ok(TaskStatsResponse {
+            window,
+            since: since.to_rfc3339(),
+            generated_at: now.to_rfc3339(),
+            task_types,
+        })
+    }
+}
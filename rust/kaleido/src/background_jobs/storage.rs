@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
 
 /// Task status enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -10,6 +12,7 @@ pub enum TaskStatus {
     Canceled,
     Completed,
     Failed,
+    Expired,
 }
 
 impl TaskStatus {
@@ -20,6 +23,7 @@ impl TaskStatus {
             TaskStatus::Canceled => "canceled",
             TaskStatus::Completed => "completed",
             TaskStatus::Failed => "failed",
+            TaskStatus::Expired => "expired",
         }
     }
 
@@ -30,6 +34,7 @@ impl TaskStatus {
             "canceled" => Some(TaskStatus::Canceled),
             "completed" => Some(TaskStatus::Completed),
             "failed" => Some(TaskStatus::Failed),
+            "expired" => Some(TaskStatus::Expired),
             _ => None,
         }
     }
@@ -41,17 +46,176 @@ pub struct TaskRecord {
     pub id: String,
     pub task_type: String,
     pub payload: serde_json::Value,
+    pub payload_version: i32,
+    pub concurrency_key: Option<String>,
+    pub batch_id: Option<i32>,
+    pub metadata: Option<serde_json::Value>,
     pub status: TaskStatus,
     pub attempts: i32,
     pub max_attempts: i32,
     pub error: Option<String>,
     pub scheduled_for: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
 }
 
+impl TaskRecord {
+    /// Whether the task missed its deadline and should no longer be processed
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+}
+
+/// Payload version of task types that never declared one
+pub const DEFAULT_PAYLOAD_VERSION: i32 = 1;
+
+fn payload_versions() -> &'static RwLock<HashMap<String, i32>> {
+    static VERSIONS: OnceLock<RwLock<HashMap<String, i32>>> = OnceLock::new();
+    VERSIONS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Stamp new tasks of `task_type` with `version`
+///
+/// The registry is process-wide, so every `TaskQueue` in the process agrees
+/// with the processor. Workers register their processors' versions; other
+/// processes that enqueue a versioned task type call this at startup.
+pub fn register_payload_version(task_type: impl Into<String>, version: i32) {
+    payload_versions()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(task_type.into(), version);
+}
+
+/// Version stamped on new tasks of `task_type`
+pub fn payload_version(task_type: &str) -> i32 {
+    payload_versions()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(task_type)
+        .copied()
+        .unwrap_or(DEFAULT_PAYLOAD_VERSION)
+}
+
+/// Options applied when enqueuing a task
+#[derive(Debug, Clone)]
+pub struct EnqueueOptions {
+    pub scheduled_for: Option<DateTime<Utc>>,
+    pub max_attempts: i32,
+    /// Schema version of the serialized payload
+    ///
+    /// `None` uses the version registered with [`register_payload_version`]
+    /// for the task type, falling back to [`DEFAULT_PAYLOAD_VERSION`].
+    pub payload_version: Option<i32>,
+    /// Tasks not started by this time are skipped and marked `expired`
+    pub expires_at: Option<DateTime<Utc>>,
+    /// At most one task per key is processed at a time, across all workers
+    pub concurrency_key: Option<String>,
+    /// Batch created by `TaskStorage::create_batch`
+    pub batch_id: Option<i32>,
+    /// JSON object stored alongside the task, outside the payload
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl Default for EnqueueOptions {
+    fn default() -> Self {
+        Self {
+            scheduled_for: None,
+            max_attempts: 3,
+            payload_version: None,
+            expires_at: None,
+            concurrency_key: None,
+            batch_id: None,
+            metadata: None,
+        }
+    }
+}
+
+impl EnqueueOptions {
+    pub fn payload_version(&self) -> i32 {
+        self.payload_version.unwrap_or(DEFAULT_PAYLOAD_VERSION)
+    }
+
+    pub fn with_scheduled_for(mut self, scheduled_for: DateTime<Utc>) -> Self {
+        self.scheduled_for = Some(scheduled_for);
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_payload_version(mut self, payload_version: i32) -> Self {
+        self.payload_version = Some(payload_version);
+        self
+    }
+
+    pub fn with_expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn with_concurrency_key(mut self, concurrency_key: impl Into<String>) -> Self {
+        self.concurrency_key = Some(concurrency_key.into());
+        self
+    }
+
+    pub fn with_batch_id(mut self, batch_id: i32) -> Self {
+        self.batch_id = Some(batch_id);
+        self
+    }
+
+    /// Set one metadata entry, keeping any others
+    pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        let metadata = self
+            .metadata
+            .get_or_insert_with(|| serde_json::Value::Object(Default::default()));
+        if let Some(entries) = metadata.as_object_mut() {
+            entries.insert(key.into(), value);
+        }
+        self
+    }
+
+    /// Expire the task `ttl` after it is enqueued
+    pub fn with_ttl(self, ttl: chrono::Duration) -> Self {
+        self.with_expires_at(Utc::now() + ttl)
+    }
+}
+
+/// Options applied when starting a batch
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    pub name: Option<String>,
+    /// Task enqueued once every member reaches a terminal state
+    pub callback_task_type: Option<String>,
+    pub callback_payload: Option<serde_json::Value>,
+}
+
+impl BatchOptions {
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Enqueue `task_type` when the batch finishes
+    ///
+    /// The callback receives `{batch_id, total, completed, failed, data}`
+    /// where `data` is `payload`.
+    pub fn with_callback(
+        mut self,
+        task_type: impl Into<String>,
+        payload: serde_json::Value,
+    ) -> Self {
+        self.callback_task_type = Some(task_type.into());
+        self.callback_payload = Some(payload);
+        self
+    }
+}
+
 /// Trait for task storage implementations
 #[async_trait::async_trait]
 pub trait TaskStorage: Send + Sync {
@@ -60,8 +224,7 @@ pub trait TaskStorage: Send + Sync {
         &self,
         task_type: String,
         payload: serde_json::Value,
-        scheduled_for: Option<DateTime<Utc>>,
-        max_attempts: i32,
+        options: EnqueueOptions,
     ) -> Result<TaskRecord, crate::background_jobs::error::TaskError>;
 
     /// Find pending tasks ready to be processed
@@ -89,9 +252,35 @@ pub trait TaskStorage: Send + Sync {
         error: String,
     ) -> Result<TaskRecord, crate::background_jobs::error::TaskError>;
 
+    /// Mark task as expired without processing it
+    async fn mark_expired(
+        &self,
+        id: &str,
+    ) -> Result<TaskRecord, crate::background_jobs::error::TaskError>;
+
     /// Get task by ID
     async fn get_task(
         &self,
         id: &str,
     ) -> Result<Option<TaskRecord>, crate::background_jobs::error::TaskError>;
+
+    /// Create an open batch and return its ID
+    async fn create_batch(
+        &self,
+        _options: BatchOptions,
+    ) -> Result<i32, crate::background_jobs::error::TaskError> {
+        Err(crate::background_jobs::error::TaskError::Storage(
+            "Batches are not supported by this storage".to_string(),
+        ))
+    }
+
+    /// Stop accepting tasks into a batch so it can finish
+    async fn seal_batch(
+        &self,
+        _batch_id: i32,
+    ) -> Result<(), crate::background_jobs::error::TaskError> {
+        Err(crate::background_jobs::error::TaskError::Storage(
+            "Batches are not supported by this storage".to_string(),
+        ))
+    }
 }
@@ -0,0 +1,130 @@
+// W3C trace context carried from enqueue to task processing
+//
+// `TaskQueue` stores the `traceparent` and `tracestate` of the enqueuing
+// span in the task's `metadata`, and the worker makes that context the
+// parent of its processing span. Both ends are no-ops unless the crate is
+// built with the `otel` feature and a text map propagator is installed
+// (`init_json_tracing` installs one).
+
+use serde_json::{Map, Value};
+
+pub const TRACEPARENT: &str = "traceparent";
+pub const TRACESTATE: &str = "tracestate";
+
+/// Trace context of the current span, as metadata entries
+#[cfg(feature = "otel")]
+pub fn capture() -> Map<String, Value> {
+    use std::collections::HashMap;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let context = tracing::Span::current().context();
+    let mut carrier = HashMap::new();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut carrier)
+    });
+
+    carrier
+        .into_iter()
+        .filter(|(key, _)| key == TRACEPARENT || key == TRACESTATE)
+        .map(|(key, value)| (key, Value::String(value)))
+        .collect()
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn capture() -> Map<String, Value> {
+    Map::new()
+}
+
+/// Merge the current trace context into `metadata`, keeping explicit entries
+pub fn inject(metadata: &mut Option<Value>) {
+    let captured = capture();
+    if captured.is_empty() {
+        return;
+    }
+
+    let metadata = metadata.get_or_insert_with(|| Value::Object(Map::new()));
+    if let Some(entries) = metadata.as_object_mut() {
+        for (key, value) in captured {
+            entries.entry(key).or_insert(value);
+        }
+    }
+}
+
+/// Parent `span` on the trace context stored in `metadata`, if any
+#[cfg(feature = "otel")]
+pub fn attach(span: &tracing::Span, metadata: Option<&Value>) {
+    use std::collections::HashMap;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let Some(entries) = metadata.and_then(Value::as_object) else {
+        return;
+    };
+    let carrier: HashMap<String, String> = [TRACEPARENT, TRACESTATE]
+        .into_iter()
+        .filter_map(|key| {
+            let value = entries.get(key)?.as_str()?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+    if !carrier.contains_key(TRACEPARENT) {
+        return;
+    }
+
+    let context =
+        opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&carrier));
+    if let Err(error) = span.set_parent(context) {
+        tracing::debug!(?error, "Could not restore task trace context");
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn attach(_span: &tracing::Span, _metadata: Option<&Value>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_keeps_explicit_entries() {
+        let mut metadata = Some(serde_json::json!({ "tenant": "acme" }));
+        inject(&mut metadata);
+        assert_eq!(metadata.unwrap()["tenant"], "acme");
+
+        // Outside a traced span there is no context to capture
+        let mut empty = None;
+        inject(&mut empty);
+        assert!(empty.is_none());
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_processing_span_joins_enqueuing_trace() {
+        use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+        use opentelemetry_sdk::propagation::TraceContextPropagator;
+        use opentelemetry_sdk::trace::SdkTracerProvider;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!("request");
+            let metadata = request.in_scope(|| {
+                let mut metadata = None;
+                inject(&mut metadata);
+                metadata
+            });
+            assert!(metadata.as_ref().unwrap()[TRACEPARENT].is_string());
+
+            let processing = tracing::info_span!("background_task");
+            attach(&processing, metadata.as_ref());
+            assert_eq!(
+                processing.context().span().span_context().trace_id(),
+                request.context().span().span_context().trace_id()
+            );
+        });
+    }
+}
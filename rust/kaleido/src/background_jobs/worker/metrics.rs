@@ -1,19 +1,29 @@
+use crate::background_jobs::worker::queue_sampler::QueueDepth;
 use axum::body::Body;
 use axum::http::header::CONTENT_TYPE;
 use axum::response::Response;
 use axum::{routing::get, Router};
 use prometheus::{
-    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
 };
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 
 pub struct WorkerMetrics {
     registry: Registry,
     tasks_completed: IntCounterVec,
     tasks_failed: IntCounterVec,
+    tasks_expired: IntCounterVec,
+    task_errors: IntCounterVec,
     task_invocations: IntCounterVec,
     task_processing_lag: HistogramVec,
     task_duration_seconds: HistogramVec,
+    queue_pending: IntGaugeVec,
+    queue_processing: IntGaugeVec,
+    queue_scheduled: IntGaugeVec,
+    queue_oldest_pending_age: GaugeVec,
+    sampled_task_types: Mutex<HashSet<String>>,
 }
 
 impl WorkerMetrics {
@@ -42,6 +52,30 @@ impl WorkerMetrics {
             .register(Box::new(tasks_failed.clone()))
             .expect("failed to register tasks_failed metric");
 
+        let tasks_expired = IntCounterVec::new(
+            Opts::new(
+                "tasks_expired_total",
+                "Number of background tasks skipped because they expired",
+            ),
+            &["type"],
+        )
+        .expect("failed to create tasks_expired metric");
+        registry
+            .register(Box::new(tasks_expired.clone()))
+            .expect("failed to register tasks_expired metric");
+
+        let task_errors = IntCounterVec::new(
+            Opts::new(
+                "task_errors_total",
+                "Number of task processing errors by classification",
+            ),
+            &["type", "kind"],
+        )
+        .expect("failed to create task_errors metric");
+        registry
+            .register(Box::new(task_errors.clone()))
+            .expect("failed to register task_errors metric");
+
         let task_invocations = IntCounterVec::new(
             Opts::new(
                 "task_invocations_total",
@@ -80,13 +114,62 @@ impl WorkerMetrics {
             .register(Box::new(task_duration_seconds.clone()))
             .expect("failed to register task_duration_seconds metric");
 
+        let queue_pending = IntGaugeVec::new(
+            Opts::new("queue_pending", "Pending background tasks that are due"),
+            &["type"],
+        )
+        .expect("failed to create queue_pending metric");
+        registry
+            .register(Box::new(queue_pending.clone()))
+            .expect("failed to register queue_pending metric");
+
+        let queue_processing = IntGaugeVec::new(
+            Opts::new("queue_processing", "Background tasks currently processing"),
+            &["type"],
+        )
+        .expect("failed to create queue_processing metric");
+        registry
+            .register(Box::new(queue_processing.clone()))
+            .expect("failed to register queue_processing metric");
+
+        let queue_scheduled = IntGaugeVec::new(
+            Opts::new(
+                "queue_scheduled",
+                "Pending background tasks scheduled for the future",
+            ),
+            &["type"],
+        )
+        .expect("failed to create queue_scheduled metric");
+        registry
+            .register(Box::new(queue_scheduled.clone()))
+            .expect("failed to register queue_scheduled metric");
+
+        let queue_oldest_pending_age = GaugeVec::new(
+            Opts::new(
+                "queue_oldest_pending_age_seconds",
+                "Age of the oldest due pending background task in seconds",
+            ),
+            &["type"],
+        )
+        .expect("failed to create queue_oldest_pending_age metric");
+        registry
+            .register(Box::new(queue_oldest_pending_age.clone()))
+            .expect("failed to register queue_oldest_pending_age metric");
+
         Self {
             registry,
             tasks_completed,
             tasks_failed,
+            tasks_expired,
+            task_errors,
             task_invocations,
             task_processing_lag,
             task_duration_seconds,
+            queue_pending,
+            queue_processing,
+            queue_scheduled,
+            queue_oldest_pending_age,
+            sampled_task_types: Mutex::new(HashSet::new()),
         }
     }
 
@@ -96,6 +179,9 @@ impl WorkerMetrics {
                 .with_label_values(&[*task_type])
                 .inc_by(0);
             self.tasks_failed.with_label_values(&[*task_type]).inc_by(0);
+            self.tasks_expired
+                .with_label_values(&[*task_type])
+                .inc_by(0);
             self.task_invocations
                 .with_label_values(&[*task_type])
                 .inc_by(0);
@@ -106,6 +192,11 @@ impl WorkerMetrics {
                 .with_label_values(&[*task_type])
                 .observe(0.0);
         }
+        self.sampled_task_types
+            .lock()
+            .unwrap()
+            .extend(task_types.iter().map(|t| t.to_string()));
+        self.record_queue_depths(&[]);
     }
 
     pub fn record_invocation(&self, task_type: &str) {
@@ -132,6 +223,51 @@ impl WorkerMetrics {
         self.tasks_failed.with_label_values(&[task_type]).inc();
     }
 
+    pub fn record_expired(&self, task_type: &str) {
+        self.tasks_expired.with_label_values(&[task_type]).inc();
+    }
+
+    pub fn record_error_kind(&self, task_type: &str, kind: &str) {
+        self.task_errors.with_label_values(&[task_type, kind]).inc();
+    }
+
+    /// Replace the queue gauges with a fresh sample.
+    ///
+    /// Task types seen in earlier samples but absent now are set to zero so
+    /// drained queues don't keep reporting stale depths.
+    pub fn record_queue_depths(&self, depths: &[QueueDepth]) {
+        let mut sampled = self.sampled_task_types.lock().unwrap();
+
+        for task_type in sampled.iter() {
+            if depths.iter().all(|d| &d.task_type != task_type) {
+                let labels = [task_type.as_str()];
+                self.queue_pending.with_label_values(&labels).set(0);
+                self.queue_processing.with_label_values(&labels).set(0);
+                self.queue_scheduled.with_label_values(&labels).set(0);
+                self.queue_oldest_pending_age
+                    .with_label_values(&labels)
+                    .set(0.0);
+            }
+        }
+
+        for depth in depths {
+            let labels = [depth.task_type.as_str()];
+            self.queue_pending
+                .with_label_values(&labels)
+                .set(depth.pending as i64);
+            self.queue_processing
+                .with_label_values(&labels)
+                .set(depth.processing as i64);
+            self.queue_scheduled
+                .with_label_values(&labels)
+                .set(depth.scheduled as i64);
+            self.queue_oldest_pending_age
+                .with_label_values(&labels)
+                .set(depth.oldest_pending_age_seconds);
+            sampled.insert(depth.task_type.clone());
+        }
+    }
+
     pub fn render_response(&self) -> Response {
         let encoder = TextEncoder::new();
         let metric_families = self.registry.gather();
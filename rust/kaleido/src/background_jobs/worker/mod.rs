@@ -1,15 +1,22 @@
 mod config;
 mod metrics;
+mod process_error;
 mod processor;
+mod queue_sampler;
 mod scheduler;
 mod startup;
 mod task_worker;
 mod tracing;
+mod upcast;
 
 pub use config::{WorkerConfig, WorkerConfigDefaults};
 pub use metrics::{spawn_metrics_server, WorkerMetrics};
+pub use process_error::ProcessError;
 pub use processor::TaskProcessor;
+pub use queue_sampler::{sample_queue_depths, spawn_queue_sampler, QueueDepth};
+#[allow(deprecated)]
 pub use scheduler::spawn_scheduler;
 pub use startup::WorkerStartupHook;
 pub use task_worker::{TaskWorker, WorkerError};
-pub use tracing::init_json_tracing;
+pub use tracing::{init_json_tracing, shutdown_tracing};
+pub use upcast::{verify_upcasters, UpcastError, UpcastFn, Upcasters};
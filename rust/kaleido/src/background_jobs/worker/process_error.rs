@@ -0,0 +1,107 @@
+use crate::background_jobs::worker::task_worker::WorkerError;
+use std::fmt;
+use std::time::Duration;
+
+/// How a processor wants a failed run handled
+///
+/// Return it boxed from `TaskProcessor::process`. Any other error type is
+/// treated as [`ProcessError::Retry`] without a delay.
+#[derive(Debug)]
+pub enum ProcessError {
+    /// Fail now without using the remaining attempts
+    Permanent(WorkerError),
+    /// Retry while attempts remain, optionally not before `delay`
+    Retry {
+        error: WorkerError,
+        delay: Option<Duration>,
+    },
+    /// Run again after `delay` without consuming an attempt
+    Snooze(Duration),
+}
+
+impl ProcessError {
+    pub fn permanent(error: impl Into<WorkerError>) -> Self {
+        ProcessError::Permanent(error.into())
+    }
+
+    pub fn retry(error: impl Into<WorkerError>) -> Self {
+        ProcessError::Retry {
+            error: error.into(),
+            delay: None,
+        }
+    }
+
+    pub fn retry_after(error: impl Into<WorkerError>, delay: Duration) -> Self {
+        ProcessError::Retry {
+            error: error.into(),
+            delay: Some(delay),
+        }
+    }
+
+    pub fn snooze(delay: Duration) -> Self {
+        ProcessError::Snooze(delay)
+    }
+
+    /// Classify any error returned by a processor
+    pub fn classify(error: WorkerError) -> Self {
+        match error.downcast::<ProcessError>() {
+            Ok(process_error) => *process_error,
+            Err(error) => ProcessError::retry(error),
+        }
+    }
+
+    /// Value stored in `background_tasks.error_kind` and used as a metric label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProcessError::Permanent(_) => "permanent",
+            ProcessError::Retry { .. } => "retry",
+            ProcessError::Snooze(_) => "snooze",
+        }
+    }
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::Permanent(error) => write!(f, "{}", error),
+            ProcessError::Retry { error, .. } => write!(f, "{}", error),
+            ProcessError::Snooze(delay) => write!(f, "snoozed for {}s", delay.as_secs()),
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProcessError::Permanent(error) | ProcessError::Retry { error, .. } => {
+                Some(error.as_ref())
+            }
+            ProcessError::Snooze(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let plain: WorkerError = "boom".into();
+        assert!(matches!(
+            ProcessError::classify(plain),
+            ProcessError::Retry { delay: None, .. }
+        ));
+
+        let permanent: WorkerError = Box::new(ProcessError::permanent("bad payload"));
+        let classified = ProcessError::classify(permanent);
+        assert_eq!(classified.kind(), "permanent");
+        assert_eq!(classified.to_string(), "bad payload");
+
+        let snooze: WorkerError = Box::new(ProcessError::snooze(Duration::from_secs(30)));
+        assert!(matches!(
+            ProcessError::classify(snooze),
+            ProcessError::Snooze(delay) if delay == Duration::from_secs(30)
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::background_jobs::storage::DEFAULT_PAYLOAD_VERSION;
+use crate::background_jobs::worker::upcast::Upcasters;
 use async_trait::async_trait;
 
 #[async_trait]
@@ -8,6 +10,21 @@ pub trait TaskProcessor: Send + Sync {
         None
     }
 
+    /// Payload of each scheduled run, synced to the schedule on worker start
+    fn schedule_payload(&self) -> serde_json::Value {
+        serde_json::json!({})
+    }
+
+    /// Payload version `process` expects; older payloads are upcast first
+    fn payload_version(&self) -> i32 {
+        DEFAULT_PAYLOAD_VERSION
+    }
+
+    /// Migrations from older payload versions to `payload_version`
+    fn upcasters(&self) -> Upcasters {
+        Upcasters::default()
+    }
+
     async fn process(
         &self,
         task_id: i32,
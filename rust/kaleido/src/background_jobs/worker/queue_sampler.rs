@@ -0,0 +1,131 @@
+use crate::background_jobs::entities::background_tasks::{Column, Entity, TaskStatus};
+use crate::background_jobs::worker::metrics::WorkerMetrics;
+use crate::glass::aggregator::Aggregator;
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, FromQueryResult, QueryFilter,
+    QuerySelect,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Point-in-time queue state for one task type
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueDepth {
+    pub task_type: String,
+    /// Pending and due now
+    pub pending: u64,
+    pub processing: u64,
+    /// Pending but `scheduled_for` is still in the future
+    pub scheduled: u64,
+    /// Seconds since the oldest due pending task became due
+    pub oldest_pending_age_seconds: f64,
+}
+
+fn due_pending(now: DateTime<Utc>) -> Condition {
+    Condition::all()
+        .add(Column::Status.eq(TaskStatus::Pending.as_str()))
+        .add(
+            Condition::any()
+                .add(Column::ScheduledFor.is_null())
+                .add(Column::ScheduledFor.lte(now)),
+        )
+}
+
+/// Sample queue depth per task type from `background_tasks`
+pub async fn sample_queue_depths(
+    db: &DatabaseConnection,
+    now: DateTime<Utc>,
+) -> Result<Vec<QueueDepth>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct OldestDue {
+        task_type: String,
+        oldest: Option<DateTime<Utc>>,
+    }
+
+    let epoch = DateTime::<Utc>::UNIX_EPOCH;
+    let (pending, processing, scheduled, oldest) = tokio::join!(
+        Aggregator::grouped_count_since::<Entity>(
+            db,
+            Column::TaskType,
+            Column::CreatedAt,
+            epoch,
+            due_pending(now),
+        ),
+        Aggregator::grouped_count_since::<Entity>(
+            db,
+            Column::TaskType,
+            Column::CreatedAt,
+            epoch,
+            Condition::all().add(Column::Status.eq(TaskStatus::Processing.as_str())),
+        ),
+        Aggregator::grouped_count_since::<Entity>(
+            db,
+            Column::TaskType,
+            Column::CreatedAt,
+            epoch,
+            Condition::all()
+                .add(Column::Status.eq(TaskStatus::Pending.as_str()))
+                .add(Column::ScheduledFor.gt(now)),
+        ),
+        // A scheduled task becomes due at `scheduled_for`, not at creation
+        Entity::find()
+            .select_only()
+            .column(Column::TaskType)
+            .column_as(
+                Expr::cust("MIN(COALESCE(\"scheduled_for\", \"created_at\"))"),
+                "oldest",
+            )
+            .filter(due_pending(now))
+            .group_by(Column::TaskType)
+            .into_model::<OldestDue>()
+            .all(db),
+    );
+    let (pending, processing, scheduled, oldest) = (pending?, processing?, scheduled?, oldest?);
+
+    let oldest: HashMap<String, DateTime<Utc>> = oldest
+        .into_iter()
+        .filter_map(|row| row.oldest.map(|oldest| (row.task_type, oldest)))
+        .collect();
+    let task_types: BTreeSet<&String> = pending
+        .keys()
+        .chain(processing.keys())
+        .chain(scheduled.keys())
+        .collect();
+
+    Ok(task_types
+        .into_iter()
+        .map(|task_type| QueueDepth {
+            task_type: task_type.clone(),
+            pending: pending.get(task_type).copied().unwrap_or(0),
+            processing: processing.get(task_type).copied().unwrap_or(0),
+            scheduled: scheduled.get(task_type).copied().unwrap_or(0),
+            oldest_pending_age_seconds: oldest
+                .get(task_type)
+                .map(|oldest| (now - *oldest).num_milliseconds().max(0) as f64 / 1000.0)
+                .unwrap_or(0.0),
+        })
+        .collect())
+}
+
+/// Periodically sample queue depth into `metrics` so stalled workers still
+/// move a metric that can be alerted on
+pub fn spawn_queue_sampler(
+    db: DatabaseConnection,
+    metrics: Arc<WorkerMetrics>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+            match sample_queue_depths(&db, Utc::now()).await {
+                Ok(depths) => metrics.record_queue_depths(&depths),
+                Err(error) => tracing::warn!(%error, "Failed to sample queue depth"),
+            }
+        }
+    })
+}
@@ -1,6 +1,13 @@
 use cron::Schedule;
 use std::future::Future;
 
+/// Run `enqueue` on a cron schedule inside this process.
+///
+/// Every replica that calls this fires independently. Declare the schedule
+/// with `TaskProcessor::schedule` and enable
+/// `TaskWorker::with_schedule_runner` instead, which fires each run once
+/// across replicas.
+#[deprecated(note = "use TaskProcessor::schedule with TaskWorker::with_schedule_runner")]
 pub fn spawn_scheduler<F, Fut>(schedule_expression: Option<&str>, mut enqueue: F)
 where
     F: FnMut() -> Fut + Send + 'static,
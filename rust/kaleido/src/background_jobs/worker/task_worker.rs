@@ -1,12 +1,23 @@
 use crate::background_jobs::background_tasks;
+use crate::background_jobs::batches::BatchService;
+use crate::background_jobs::cipher::PayloadCipher;
+use crate::background_jobs::durable::DurableStorage;
+use crate::background_jobs::events::{publish_task_event, TaskEvent, TaskEventBus, TaskEventKind};
+use crate::background_jobs::queue::TaskQueue;
+use crate::background_jobs::schedules::{spawn_schedule_runner, DeclaredSchedule, ScheduleService};
+use crate::background_jobs::storage::{self, DEFAULT_PAYLOAD_VERSION};
+use crate::background_jobs::trace_context;
 use crate::background_jobs::worker::metrics::WorkerMetrics;
+use crate::background_jobs::worker::process_error::ProcessError;
 use crate::background_jobs::worker::processor::TaskProcessor;
+use crate::background_jobs::worker::queue_sampler::spawn_queue_sampler;
 use crate::background_jobs::worker::startup::WorkerStartupHook;
+use crate::background_jobs::worker::upcast::Upcasters;
 use sea_orm::DatabaseConnection;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 pub type WorkerError = Box<dyn std::error::Error + Send + Sync>;
 
@@ -15,8 +26,14 @@ pub struct TaskWorker {
     batch_size: u64,
     poll_interval: Duration,
     processors: HashMap<String, Arc<dyn TaskProcessor>>,
+    upcasters: HashMap<String, Upcasters>,
     startup_hooks: Vec<Arc<dyn WorkerStartupHook>>,
     metrics: Option<Arc<WorkerMetrics>>,
+    cipher: Option<Arc<PayloadCipher>>,
+    schedule_poll_interval: Option<Duration>,
+    events: Option<TaskEventBus>,
+    task_queue: Option<TaskQueue<DurableStorage>>,
+    queue_sample_interval: Duration,
 }
 
 impl TaskWorker {
@@ -26,8 +43,14 @@ impl TaskWorker {
             batch_size: 10,
             poll_interval: Duration::from_secs(1),
             processors: HashMap::new(),
+            upcasters: HashMap::new(),
             startup_hooks: Vec::new(),
             metrics: None,
+            cipher: None,
+            schedule_poll_interval: None,
+            events: None,
+            task_queue: None,
+            queue_sample_interval: Duration::from_secs(15),
         }
     }
 
@@ -46,7 +69,59 @@ impl TaskWorker {
         self
     }
 
+    /// Decrypt payloads of sensitive tasks before they reach processors
+    pub fn with_payload_cipher(mut self, cipher: Arc<PayloadCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// How often to sample queue depth gauges; only used with metrics enabled
+    pub fn with_queue_sample_interval(mut self, interval: Duration) -> Self {
+        self.queue_sample_interval = interval;
+        self
+    }
+
+    /// Also deliver task events to an in-process bus (Postgres NOTIFY is always sent)
+    pub fn with_event_bus(mut self, events: TaskEventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Queue for tasks the worker enqueues itself, such as batch callbacks
+    ///
+    /// Pass the application's queue so its cipher and sensitive task types
+    /// apply. Without one, a queue over this worker's database is used.
+    pub fn with_task_queue(mut self, queue: TaskQueue<DurableStorage>) -> Self {
+        self.task_queue = Some(queue);
+        self
+    }
+
+    /// Sync declared schedules and fire due `task_schedules` runs, checking
+    /// every `poll_interval`
+    ///
+    /// Requires the `task_schedules` migration. Runs are claimed in the
+    /// database, so any number of replicas may enable this.
+    pub fn with_schedule_runner(mut self, poll_interval: Duration) -> Self {
+        self.schedule_poll_interval = Some(poll_interval);
+        self
+    }
+
+    /// Also registers the processor's payload version, so tasks enqueued
+    /// from this process are stamped with it
     pub fn register_processor(mut self, processor: Arc<dyn TaskProcessor>) -> Self {
+        let task_type = processor.task_type();
+        let declared = storage::payload_version(task_type);
+        if declared != DEFAULT_PAYLOAD_VERSION && declared != processor.payload_version() {
+            warn!(
+                task_type,
+                declared,
+                processor_version = processor.payload_version(),
+                "Registered payload version differs from the processor's; using the processor's"
+            );
+        }
+        storage::register_payload_version(task_type, processor.payload_version());
+        self.upcasters
+            .insert(processor.task_type().to_string(), processor.upcasters());
         self.processors
             .insert(processor.task_type().to_string(), processor);
         self
@@ -71,6 +146,14 @@ impl TaskWorker {
         );
 
         self.run_startup_hooks().await;
+        let _schedule_runner = self.start_schedule_runner().await;
+        let _queue_sampler = self.metrics.as_ref().map(|metrics| {
+            spawn_queue_sampler(
+                self.db.clone(),
+                Arc::clone(metrics),
+                self.queue_sample_interval,
+            )
+        });
 
         let mut current_interval = self.poll_interval;
         let max_backoff = Duration::from_secs(60);
@@ -104,6 +187,8 @@ impl TaskWorker {
     }
 
     async fn process_batch(&self) -> Result<usize, WorkerError> {
+        self.release_abandoned().await?;
+
         let tasks = background_tasks::Model::find_pending(&self.db, self.batch_size).await?;
         let count = tasks.len();
         debug!(count, "Found pending task batch");
@@ -117,9 +202,74 @@ impl TaskWorker {
         Ok(count)
     }
 
+    /// Requeue or fail tasks left processing by a worker that died, and
+    /// finish batches whose last member's update it lost
+    async fn release_abandoned(&self) -> Result<(), WorkerError> {
+        for task_model in background_tasks::Model::release_abandoned(&self.db).await? {
+            warn!(
+                task_id = task_model.id,
+                task_type = task_model.task_type,
+                status = task_model.status,
+                "Released background task abandoned by its worker"
+            );
+            let kind = if task_model.status == background_tasks::TaskStatus::Failed.as_str() {
+                TaskEventKind::Failed
+            } else {
+                TaskEventKind::Retrying
+            };
+            self.publish_event(kind, &task_model).await;
+            self.record_batch_member(&task_model).await;
+        }
+
+        match BatchService::finish_stalled(&self.task_queue()).await {
+            Ok(0) => {}
+            Ok(finished) => warn!(finished, "Finished stalled background task batches"),
+            Err(error) => error!(%error, "Failed to finish stalled task batches"),
+        }
+        Ok(())
+    }
+
     async fn process_task(&self, task_model: background_tasks::Model) -> Result<(), WorkerError> {
         let task_type = task_model.task_type.as_str();
         let task_id = task_model.id;
+
+        if task_model.is_expired() {
+            let Some(expired) = task_model.mark_expired(&self.db).await? else {
+                debug!(
+                    task_id,
+                    task_type, "Expired background task already left pending"
+                );
+                return Ok(());
+            };
+            self.publish_event(TaskEventKind::Expired, &expired).await;
+            self.record_batch_member(&expired).await;
+            warn!(task_id, task_type, "Skipped expired background task");
+            if let Some(metrics) = &self.metrics {
+                metrics.record_expired(task_type);
+            }
+            return Ok(());
+        }
+
+        let Some(task_model) = task_model.claim(&self.db).await? else {
+            debug!(
+                task_id,
+                task_type,
+                concurrency_key = task_model.concurrency_key.as_deref(),
+                "Background task already claimed or its concurrency key is busy"
+            );
+            return Ok(());
+        };
+
+        let span = info_span!("background_task", task_id, task_type = %task_model.task_type);
+        trace_context::attach(&span, task_model.metadata.as_ref());
+        self.run_claimed(task_model).instrument(span).await
+    }
+
+    /// Run a claimed task inside its processing span and record the outcome
+    async fn run_claimed(&self, task_model: background_tasks::Model) -> Result<(), WorkerError> {
+        let task_type = task_model.task_type.as_str();
+        let task_id = task_model.id;
+
         info!(task_id, task_type, "Starting background task");
 
         if let Some(metrics) = &self.metrics {
@@ -129,21 +279,21 @@ impl TaskWorker {
             metrics.record_processing_lag(task_type, lag_seconds);
         }
 
-        let task_model = task_model.mark_processing(&self.db).await?;
+        self.publish_event(TaskEventKind::Processing, &task_model)
+            .await;
         let started_at = std::time::Instant::now();
         let heartbeat = spawn_processing_heartbeat(
             self.db.clone(),
             task_model.id,
             task_model.task_type.clone(),
-            Duration::from_secs(30),
+            background_tasks::HEARTBEAT_INTERVAL,
         );
 
         let result = match self.processors.get(task_type) {
-            Some(processor) => {
-                processor
-                    .process(task_model.id, task_model.payload.clone())
-                    .await
-            }
+            Some(processor) => match self.prepare_payload(processor.as_ref(), &task_model) {
+                Ok(payload) => processor.process(task_model.id, payload).await,
+                Err(prepare_error) => Err(Box::new(prepare_error) as WorkerError),
+            },
             None => Err(format!("No processor registered for task type: {}", task_type).into()),
         };
         heartbeat.abort();
@@ -154,18 +304,85 @@ impl TaskWorker {
 
         match result {
             Ok(()) => {
-                task_model.mark_completed(&self.db).await?;
+                let Some(completed) = task_model.mark_completed(&self.db).await? else {
+                    warn!(
+                        task_id,
+                        task_type,
+                        "Background task left processing before its outcome was recorded"
+                    );
+                    return Ok(());
+                };
+                self.publish_event(TaskEventKind::Completed, &completed)
+                    .await;
+                self.record_batch_member(&completed).await;
                 info!(task_id, task_type, "Completed background task");
                 if let Some(metrics) = &self.metrics {
                     metrics.record_completed(task_type);
                 }
             }
             Err(process_error) => {
-                let error_message = process_error.to_string();
-                task_model
-                    .mark_failed(&self.db, error_message.clone())
-                    .await?;
-                warn!(task_id, task_type, error = %error_message, "Failed background task");
+                let process_error = ProcessError::classify(process_error);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_error_kind(task_type, process_error.kind());
+                }
+
+                let (failed, error_message) = match process_error {
+                    ProcessError::Snooze(delay) => {
+                        let Some(snoozed) = task_model.snooze(&self.db, delay).await? else {
+                            warn!(
+                                task_id,
+                                task_type,
+                                "Background task left processing before its outcome was recorded"
+                            );
+                            return Ok(());
+                        };
+                        self.publish_event(TaskEventKind::Snoozed, &snoozed).await;
+                        info!(
+                            task_id,
+                            task_type,
+                            delay_secs = delay.as_secs(),
+                            "Snoozed background task"
+                        );
+                        return Ok(());
+                    }
+                    ProcessError::Permanent(error) => {
+                        let error_message = error.to_string();
+                        let failed = task_model
+                            .mark_failed_permanently(&self.db, error_message.clone())
+                            .await?;
+                        (failed, error_message)
+                    }
+                    ProcessError::Retry { error, delay } => {
+                        let error_message = error.to_string();
+                        let failed = task_model
+                            .mark_retry(&self.db, error_message.clone(), delay)
+                            .await?;
+                        (failed, error_message)
+                    }
+                };
+
+                let Some(failed) = failed else {
+                    warn!(
+                        task_id,
+                        task_type,
+                        "Background task left processing before its outcome was recorded"
+                    );
+                    return Ok(());
+                };
+                let kind = if failed.status == background_tasks::TaskStatus::Failed.as_str() {
+                    TaskEventKind::Failed
+                } else {
+                    TaskEventKind::Retrying
+                };
+                self.publish_event(kind, &failed).await;
+                self.record_batch_member(&failed).await;
+                warn!(
+                    task_id,
+                    task_type,
+                    error = %error_message,
+                    error_kind = failed.error_kind.as_deref().unwrap_or_default(),
+                    "Failed background task"
+                );
                 if let Some(metrics) = &self.metrics {
                     metrics.record_failed(task_type);
                 }
@@ -175,6 +392,109 @@ impl TaskWorker {
         Ok(())
     }
 
+    async fn publish_event(&self, kind: TaskEventKind, task_model: &background_tasks::Model) {
+        publish_task_event(
+            &self.db,
+            self.events.as_ref(),
+            TaskEvent::from_model(kind, task_model),
+        )
+        .await;
+    }
+
+    /// Count a task that reached a terminal status toward its batch
+    async fn record_batch_member(&self, task_model: &background_tasks::Model) {
+        let Some(batch_id) = task_model.batch_id else {
+            return;
+        };
+        if task_model.status == background_tasks::TaskStatus::Pending.as_str() {
+            return;
+        }
+
+        if let Err(error) = BatchService::record_finished(&self.task_queue(), batch_id).await {
+            error!(%error, batch_id, task_id = task_model.id, "Failed to update task batch");
+        }
+    }
+
+    /// Queue stamping each registered task type with its processor's payload version
+    fn task_queue(&self) -> TaskQueue<DurableStorage> {
+        match &self.task_queue {
+            Some(queue) => queue.clone(),
+            None => {
+                let mut storage = DurableStorage::new(self.db.clone());
+                if let Some(events) = &self.events {
+                    storage = storage.with_event_bus(events.clone());
+                }
+                let queue = TaskQueue::new(storage);
+                match &self.cipher {
+                    Some(cipher) => queue.with_cipher(Arc::clone(cipher)),
+                    None => queue,
+                }
+            }
+        }
+    }
+
+    /// Decrypt and upcast a stored payload into the shape the processor expects
+    ///
+    /// Retrying cannot fix a payload that fails here, so errors are permanent.
+    fn prepare_payload(
+        &self,
+        processor: &dyn TaskProcessor,
+        task_model: &background_tasks::Model,
+    ) -> Result<serde_json::Value, ProcessError> {
+        let payload = if PayloadCipher::is_encrypted(&task_model.payload) {
+            let cipher = self.cipher.as_ref().ok_or_else(|| {
+                ProcessError::permanent("Encrypted task payload but no payload cipher configured")
+            })?;
+            cipher
+                .decrypt(&task_model.task_type, &task_model.payload)
+                .map_err(ProcessError::permanent)?
+        } else {
+            task_model.payload.clone()
+        };
+
+        let current_version = processor.payload_version();
+        if task_model.payload_version == current_version {
+            return Ok(payload);
+        }
+
+        debug!(
+            task_id = task_model.id,
+            task_type = task_model.task_type,
+            from_version = task_model.payload_version,
+            to_version = current_version,
+            "Upcasting background task payload"
+        );
+
+        self.upcasters
+            .get(processor.task_type())
+            .cloned()
+            .unwrap_or_default()
+            .upcast(payload, task_model.payload_version, current_version)
+            .map_err(ProcessError::permanent)
+    }
+
+    async fn start_schedule_runner(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let poll_interval = self.schedule_poll_interval?;
+
+        let declared: Vec<DeclaredSchedule> = self
+            .processors
+            .values()
+            .filter_map(|processor| {
+                processor.schedule().map(|expression| DeclaredSchedule {
+                    task_type: processor.task_type().to_string(),
+                    cron_expression: expression.to_string(),
+                    payload: processor.schedule_payload(),
+                })
+            })
+            .collect();
+
+        if let Err(schedule_error) = ScheduleService::sync_declared(&self.db, &declared).await {
+            error!(%schedule_error, "Failed to sync declared task schedules");
+        }
+
+        Some(spawn_schedule_runner(self.task_queue(), poll_interval))
+    }
+
     async fn run_startup_hooks(&self) {
         for hook in &self.startup_hooks {
             let hook_name = hook.name();
@@ -215,3 +535,82 @@ fn spawn_processing_heartbeat(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    struct Report;
+
+    #[async_trait]
+    impl TaskProcessor for Report {
+        fn task_type(&self) -> &str {
+            "report"
+        }
+
+        fn payload_version(&self) -> i32 {
+            2
+        }
+
+        async fn process(
+            &self,
+            _task_id: i32,
+            _payload: serde_json::Value,
+        ) -> Result<(), WorkerError> {
+            Ok(())
+        }
+    }
+
+    fn task(payload: serde_json::Value, payload_version: i32) -> background_tasks::Model {
+        let now = chrono::Utc::now();
+        background_tasks::Model {
+            id: 1,
+            task_type: "report".to_string(),
+            payload,
+            payload_version,
+            concurrency_key: None,
+            batch_id: None,
+            metadata: None,
+            status: "processing".to_string(),
+            attempts: 1,
+            max_attempts: 3,
+            error: None,
+            error_kind: None,
+            scheduled_for: None,
+            expires_at: None,
+            created_at: now,
+            updated_at: now,
+            started_at: Some(now),
+            completed_at: None,
+            result: None,
+        }
+    }
+
+    #[test]
+    fn test_prepare_payload_failures_are_permanent() {
+        let worker = TaskWorker::new(DatabaseConnection::default());
+        let cipher = PayloadCipher::new("k1", [7u8; 32]);
+        let encrypted = cipher.encrypt("report", &json!({"id": 1})).unwrap();
+
+        // Encrypted payload without a cipher
+        let error = worker
+            .prepare_payload(&Report, &task(encrypted.clone(), 2))
+            .unwrap_err();
+        assert!(matches!(error, ProcessError::Permanent(_)));
+
+        // Encrypted with a key the worker doesn't have
+        let worker = worker.with_payload_cipher(Arc::new(PayloadCipher::new("k2", [9u8; 32])));
+        let error = worker
+            .prepare_payload(&Report, &task(encrypted, 2))
+            .unwrap_err();
+        assert!(matches!(error, ProcessError::Permanent(_)));
+
+        // No upcaster from version 1
+        let error = worker
+            .prepare_payload(&Report, &task(json!({"id": 1}), 1))
+            .unwrap_err();
+        assert!(matches!(error, ProcessError::Permanent(_)));
+    }
+}
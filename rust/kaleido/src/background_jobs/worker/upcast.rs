@@ -0,0 +1,239 @@
+use crate::background_jobs::worker::processor::TaskProcessor;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Migrates a payload from one version to the next
+pub type UpcastFn = fn(Value) -> Result<Value, String>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpcastError {
+    /// No upcaster registered to move a payload past `from_version`
+    MissingStep { from_version: i32 },
+    /// The payload was written by a newer deployment than this worker supports
+    UnsupportedVersion { version: i32, current_version: i32 },
+    /// An upcaster rejected the payload
+    StepFailed { from_version: i32, message: String },
+}
+
+impl fmt::Display for UpcastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpcastError::MissingStep { from_version } => {
+                write!(
+                    f,
+                    "no upcaster registered for payload version {}",
+                    from_version
+                )
+            }
+            UpcastError::UnsupportedVersion {
+                version,
+                current_version,
+            } => write!(
+                f,
+                "payload version {} is newer than supported version {}",
+                version, current_version
+            ),
+            UpcastError::StepFailed {
+                from_version,
+                message,
+            } => write!(
+                f,
+                "failed to upcast payload from version {}: {}",
+                from_version, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UpcastError {}
+
+/// Chain of payload migrations for a single task type
+///
+/// Each step moves a payload from `from_version` to `from_version + 1`, so a
+/// version 1 payload reaches version 3 by running the steps for 1 and 2.
+#[derive(Debug, Clone, Default)]
+pub struct Upcasters {
+    steps: BTreeMap<i32, UpcastFn>,
+}
+
+impl Upcasters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the step that migrates `from_version` payloads to `from_version + 1`
+    pub fn register(mut self, from_version: i32, step: UpcastFn) -> Self {
+        self.steps.insert(from_version, step);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Registered source versions, oldest first
+    pub fn versions(&self) -> impl Iterator<Item = i32> + '_ {
+        self.steps.keys().copied()
+    }
+
+    /// Migrate `payload` from `version` to `current_version`
+    pub fn upcast(
+        &self,
+        mut payload: Value,
+        version: i32,
+        current_version: i32,
+    ) -> Result<Value, UpcastError> {
+        if version > current_version {
+            return Err(UpcastError::UnsupportedVersion {
+                version,
+                current_version,
+            });
+        }
+
+        for from_version in version..current_version {
+            let step = self
+                .steps
+                .get(&from_version)
+                .ok_or(UpcastError::MissingStep { from_version })?;
+            payload = step(payload).map_err(|message| UpcastError::StepFailed {
+                from_version,
+                message,
+            })?;
+        }
+
+        Ok(payload)
+    }
+}
+
+/// Verify a processor can upcast every version it registered
+///
+/// Intended for processor tests: each registered version must have an
+/// unbroken chain to the processor's current version, and every sample
+/// `(version, payload)` must upcast without error.
+pub fn verify_upcasters(
+    processor: &dyn TaskProcessor,
+    samples: &[(i32, Value)],
+) -> Result<(), UpcastError> {
+    let upcasters = processor.upcasters();
+    let current_version = processor.payload_version();
+
+    for from_version in upcasters.versions() {
+        if from_version >= current_version {
+            return Err(UpcastError::UnsupportedVersion {
+                version: from_version,
+                current_version,
+            });
+        }
+        for step_version in from_version..current_version {
+            if !upcasters.steps.contains_key(&step_version) {
+                return Err(UpcastError::MissingStep {
+                    from_version: step_version,
+                });
+            }
+        }
+    }
+
+    for (version, payload) in samples {
+        upcasters.upcast(payload.clone(), *version, current_version)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    fn v1_to_v2(mut payload: Value) -> Result<Value, String> {
+        let name = payload
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or("missing name")?
+            .to_string();
+        payload["display_name"] = Value::String(name);
+        Ok(payload)
+    }
+
+    fn v2_to_v3(mut payload: Value) -> Result<Value, String> {
+        payload["locale"] = Value::String("en".to_string());
+        Ok(payload)
+    }
+
+    struct VersionedProcessor {
+        upcasters: Upcasters,
+    }
+
+    #[async_trait]
+    impl TaskProcessor for VersionedProcessor {
+        fn task_type(&self) -> &str {
+            "versioned"
+        }
+
+        fn payload_version(&self) -> i32 {
+            3
+        }
+
+        fn upcasters(&self) -> Upcasters {
+            self.upcasters.clone()
+        }
+
+        async fn process(
+            &self,
+            _task_id: i32,
+            _payload: Value,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_upcast_runs_each_step_in_order() {
+        let upcasters = Upcasters::new().register(1, v1_to_v2).register(2, v2_to_v3);
+
+        let payload = upcasters.upcast(json!({"name": "Ada"}), 1, 3).unwrap();
+
+        assert_eq!(
+            payload,
+            json!({"name": "Ada", "display_name": "Ada", "locale": "en"})
+        );
+    }
+
+    #[test]
+    fn test_upcast_rejects_newer_versions() {
+        let upcasters = Upcasters::new();
+
+        assert_eq!(
+            upcasters.upcast(json!({}), 2, 1),
+            Err(UpcastError::UnsupportedVersion {
+                version: 2,
+                current_version: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_upcasters() {
+        let processor = VersionedProcessor {
+            upcasters: Upcasters::new().register(1, v1_to_v2).register(2, v2_to_v3),
+        };
+        assert!(verify_upcasters(&processor, &[(1, json!({"name": "Ada"}))]).is_ok());
+        assert!(matches!(
+            verify_upcasters(&processor, &[(1, json!({}))]),
+            Err(UpcastError::StepFailed {
+                from_version: 1,
+                ..
+            })
+        ));
+
+        let broken = VersionedProcessor {
+            upcasters: Upcasters::new().register(1, v1_to_v2),
+        };
+        assert_eq!(
+            verify_upcasters(&broken, &[]),
+            Err(UpcastError::MissingStep { from_version: 2 })
+        );
+    }
+}
@@ -1,6 +1,10 @@
-use chrono::{Duration, Utc};
-use sea_orm::{prelude::*, sea_query::Alias, ColumnTrait, ExprTrait, FromQueryResult, QuerySelect};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    prelude::*, sea_query::Alias, ColumnTrait, Condition, DatabaseBackend, ExprTrait,
+    FromQueryResult, QueryOrder, QuerySelect,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use utoipa::ToSchema;
 
 /// A named, displayable metric with a machine-readable key and human-readable label.
@@ -73,6 +77,35 @@ impl StatResult {
     }
 }
 
+/// Rows [`Aggregator::grouped_durations_since`] loads per query on backends
+/// that cannot compute percentiles in SQL.
+pub const MAX_DURATION_SAMPLES: u64 = 10_000;
+
+/// Duration percentiles for one group, in seconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct DurationStats {
+    pub samples: u64,
+    pub avg_seconds: Option<f64>,
+    pub p50_seconds: Option<f64>,
+    pub p95_seconds: Option<f64>,
+    pub p99_seconds: Option<f64>,
+    pub max_seconds: Option<f64>,
+}
+
+impl DurationStats {
+    /// Summarize an ascending slice of durations.
+    pub fn from_sorted(sorted: &[f64]) -> Self {
+        Self {
+            samples: sorted.len() as u64,
+            avg_seconds: Aggregator::mean(sorted),
+            p50_seconds: Aggregator::percentile(sorted, 50.0),
+            p95_seconds: Aggregator::percentile(sorted, 95.0),
+            p99_seconds: Aggregator::percentile(sorted, 99.0),
+            max_seconds: sorted.last().copied(),
+        }
+    }
+}
+
 /// Generic database aggregation helpers for admin metrics.
 pub struct Aggregator;
 
@@ -187,4 +220,209 @@ impl Aggregator {
 
         Ok(res.and_then(|r| r.total).unwrap_or(0.0) as u64)
     }
+
+    /// COUNT(*) per distinct `group_col` value for rows matching `condition`
+    /// where `date_col >= since`.
+    pub async fn grouped_count_since<E>(
+        db: &DatabaseConnection,
+        group_col: E::Column,
+        date_col: E::Column,
+        since: DateTime<Utc>,
+        condition: Condition,
+    ) -> Result<HashMap<String, u64>, DbErr>
+    where
+        E: EntityTrait,
+        E::Column: ColumnTrait,
+    {
+        #[derive(FromQueryResult)]
+        struct GroupCount {
+            group_key: String,
+            count: Option<i64>,
+        }
+
+        let rows: Vec<GroupCount> = E::find()
+            .select_only()
+            .column_as(group_col, "group_key")
+            .column_as(group_col.count(), "count")
+            .filter(date_col.gte(since))
+            .filter(condition)
+            .group_by(group_col)
+            .into_model::<GroupCount>()
+            .all(db)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.group_key, r.count.unwrap_or(0) as u64))
+            .collect())
+    }
+
+    /// Summary of the seconds between `start_col` and `end_col` per `group_col`
+    /// value, for rows matching `condition` where `end_col >= since` and both
+    /// are set.
+    ///
+    /// Postgres aggregates in SQL with `percentile_cont`. Other backends load
+    /// at most [`MAX_DURATION_SAMPLES`] of the most recent rows and summarize
+    /// them in Rust, so a busy window is sampled rather than read in full.
+    pub async fn grouped_durations_since<E>(
+        db: &DatabaseConnection,
+        group_col: E::Column,
+        start_col: E::Column,
+        end_col: E::Column,
+        since: DateTime<Utc>,
+        condition: Condition,
+    ) -> Result<HashMap<String, DurationStats>, DbErr>
+    where
+        E: EntityTrait,
+        E::Column: ColumnTrait,
+    {
+        if db.get_database_backend() == DatabaseBackend::Postgres {
+            return Self::grouped_durations_in_sql::<E>(
+                db, group_col, start_col, end_col, since, condition,
+            )
+            .await;
+        }
+
+        #[derive(FromQueryResult)]
+        struct Span {
+            group_key: String,
+            started: DateTime<Utc>,
+            ended: DateTime<Utc>,
+        }
+
+        let rows: Vec<Span> = E::find()
+            .select_only()
+            .column_as(group_col, "group_key")
+            .column_as(start_col, "started")
+            .column_as(end_col, "ended")
+            .filter(start_col.is_not_null())
+            .filter(end_col.gte(since))
+            .filter(condition)
+            .order_by_desc(end_col)
+            .limit(MAX_DURATION_SAMPLES)
+            .into_model::<Span>()
+            .all(db)
+            .await?;
+
+        let mut durations: HashMap<String, Vec<f64>> = HashMap::new();
+        for row in rows {
+            let millis = (row.ended - row.started).num_milliseconds();
+            let seconds = Ord::max(millis, 0) as f64 / 1000.0;
+            durations.entry(row.group_key).or_default().push(seconds);
+        }
+
+        Ok(durations
+            .into_iter()
+            .map(|(group_key, mut values)| {
+                values.sort_by(f64::total_cmp);
+                (group_key, DurationStats::from_sorted(&values))
+            })
+            .collect())
+    }
+
+    async fn grouped_durations_in_sql<E>(
+        db: &DatabaseConnection,
+        group_col: E::Column,
+        start_col: E::Column,
+        end_col: E::Column,
+        since: DateTime<Utc>,
+        condition: Condition,
+    ) -> Result<HashMap<String, DurationStats>, DbErr>
+    where
+        E: EntityTrait,
+        E::Column: ColumnTrait,
+    {
+        #[derive(FromQueryResult)]
+        struct Summary {
+            group_key: String,
+            samples: i64,
+            avg_seconds: Option<f64>,
+            p50_seconds: Option<f64>,
+            p95_seconds: Option<f64>,
+            p99_seconds: Option<f64>,
+            max_seconds: Option<f64>,
+        }
+
+        let seconds = format!(
+            r#"GREATEST(EXTRACT(EPOCH FROM ("{}" - "{}"))::float8, 0)"#,
+            end_col.as_str(),
+            start_col.as_str()
+        );
+        let percentile = |fraction: f64| {
+            Expr::cust(format!(
+                "percentile_cont({fraction}) WITHIN GROUP (ORDER BY {seconds})"
+            ))
+        };
+
+        let rows: Vec<Summary> = E::find()
+            .select_only()
+            .column_as(group_col, "group_key")
+            .column_as(Expr::cust("COUNT(*)"), "samples")
+            .column_as(Expr::cust(format!("AVG({seconds})")), "avg_seconds")
+            .column_as(percentile(0.5), "p50_seconds")
+            .column_as(percentile(0.95), "p95_seconds")
+            .column_as(percentile(0.99), "p99_seconds")
+            .column_as(Expr::cust(format!("MAX({seconds})")), "max_seconds")
+            .filter(start_col.is_not_null())
+            .filter(end_col.gte(since))
+            .filter(condition)
+            .group_by(group_col)
+            .into_model::<Summary>()
+            .all(db)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| {
+                let summary = DurationStats {
+                    samples: r.samples as u64,
+                    avg_seconds: r.avg_seconds,
+                    p50_seconds: r.p50_seconds,
+                    p95_seconds: r.p95_seconds,
+                    p99_seconds: r.p99_seconds,
+                    max_seconds: r.max_seconds,
+                };
+                (r.group_key, summary)
+            })
+            .collect())
+    }
+
+    /// Linearly interpolated percentile (`pct` in 0..=100) of an ascending
+    /// slice, matching Postgres `percentile_cont`.
+    pub fn percentile(sorted: &[f64], pct: f64) -> Option<f64> {
+        let (first, last) = (sorted.first()?, sorted.last()?);
+        let rank = (pct / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        if lower == upper {
+            return Some(sorted[lower]);
+        }
+        let value = sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64);
+        Some(value.clamp(*first, *last))
+    }
+
+    /// Arithmetic mean, `None` when empty.
+    pub fn mean(values: &[f64]) -> Option<f64> {
+        if values.is_empty() {
+            return None;
+        }
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_interpolates() {
+        let values: Vec<f64> = (1..=20).map(f64::from).collect();
+
+        assert_eq!(Aggregator::percentile(&values, 50.0), Some(10.5));
+        assert!((Aggregator::percentile(&values, 95.0).unwrap() - 19.05).abs() < 1e-9);
+        assert_eq!(Aggregator::percentile(&values, 100.0), Some(20.0));
+        assert_eq!(Aggregator::percentile(&values, 0.0), Some(1.0));
+        assert_eq!(Aggregator::percentile(&[], 50.0), None);
+        assert_eq!(Aggregator::percentile(&[4.0], 95.0), Some(4.0));
+        assert_eq!(Aggregator::mean(&[1.0, 2.0, 3.0]), Some(2.0));
+    }
 }
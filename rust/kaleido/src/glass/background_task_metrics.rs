@@ -11,8 +11,10 @@ impl BackgroundTaskMetrics {
         let f_pending = Self::count_by_status(db, "pending");
         let f_completed = Self::count_by_status_since(db, "completed", 24);
         let f_failed = Self::count_by_status_since(db, "failed", 24);
+        let f_expired = Self::count_by_status_since(db, "expired", 24);
 
-        let (pending, completed, failed) = tokio::join!(f_pending, f_completed, f_failed);
+        let (pending, completed, failed, expired) =
+            tokio::join!(f_pending, f_completed, f_failed, f_expired);
 
         vec![
             NamedStat::new("tasks_pending", "Pending Tasks", "current", pending),
@@ -28,6 +30,12 @@ impl BackgroundTaskMetrics {
                 "last 24 hours",
                 failed,
             ),
+            NamedStat::new(
+                "tasks_expired_last_24h",
+                "Expired (24h)",
+                "last 24 hours",
+                expired,
+            ),
         ]
     }
 
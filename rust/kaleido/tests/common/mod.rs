@@ -0,0 +1,29 @@
+use kaleido_migrations::{MigrationTrait, MigratorTrait};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        let mut migrations = kaleido_migrations::external_migrations();
+        migrations.sort_by_key(|m| m.name().to_string());
+        migrations
+    }
+}
+
+/// Fresh in-memory SQLite database with every kaleido migration applied
+///
+/// Each connection to `sqlite::memory:` is its own database, so the pool is
+/// limited to a single connection.
+pub async fn sqlite_db() -> DatabaseConnection {
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    options.max_connections(1).sqlx_logging(false);
+    let db = Database::connect(options)
+        .await
+        .expect("failed to open in-memory sqlite database");
+    Migrator::up(&db, None)
+        .await
+        .expect("failed to run migrations on sqlite");
+    db
+}
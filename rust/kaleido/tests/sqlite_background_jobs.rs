@@ -0,0 +1,897 @@
+mod common;
+
+use async_trait::async_trait;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::{Duration, Utc};
+use common::sqlite_db;
+use kaleido::background_jobs::admin::{
+    self, AdminTaskError, AdminVerified, BackgroundTasksStorage, RescheduleTaskRequest,
+    SetMaxAttemptsRequest, TaskFilter,
+};
+use kaleido::background_jobs::batches::BatchService;
+use kaleido::background_jobs::entities::{background_tasks, task_batches};
+use kaleido::background_jobs::schedules::{
+    DeclaredSchedule, NewSchedule, ScheduleError, ScheduleService,
+};
+use kaleido::background_jobs::stats::{StatsWindow, TaskStatsResponse};
+use kaleido::background_jobs::worker::{
+    sample_queue_depths, TaskProcessor, TaskWorker, WorkerError, WorkerMetrics,
+};
+use kaleido::background_jobs::{
+    register_payload_version, BatchOptions, DurableStorage, EnqueueOptions, PayloadCipher,
+    TaskQueue, TaskStatus, TaskStorage,
+};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde_json::json;
+use std::sync::Arc;
+
+struct AdminState {
+    db: DatabaseConnection,
+}
+
+impl BackgroundTasksStorage for AdminState {
+    fn db(&self) -> &DatabaseConnection {
+        &self.db
+    }
+
+    fn task_queue(&self) -> TaskQueue<DurableStorage> {
+        TaskQueue::new(DurableStorage::new(self.db.clone()))
+    }
+}
+
+struct TestAdmin;
+
+impl AdminVerified for TestAdmin {}
+
+fn admin_state(db: &DatabaseConnection) -> State<Arc<AdminState>> {
+    State(Arc::new(AdminState { db: db.clone() }))
+}
+
+fn task_type_filter(task_type: &str) -> Query<TaskFilter> {
+    Query(TaskFilter {
+        task_type: Some(task_type.to_string()),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_durable_storage_lifecycle() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+
+    let task = storage
+        .enqueue(
+            "send_email".to_string(),
+            json!({"to": "a@example.com"}),
+            EnqueueOptions::default().with_max_attempts(2),
+        )
+        .await
+        .unwrap();
+    assert_eq!(task.status, TaskStatus::Pending);
+
+    let pending = storage.find_pending(10).await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].payload, json!({"to": "a@example.com"}));
+
+    storage.mark_processing(&task.id).await.unwrap();
+    let failed = storage
+        .mark_failed(&task.id, "smtp down".to_string())
+        .await
+        .unwrap();
+    assert_eq!(failed.status, TaskStatus::Pending);
+
+    storage.mark_processing(&task.id).await.unwrap();
+    let completed = storage.mark_completed(&task.id).await.unwrap();
+    assert_eq!(completed.status, TaskStatus::Completed);
+    assert_eq!(completed.attempts, 2);
+    assert!(storage.find_pending(10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_scheduled_tasks_wait_until_due() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+
+    storage
+        .enqueue(
+            "later".to_string(),
+            json!({}),
+            EnqueueOptions::default().with_scheduled_for(Utc::now() + Duration::hours(1)),
+        )
+        .await
+        .unwrap();
+
+    assert!(storage.find_pending(10).await.unwrap().is_empty());
+
+    storage
+        .enqueue("later".to_string(), json!({}), EnqueueOptions::default())
+        .await
+        .unwrap();
+
+    let depths = sample_queue_depths(&db, Utc::now() + Duration::seconds(5))
+        .await
+        .unwrap();
+    assert_eq!(depths.len(), 1);
+    assert_eq!(depths[0].pending, 1);
+    assert_eq!(depths[0].scheduled, 1);
+    assert!(depths[0].oldest_pending_age_seconds >= 5.0);
+}
+
+async fn metrics_text(metrics: &WorkerMetrics) -> String {
+    let body = axum::body::to_bytes(metrics.render_response().into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_queue_depth_sampling_feeds_metrics() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+    let metrics = WorkerMetrics::new("kaleido");
+
+    let report = storage
+        .enqueue("report".to_string(), json!({}), EnqueueOptions::default())
+        .await
+        .unwrap();
+    storage
+        .enqueue("report".to_string(), json!({}), EnqueueOptions::default())
+        .await
+        .unwrap();
+    let digest = storage
+        .enqueue("digest".to_string(), json!({}), EnqueueOptions::default())
+        .await
+        .unwrap();
+    storage.mark_processing(&digest.id).await.unwrap();
+
+    let now = Utc::now() + Duration::seconds(30);
+    let depths = sample_queue_depths(&db, now).await.unwrap();
+    let depth = |task_type: &str| depths.iter().find(|d| d.task_type == task_type).unwrap();
+    assert_eq!(depths.len(), 2);
+    assert_eq!(
+        (depth("report").pending, depth("report").processing),
+        (2, 0)
+    );
+    assert!(depth("report").oldest_pending_age_seconds >= 30.0);
+    assert_eq!(
+        (depth("digest").pending, depth("digest").processing),
+        (0, 1)
+    );
+    assert_eq!(depth("digest").oldest_pending_age_seconds, 0.0);
+
+    metrics.record_queue_depths(&depths);
+    let text = metrics_text(&metrics).await;
+    assert!(text.contains("kaleido_queue_pending{type=\"report\"} 2"));
+    assert!(text.contains("kaleido_queue_processing{type=\"digest\"} 1"));
+
+    // Once a task type drops out of the sample its gauges go back to zero
+    storage.mark_completed(&digest.id).await.unwrap();
+    storage.mark_processing(&report.id).await.unwrap();
+    let depths = sample_queue_depths(&db, now).await.unwrap();
+    assert_eq!(depths.len(), 1);
+
+    metrics.record_queue_depths(&depths);
+    let text = metrics_text(&metrics).await;
+    assert!(text.contains("kaleido_queue_pending{type=\"report\"} 1"));
+    assert!(text.contains("kaleido_queue_processing{type=\"report\"} 1"));
+    assert!(text.contains("kaleido_queue_processing{type=\"digest\"} 0"));
+    assert!(text.contains("kaleido_queue_oldest_pending_age_seconds{type=\"digest\"} 0"));
+}
+
+#[tokio::test]
+async fn test_claim_respects_concurrency_key() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+    let options = EnqueueOptions::default().with_concurrency_key("feed:1");
+
+    storage
+        .enqueue("sync_feed".to_string(), json!({}), options.clone())
+        .await
+        .unwrap();
+    storage
+        .enqueue("sync_feed".to_string(), json!({}), options)
+        .await
+        .unwrap();
+
+    let pending = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 2);
+
+    let claimed = pending[0].claim(&db).await.unwrap();
+    assert!(claimed.is_some());
+    assert!(pending[1].claim(&db).await.unwrap().is_none());
+    // A task claimed once cannot be claimed again
+    assert!(pending[0].claim(&db).await.unwrap().is_none());
+
+    let pending = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap();
+    assert!(pending.is_empty());
+
+    claimed.unwrap().mark_completed(&db).await.unwrap();
+    let pending = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+}
+
+#[tokio::test]
+async fn test_expire_skips_tasks_that_left_pending() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+
+    storage
+        .enqueue(
+            "digest".to_string(),
+            json!({}),
+            EnqueueOptions::default().with_expires_at(Utc::now() - Duration::seconds(1)),
+        )
+        .await
+        .unwrap();
+    let pending = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap();
+    let claimed = pending[0].claim(&db).await.unwrap().unwrap();
+
+    // The worker's stale copy still reads pending, but another worker owns it
+    assert!(pending[0].mark_expired(&db).await.unwrap().is_none());
+    let task = background_tasks::Entity::find_by_id(claimed.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "processing");
+
+    let expired = storage
+        .enqueue(
+            "digest".to_string(),
+            json!({}),
+            EnqueueOptions::default().with_expires_at(Utc::now() - Duration::seconds(1)),
+        )
+        .await
+        .unwrap();
+    let task = background_tasks::Entity::find_by_id(expired.id.parse::<i32>().unwrap())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    let task = task.mark_expired(&db).await.unwrap().unwrap();
+    assert_eq!(task.status, "expired");
+    assert!(task.mark_expired(&db).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_batch_enqueues_callback_once_all_members_finish() {
+    let db = sqlite_db().await;
+    let queue = TaskQueue::new(DurableStorage::new(db.clone()));
+
+    let batch = queue
+        .start_batch_with(
+            BatchOptions::default()
+                .with_name("import")
+                .with_callback("import_done", json!({"import_id": 7})),
+        )
+        .await
+        .unwrap();
+    let batch_id = batch.id();
+    let first = batch
+        .enqueue("import_row".to_string(), json!({"row": 1}))
+        .await
+        .unwrap();
+    let second = batch
+        .enqueue("import_row".to_string(), json!({"row": 2}))
+        .await
+        .unwrap();
+    batch.seal().await.unwrap();
+
+    queue.mark_processing(&first.id).await.unwrap();
+    queue.mark_completed(&first.id).await.unwrap();
+    queue.mark_expired(&second.id).await.unwrap();
+
+    let batch = task_batches::Entity::find_by_id(batch_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((batch.total, batch.completed, batch.failed), (2, 1, 1));
+    assert!(batch.finished_at.is_some());
+
+    let callbacks = background_tasks::Entity::find()
+        .filter(background_tasks::Column::TaskType.eq("import_done"))
+        .all(&db)
+        .await
+        .unwrap();
+    assert_eq!(callbacks.len(), 1);
+    assert_eq!(Some(callbacks[0].id), batch.callback_task_id);
+    assert_eq!(callbacks[0].payload["data"], json!({"import_id": 7}));
+    assert_eq!(callbacks[0].payload["failed"], json!(1));
+}
+
+#[tokio::test]
+async fn test_batch_finishes_when_a_member_update_was_lost() {
+    let db = sqlite_db().await;
+    let queue = TaskQueue::new(DurableStorage::new(db.clone()));
+
+    let batch = queue
+        .start_batch_with(BatchOptions::default().with_callback("import_done", json!({})))
+        .await
+        .unwrap();
+    let batch_id = batch.id();
+    let member = batch
+        .enqueue("import_row".to_string(), json!({"row": 1}))
+        .await
+        .unwrap();
+    batch.seal().await.unwrap();
+    assert_eq!(BatchService::finish_stalled(&queue).await.unwrap(), 0);
+
+    // The member finished, but the process died before recounting the batch
+    let claimed = background_tasks::Entity::find_by_id(member.id.parse::<i32>().unwrap())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .claim(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    claimed.mark_completed(&db).await.unwrap().unwrap();
+    assert!(BatchService::get(&db, batch_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .finished_at
+        .is_none());
+
+    assert_eq!(BatchService::finish_stalled(&queue).await.unwrap(), 1);
+    let batch = BatchService::get(&db, batch_id).await.unwrap().unwrap();
+    assert_eq!((batch.total, batch.completed, batch.failed), (1, 1, 0));
+    assert!(batch.callback_task_id.is_some());
+    assert_eq!(BatchService::finish_stalled(&queue).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_schedule_fires_once_per_run() {
+    let db = sqlite_db().await;
+
+    ScheduleService::create(
+        &db,
+        NewSchedule {
+            name: "cleanup".to_string(),
+            task_type: "archive".to_string(),
+            cron_expression: "0 * * * * *".to_string(),
+            timezone: "UTC".to_string(),
+            payload: json!({}),
+            enabled: true,
+        },
+    )
+    .await
+    .unwrap();
+
+    register_payload_version("archive", 2);
+    let queue = TaskQueue::new(DurableStorage::new(db.clone()));
+    let due = Utc::now() + Duration::minutes(2);
+    assert_eq!(ScheduleService::fire_due(&queue, due).await.unwrap(), 1);
+    assert_eq!(ScheduleService::fire_due(&queue, due).await.unwrap(), 0);
+
+    let tasks = background_tasks::Entity::find().all(&db).await.unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].task_type, "archive");
+    assert_eq!(tasks[0].payload_version, 2);
+
+    let duplicate = ScheduleService::create(
+        &db,
+        NewSchedule {
+            name: "cleanup".to_string(),
+            task_type: "cleanup".to_string(),
+            cron_expression: "0 * * * * *".to_string(),
+            timezone: "UTC".to_string(),
+            payload: json!({}),
+            enabled: true,
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(duplicate, ScheduleError::DuplicateName(_)));
+    assert_eq!(
+        AdminTaskError::from(duplicate).into_response().status(),
+        StatusCode::CONFLICT
+    );
+}
+
+#[tokio::test]
+async fn test_sync_declared_schedules() {
+    let db = sqlite_db().await;
+    let declared = |task_type: &str, payload| DeclaredSchedule {
+        task_type: task_type.to_string(),
+        cron_expression: "0 0 * * * *".to_string(),
+        payload,
+    };
+
+    ScheduleService::sync_declared(
+        &db,
+        &[
+            declared("digest", json!({"limit": 50})),
+            declared("cleanup", json!({})),
+        ],
+    )
+    .await
+    .unwrap();
+    let schedules = ScheduleService::list(&db).await.unwrap();
+    assert_eq!(schedules.len(), 2);
+    let digest = schedules.iter().find(|s| s.name == "digest").unwrap();
+    assert_eq!(digest.payload, json!({"limit": 50}));
+
+    // A schedule dropped from code stops firing
+    ScheduleService::sync_declared(&db, &[declared("digest", json!({"limit": 50}))])
+        .await
+        .unwrap();
+    let schedules = ScheduleService::list(&db).await.unwrap();
+    let enabled: Vec<_> = schedules
+        .iter()
+        .map(|s| (s.name.as_str(), s.enabled))
+        .collect();
+    assert_eq!(enabled, vec![("cleanup", false), ("digest", true)]);
+
+    // Declaring it again, e.g. once a rolling deploy finishes, turns it back
+    // on, and payload changes are picked up
+    let both = [
+        declared("digest", json!({"limit": 10})),
+        declared("cleanup", json!({})),
+    ];
+    let (first, second) = tokio::join!(
+        ScheduleService::sync_declared(&db, &both),
+        ScheduleService::sync_declared(&db, &both),
+    );
+    first.unwrap();
+    second.unwrap();
+    let schedules = ScheduleService::list(&db).await.unwrap();
+    let synced: Vec<_> = schedules
+        .iter()
+        .map(|s| (s.name.as_str(), s.enabled, s.payload.clone()))
+        .collect();
+    assert_eq!(
+        synced,
+        vec![
+            ("cleanup", true, json!({})),
+            ("digest", true, json!({"limit": 10})),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_stats_collect_on_sqlite() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+
+    let task = storage
+        .enqueue("report".to_string(), json!({}), EnqueueOptions::default())
+        .await
+        .unwrap();
+    storage.mark_processing(&task.id).await.unwrap();
+    storage.mark_completed(&task.id).await.unwrap();
+
+    let stats = TaskStatsResponse::collect(&db, StatsWindow::default())
+        .await
+        .unwrap();
+    let report = stats
+        .task_types
+        .iter()
+        .find(|t| t.task_type == "report")
+        .expect("report stats");
+    assert_eq!(report.completed, 1);
+    assert_eq!(report.duration.samples, 1);
+    assert_eq!(report.duration.p50_seconds, report.duration.max_seconds);
+    assert_eq!(report.queue_lag.samples, 1);
+}
+
+#[tokio::test]
+async fn test_admin_task_actions() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+    let task = storage
+        .enqueue(
+            "report".to_string(),
+            json!({}),
+            EnqueueOptions::default().with_scheduled_for(Utc::now() + Duration::hours(1)),
+        )
+        .await
+        .unwrap();
+    let id: i32 = task.id.parse().unwrap();
+
+    let Json(task) = admin::run_task_now::<_, TestAdmin>(TestAdmin, admin_state(&db), Path(id))
+        .await
+        .unwrap();
+    assert!(task.scheduled_for.is_none());
+
+    let Json(task) = admin::reschedule_task::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        Path(id),
+        Json(RescheduleTaskRequest {
+            scheduled_for: "2030-01-01T00:00:00Z".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    assert!(task.scheduled_for.unwrap().starts_with("2030-01-01"));
+
+    let Json(task) = admin::set_max_attempts::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        Path(id),
+        Json(SetMaxAttemptsRequest { max_attempts: 5 }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(task.max_attempts, 5);
+
+    let invalid = admin::set_max_attempts::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        Path(id),
+        Json(SetMaxAttemptsRequest { max_attempts: 0 }),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(invalid.into_response().status(), StatusCode::BAD_REQUEST);
+
+    let Json(task) = admin::cancel_task::<_, TestAdmin>(TestAdmin, admin_state(&db), Path(id))
+        .await
+        .unwrap();
+    assert_eq!(task.status, "canceled");
+
+    // Only pending tasks can be run or rescheduled, and canceled is final
+    let again = admin::cancel_task::<_, TestAdmin>(TestAdmin, admin_state(&db), Path(id))
+        .await
+        .unwrap_err();
+    assert_eq!(again.into_response().status(), StatusCode::BAD_REQUEST);
+    let run = admin::run_task_now::<_, TestAdmin>(TestAdmin, admin_state(&db), Path(id))
+        .await
+        .unwrap_err();
+    assert_eq!(run.into_response().status(), StatusCode::BAD_REQUEST);
+
+    let missing = admin::cancel_task::<_, TestAdmin>(TestAdmin, admin_state(&db), Path(9999))
+        .await
+        .unwrap_err();
+    assert_eq!(missing.into_response().status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_admin_bulk_task_actions() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+    let later = EnqueueOptions::default().with_scheduled_for(Utc::now() + Duration::hours(1));
+    for task_type in ["import_row", "import_row", "report"] {
+        storage
+            .enqueue(task_type.to_string(), json!({}), later.clone())
+            .await
+            .unwrap();
+    }
+
+    let empty = admin::bulk_cancel_tasks::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        Query(TaskFilter::default()),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(empty.into_response().status(), StatusCode::BAD_REQUEST);
+
+    let Json(run) = admin::bulk_run_tasks_now::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        task_type_filter("import_row"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(run.updated, 2);
+    assert_eq!(
+        background_tasks::Model::find_pending(&db, 10)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let Json(rescheduled) = admin::bulk_reschedule_tasks::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        task_type_filter("import_row"),
+        Json(RescheduleTaskRequest {
+            scheduled_for: "2030-01-01T00:00:00Z".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(rescheduled.updated, 2);
+    assert!(background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let Json(max_attempts) = admin::bulk_set_max_attempts::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        task_type_filter("import_row"),
+        Json(SetMaxAttemptsRequest { max_attempts: 7 }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(max_attempts.updated, 2);
+
+    let Json(canceled) = admin::bulk_cancel_tasks::<_, TestAdmin>(
+        TestAdmin,
+        admin_state(&db),
+        task_type_filter("import_row"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(canceled.updated, 2);
+
+    let tasks = background_tasks::Entity::find().all(&db).await.unwrap();
+    for task in tasks {
+        if task.task_type == "import_row" {
+            assert_eq!((task.status.as_str(), task.max_attempts), ("canceled", 7));
+        } else {
+            assert_eq!((task.status.as_str(), task.max_attempts), ("pending", 3));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_cancel_while_processing_wins_over_worker_outcome() {
+    let db = sqlite_db().await;
+    let queue = TaskQueue::new(DurableStorage::new(db.clone()));
+    let batch = queue.start_batch().await.unwrap();
+    let batch_id = batch.id();
+    batch
+        .enqueue("import_row".to_string(), json!({"row": 1}))
+        .await
+        .unwrap();
+    batch.seal().await.unwrap();
+
+    let pending = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap();
+    let claimed = pending[0].claim(&db).await.unwrap().unwrap();
+
+    let Json(canceled) =
+        admin::cancel_task::<_, TestAdmin>(TestAdmin, admin_state(&db), Path(claimed.id))
+            .await
+            .unwrap();
+    assert_eq!(canceled.status, "canceled");
+
+    // The worker finishes afterwards; none of its outcomes overwrite the cancel
+    assert!(claimed.mark_completed(&db).await.unwrap().is_none());
+    assert!(claimed
+        .mark_retry(&db, "boom".to_string(), None)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(claimed
+        .mark_failed_permanently(&db, "boom".to_string())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(claimed
+        .snooze(&db, std::time::Duration::from_secs(30))
+        .await
+        .unwrap()
+        .is_none());
+
+    let task = background_tasks::Entity::find_by_id(claimed.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "canceled");
+
+    let batch = task_batches::Entity::find_by_id(batch_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((batch.total, batch.completed, batch.failed), (1, 0, 1));
+    assert!(batch.finished_at.is_some());
+}
+
+#[tokio::test]
+async fn test_retry_fails_once_attempts_run_out() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+    storage
+        .enqueue(
+            "send_email".to_string(),
+            json!({}),
+            EnqueueOptions::default().with_max_attempts(2),
+        )
+        .await
+        .unwrap();
+
+    let task = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap()
+        .remove(0);
+    let claimed = task.claim(&db).await.unwrap().unwrap();
+    let retried = claimed
+        .mark_retry(&db, "smtp down".to_string(), None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(retried.status, "pending");
+    assert_eq!(retried.error_kind.as_deref(), Some("retry"));
+    assert!(retried.completed_at.is_none());
+
+    let claimed = retried.claim(&db).await.unwrap().unwrap();
+    let failed = claimed
+        .mark_retry(&db, "smtp down".to_string(), None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((failed.status.as_str(), failed.attempts), ("failed", 2));
+    assert!(failed.completed_at.is_some());
+}
+
+#[tokio::test]
+async fn test_batch_callback_is_enqueued_through_the_queue() {
+    let db = sqlite_db().await;
+    let cipher = PayloadCipher::new("k1", [7u8; 32]);
+    let queue = TaskQueue::new(DurableStorage::new(db.clone()))
+        .with_cipher(Arc::new(cipher.clone()))
+        .with_sensitive_task_type("import_done");
+
+    let batch = queue
+        .start_batch_with(BatchOptions::default().with_callback("import_done", json!({"id": 7})))
+        .await
+        .unwrap();
+    let batch_id = batch.id();
+    batch
+        .enqueue("import_row".to_string(), json!({"row": 1}))
+        .await
+        .unwrap();
+    BatchService::seal(&queue, batch_id).await.unwrap();
+
+    let member = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap()
+        .remove(0)
+        .claim(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    member.mark_completed(&db).await.unwrap().unwrap();
+    BatchService::record_finished(&queue, batch_id)
+        .await
+        .unwrap();
+
+    let batch = task_batches::Entity::find_by_id(batch_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((batch.total, batch.completed), (1, 1));
+    let callback = background_tasks::Entity::find_by_id(batch.callback_task_id.unwrap())
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(PayloadCipher::is_encrypted(&callback.payload));
+    let payload = cipher.decrypt("import_done", &callback.payload).unwrap();
+    assert_eq!(payload["data"], json!({"id": 7}));
+    assert_eq!(payload["completed"], json!(1));
+}
+
+#[tokio::test]
+async fn test_queue_stamps_registered_payload_versions() {
+    let db = sqlite_db().await;
+    // Created before the bump, like a queue held by a long-lived service
+    let queue = TaskQueue::new(DurableStorage::new(db.clone()));
+    register_payload_version("rollup", 3);
+
+    let registered = queue
+        .enqueue("rollup".to_string(), json!({}))
+        .await
+        .unwrap();
+    assert_eq!(registered.payload_version, 3);
+
+    let explicit = queue
+        .enqueue_with(
+            "rollup".to_string(),
+            json!({}),
+            EnqueueOptions::default().with_payload_version(2),
+        )
+        .await
+        .unwrap();
+    assert_eq!(explicit.payload_version, 2);
+
+    let unregistered = queue
+        .enqueue("cleanup".to_string(), json!({}))
+        .await
+        .unwrap();
+    assert_eq!(unregistered.payload_version, 1);
+}
+
+struct LedgerSync;
+
+#[async_trait]
+impl TaskProcessor for LedgerSync {
+    fn task_type(&self) -> &str {
+        "ledger_sync"
+    }
+
+    fn payload_version(&self) -> i32 {
+        4
+    }
+
+    async fn process(&self, _task_id: i32, _payload: serde_json::Value) -> Result<(), WorkerError> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_registered_processor_versions_reach_every_queue() {
+    let db = sqlite_db().await;
+    let _worker = TaskWorker::new(db.clone()).register_processor(Arc::new(LedgerSync));
+
+    let task = TaskQueue::new(DurableStorage::new(db.clone()))
+        .enqueue("ledger_sync".to_string(), json!({}))
+        .await
+        .unwrap();
+    assert_eq!(task.payload_version, 4);
+}
+
+#[tokio::test]
+async fn test_abandoned_processing_task_releases_its_concurrency_key() {
+    let db = sqlite_db().await;
+    let storage = DurableStorage::new(db.clone());
+    let options = EnqueueOptions::default().with_concurrency_key("feed:1");
+    for _ in 0..2 {
+        storage
+            .enqueue("sync_feed".to_string(), json!({}), options.clone())
+            .await
+            .unwrap();
+    }
+
+    let pending = background_tasks::Model::find_pending(&db, 10)
+        .await
+        .unwrap();
+    let claimed = pending[0].claim(&db).await.unwrap().unwrap();
+    assert!(background_tasks::Model::release_abandoned(&db)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(pending[1].claim(&db).await.unwrap().is_none());
+
+    // The worker crashed: no heartbeat since well past the timeout
+    background_tasks::Entity::update_many()
+        .col_expr(
+            background_tasks::Column::UpdatedAt,
+            Expr::value(Utc::now() - Duration::minutes(10)),
+        )
+        .filter(background_tasks::Column::Id.eq(claimed.id))
+        .exec(&db)
+        .await
+        .unwrap();
+    assert_eq!(
+        background_tasks::Model::find_pending(&db, 10)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let released = background_tasks::Model::release_abandoned(&db)
+        .await
+        .unwrap();
+    assert_eq!(released.len(), 1);
+    assert_eq!(released[0].status, "pending");
+    assert_eq!(released[0].error_kind.as_deref(), Some("retry"));
+    assert!(released[0].completed_at.is_none());
+
+    assert!(pending[1].claim(&db).await.unwrap().is_some());
+}
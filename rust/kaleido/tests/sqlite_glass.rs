@@ -0,0 +1,81 @@
+mod common;
+
+use chrono::Utc;
+use common::sqlite_db;
+use kaleido::glass::cooldown::{BackoffStrategy, CooldownService};
+use kaleido::glass::feature_flags::entities as feature_flags;
+use kaleido::glass::feature_flags::service::FeatureFlagService;
+use sea_orm::{ActiveModelTrait, NotSet, Set};
+
+#[tokio::test]
+async fn test_cooldown_blocks_repeat_actions() {
+    let db = sqlite_db().await;
+    let message = |seconds: i64| format!("retry in {}s", seconds);
+
+    CooldownService::check_and_update(
+        &db,
+        "user",
+        Some(1),
+        "resend",
+        BackoffStrategy::Simple,
+        60,
+        message,
+    )
+    .await
+    .unwrap();
+
+    let err = CooldownService::check_and_update(
+        &db,
+        "user",
+        Some(1),
+        "resend",
+        BackoffStrategy::Simple,
+        60,
+        message,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.retry_after_seconds.is_some());
+
+    CooldownService::check(
+        &db,
+        "user",
+        Some(2),
+        "resend",
+        BackoffStrategy::Simple,
+        60,
+        message,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_feature_flags_on_sqlite() {
+    let db = sqlite_db().await;
+    let now = Utc::now();
+    feature_flags::ActiveModel {
+        id: NotSet,
+        feature_key: Set("sqlite_example".to_string()),
+        enabled: Set(false),
+        description: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+
+    let service = FeatureFlagService::new();
+    let updated = service
+        .update_flag(&db, "sqlite_example", true)
+        .await
+        .unwrap();
+    assert!(updated.enabled);
+    assert!(service.is_enabled("sqlite_example"));
+
+    let flags = FeatureFlagService::list_all(&db).await.unwrap();
+    assert!(flags
+        .iter()
+        .any(|flag| flag.feature_key == "sqlite_example"));
+}
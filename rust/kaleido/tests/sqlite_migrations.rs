@@ -0,0 +1,47 @@
+mod common;
+
+use common::{sqlite_db, Migrator};
+use kaleido::auth::entities::user_identities;
+use kaleido_migrations::MigratorTrait;
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, EntityTrait};
+
+#[tokio::test]
+async fn test_migrations_apply_and_revert_on_sqlite() {
+    let db = sqlite_db().await;
+
+    Migrator::down(&db, None).await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_user_identities_migration_moves_provider_links() {
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    options.max_connections(1).sqlx_logging(false);
+    let db = Database::connect(options).await.unwrap();
+
+    let before = Migrator::migrations()
+        .iter()
+        .position(|m| m.name() == "m20261018_000016_create_user_identities")
+        .unwrap();
+    Migrator::up(&db, Some(before as u32)).await.unwrap();
+
+    db.execute_unprepared(
+        r#"
+INSERT INTO users (pid, email, api_key, name, oauth_provider, oauth_subject, email_verified_at)
+VALUES
+    ('00000000-0000-0000-0000-000000000001', 'sso@example.com', 'key-1', 'SSO', 'github', 'gh-1', CURRENT_TIMESTAMP),
+    ('00000000-0000-0000-0000-000000000002', 'local@example.com', 'key-2', 'Local', NULL, NULL, NULL)
+"#,
+    )
+    .await
+    .unwrap();
+
+    Migrator::up(&db, None).await.unwrap();
+
+    let identities = user_identities::Entity::find().all(&db).await.unwrap();
+    assert_eq!(identities.len(), 1);
+    assert_eq!(identities[0].provider, "github");
+    assert_eq!(identities[0].subject, "gh-1");
+    assert_eq!(identities[0].email.as_deref(), Some("sso@example.com"));
+    assert!(identities[0].email_verified);
+}
@@ -10,6 +10,23 @@ mod m20260210_000001_auth_events;
 mod m20260312_000000_background_tasks_result;
 mod m20260724_000001_remove_oauth_feature_flag;
 mod m20260724_000002_rename_oauth_subject_column;
+mod m20261018_000001_background_tasks_expires_at;
+mod m20261018_000002_background_tasks_payload_version;
+mod m20261018_000003_create_task_schedules;
+mod m20261018_000004_background_tasks_error_kind;
+mod m20261018_000005_background_tasks_concurrency_key;
+mod m20261018_000006_create_task_batches;
+mod m20261018_000007_background_tasks_metadata;
+mod m20261018_000008_create_user_mfa;
+mod m20261018_000009_create_passkeys;
+mod m20261018_000010_refresh_token_families;
+mod m20261018_000011_refresh_token_sessions;
+mod m20261018_000012_hash_refresh_tokens;
+mod m20261018_000013_revoked_access_tokens;
+mod m20261018_000014_users_disabled_at;
+mod m20261018_000015_create_oauth_states;
+mod m20261018_000016_create_user_identities;
+mod m20261018_000017_oauth_states_link_user_id;
 
 pub fn external_migrations() -> Vec<Box<dyn MigrationTrait>> {
     vec![
@@ -23,5 +40,22 @@ pub fn external_migrations() -> Vec<Box<dyn MigrationTrait>> {
         Box::new(m20260312_000000_background_tasks_result::Migration),
         Box::new(m20260724_000001_remove_oauth_feature_flag::Migration),
         Box::new(m20260724_000002_rename_oauth_subject_column::Migration),
+        Box::new(m20261018_000001_background_tasks_expires_at::Migration),
+        Box::new(m20261018_000002_background_tasks_payload_version::Migration),
+        Box::new(m20261018_000003_create_task_schedules::Migration),
+        Box::new(m20261018_000004_background_tasks_error_kind::Migration),
+        Box::new(m20261018_000005_background_tasks_concurrency_key::Migration),
+        Box::new(m20261018_000006_create_task_batches::Migration),
+        Box::new(m20261018_000007_background_tasks_metadata::Migration),
+        Box::new(m20261018_000008_create_user_mfa::Migration),
+        Box::new(m20261018_000009_create_passkeys::Migration),
+        Box::new(m20261018_000010_refresh_token_families::Migration),
+        Box::new(m20261018_000011_refresh_token_sessions::Migration),
+        Box::new(m20261018_000012_hash_refresh_tokens::Migration),
+        Box::new(m20261018_000013_revoked_access_tokens::Migration),
+        Box::new(m20261018_000014_users_disabled_at::Migration),
+        Box::new(m20261018_000015_create_oauth_states::Migration),
+        Box::new(m20261018_000016_create_user_identities::Migration),
+        Box::new(m20261018_000017_oauth_states_link_user_id::Migration),
     ]
 }
@@ -6,56 +6,43 @@ pub struct Migration;
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        manager
-            .get_connection()
-            .execute_unprepared(
-                r#"
-DO $$
-BEGIN
-    IF EXISTS (
-        SELECT 1
-        FROM information_schema.columns
-        WHERE table_name = 'users'
-          AND column_name = 'google_id'
-    ) AND NOT EXISTS (
-        SELECT 1
-        FROM information_schema.columns
-        WHERE table_name = 'users'
-          AND column_name = 'oauth_subject'
-    ) THEN
-        ALTER TABLE users RENAME COLUMN google_id TO oauth_subject;
-    END IF;
-END $$;
-"#,
-            )
-            .await
-            .map(|_| ())
+        rename_if_present(manager, Users::GoogleId, Users::OauthSubject).await
     }
 
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        manager
-            .get_connection()
-            .execute_unprepared(
-                r#"
-DO $$
-BEGIN
-    IF EXISTS (
-        SELECT 1
-        FROM information_schema.columns
-        WHERE table_name = 'users'
-          AND column_name = 'oauth_subject'
-    ) AND NOT EXISTS (
-        SELECT 1
-        FROM information_schema.columns
-        WHERE table_name = 'users'
-          AND column_name = 'google_id'
-    ) THEN
-        ALTER TABLE users RENAME COLUMN oauth_subject TO google_id;
-    END IF;
-END $$;
-"#,
-            )
-            .await
-            .map(|_| ())
+        rename_if_present(manager, Users::OauthSubject, Users::GoogleId).await
     }
 }
+
+/// Rename `from` to `to` only when `from` exists and `to` does not
+///
+/// Checked through the schema manager rather than a `DO $$` block so the
+/// migration also runs on SQLite.
+async fn rename_if_present(
+    manager: &SchemaManager<'_>,
+    from: Users,
+    to: Users,
+) -> Result<(), DbErr> {
+    let table = Users::Table.to_string();
+    if !manager.has_column(&table, &from.to_string()).await?
+        || manager.has_column(&table, &to.to_string()).await?
+    {
+        return Ok(());
+    }
+
+    manager
+        .alter_table(
+            Table::alter()
+                .table(Users::Table)
+                .rename_column(from, to)
+                .to_owned(),
+        )
+        .await
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    GoogleId,
+    OauthSubject,
+}
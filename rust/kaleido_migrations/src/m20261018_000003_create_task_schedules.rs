@@ -0,0 +1,112 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskSchedules::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TaskSchedules::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::Name)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(TaskSchedules::TaskType).string().not_null())
+                    .col(
+                        ColumnDef::new(TaskSchedules::CronExpression)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::Timezone)
+                            .string()
+                            .not_null()
+                            .default("UTC"),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::Payload)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::Enabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(ColumnDef::new(TaskSchedules::Source).string().not_null())
+                    .col(
+                        ColumnDef::new(TaskSchedules::LastRunAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::NextRunAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Index for the schedule runner's due lookup
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_task_schedules_enabled_next_run")
+                    .table(TaskSchedules::Table)
+                    .col(TaskSchedules::Enabled)
+                    .col(TaskSchedules::NextRunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TaskSchedules::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TaskSchedules {
+    Table,
+    Id,
+    Name,
+    TaskType,
+    CronExpression,
+    Timezone,
+    Payload,
+    Enabled,
+    Source,
+    LastRunAt,
+    NextRunAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BackgroundTasks::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(BackgroundTasks::ConcurrencyKey)
+                            .string()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Partial index so only one task per key can be processing; the
+        // syntax is shared by Postgres and SQLite
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+CREATE UNIQUE INDEX IF NOT EXISTS idx_background_tasks_concurrency_key_processing
+    ON background_tasks (concurrency_key)
+    WHERE status = 'processing' AND concurrency_key IS NOT NULL
+"#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "DROP INDEX IF EXISTS idx_background_tasks_concurrency_key_processing",
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BackgroundTasks::Table)
+                    .drop_column(BackgroundTasks::ConcurrencyKey)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum BackgroundTasks {
+    Table,
+    ConcurrencyKey,
+}
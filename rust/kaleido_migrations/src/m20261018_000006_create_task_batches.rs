@@ -0,0 +1,147 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskBatches::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TaskBatches::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TaskBatches::Name).string().null())
+                    .col(
+                        ColumnDef::new(TaskBatches::Total)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(TaskBatches::Completed)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(TaskBatches::Failed)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(TaskBatches::Sealed)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(TaskBatches::CallbackTaskType)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskBatches::CallbackPayload)
+                            .json_binary()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(TaskBatches::CallbackTaskId).integer().null())
+                    .col(
+                        ColumnDef::new(TaskBatches::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskBatches::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskBatches::FinishedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BackgroundTasks::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(BackgroundTasks::BatchId).integer().null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_background_tasks_batch_id")
+                    .table(BackgroundTasks::Table)
+                    .col(BackgroundTasks::BatchId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_background_tasks_batch_id")
+                    .table(BackgroundTasks::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BackgroundTasks::Table)
+                    .drop_column(BackgroundTasks::BatchId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(TaskBatches::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TaskBatches {
+    Table,
+    Id,
+    Name,
+    Total,
+    Completed,
+    Failed,
+    Sealed,
+    CallbackTaskType,
+    CallbackPayload,
+    CallbackTaskId,
+    CreatedAt,
+    UpdatedAt,
+    FinishedAt,
+}
+
+#[derive(DeriveIden)]
+enum BackgroundTasks {
+    Table,
+    BatchId,
+}
@@ -0,0 +1,175 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum PasskeyCredentials {
+    Table,
+    Id,
+    UserId,
+    CredentialId,
+    PublicKey,
+    Algorithm,
+    SignCount,
+    Name,
+    Aaguid,
+    CreatedAt,
+    LastUsedAt,
+}
+
+#[derive(DeriveIden)]
+enum PasskeyChallenges {
+    Table,
+    Id,
+    Challenge,
+    Purpose,
+    UserId,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PasskeyCredentials::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::CredentialId)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::PublicKey)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::Algorithm)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::SignCount)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(PasskeyCredentials::Name).string().not_null())
+                    .col(ColumnDef::new(PasskeyCredentials::Aaguid).string().null())
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("CURRENT_TIMESTAMP")),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyCredentials::LastUsedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_passkey_credentials_user")
+                            .from(PasskeyCredentials::Table, PasskeyCredentials::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_passkey_credentials_user_id")
+                    .table(PasskeyCredentials::Table)
+                    .col(PasskeyCredentials::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PasskeyChallenges::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PasskeyChallenges::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyChallenges::Challenge)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyChallenges::Purpose)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PasskeyChallenges::UserId).integer().null())
+                    .col(
+                        ColumnDef::new(PasskeyChallenges::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PasskeyChallenges::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("CURRENT_TIMESTAMP")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_passkey_challenges_user")
+                            .from(PasskeyChallenges::Table, PasskeyChallenges::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PasskeyChallenges::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PasskeyCredentials::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One column per statement; SQLite cannot add several at once
+        for mut column in [
+            ColumnDef::new(RefreshTokens::FamilyId)
+                .uuid()
+                .null()
+                .to_owned(),
+            ColumnDef::new(RefreshTokens::ParentToken)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(RefreshTokens::RotatedAt)
+                .timestamp_with_time_zone()
+                .null()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RefreshTokens::Table)
+                        .add_column_if_not_exists(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_refresh_tokens_family_id")
+                    .table(RefreshTokens::Table)
+                    .col(RefreshTokens::FamilyId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_refresh_tokens_family_id")
+                    .table(RefreshTokens::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [
+            RefreshTokens::RotatedAt,
+            RefreshTokens::ParentToken,
+            RefreshTokens::FamilyId,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RefreshTokens::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum RefreshTokens {
+    Table,
+    FamilyId,
+    ParentToken,
+    RotatedAt,
+}
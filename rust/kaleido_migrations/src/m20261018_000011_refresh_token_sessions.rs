@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One column per statement; SQLite cannot add several at once
+        for mut column in [
+            ColumnDef::new(RefreshTokens::LastUsedAt)
+                .timestamp_with_time_zone()
+                .null()
+                .to_owned(),
+            ColumnDef::new(RefreshTokens::Ip).string().null().to_owned(),
+            ColumnDef::new(RefreshTokens::UserAgent)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(RefreshTokens::Label)
+                .string()
+                .null()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RefreshTokens::Table)
+                        .add_column_if_not_exists(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            RefreshTokens::Label,
+            RefreshTokens::UserAgent,
+            RefreshTokens::Ip,
+            RefreshTokens::LastUsedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RefreshTokens::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum RefreshTokens {
+    Table,
+    LastUsedAt,
+    Ip,
+    UserAgent,
+    Label,
+}
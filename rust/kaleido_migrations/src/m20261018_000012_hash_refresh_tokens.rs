@@ -0,0 +1,28 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Refresh tokens are now stored as an HMAC under a key the database
+        // never sees, so existing raw tokens cannot be converted here. Drop
+        // them; users sign in again once.
+        manager
+            .exec_stmt(Query::delete().from_table(RefreshTokens::Table).to_owned())
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Hashed rows are useless to code that looks tokens up raw
+        manager
+            .exec_stmt(Query::delete().from_table(RefreshTokens::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum RefreshTokens {
+    Table,
+}
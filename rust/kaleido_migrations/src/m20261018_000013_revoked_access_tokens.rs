@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum RevokedAccessTokens {
+    Table,
+    Jti,
+    UserPid,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum RefreshTokens {
+    Table,
+    AccessTokenJti,
+    AccessTokenExpiresAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RevokedAccessTokens::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RevokedAccessTokens::Jti)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RevokedAccessTokens::UserPid).uuid().null())
+                    .col(
+                        ColumnDef::new(RevokedAccessTokens::ExpiresAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RevokedAccessTokens::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("CURRENT_TIMESTAMP")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_revoked_access_tokens_expires_at")
+                    .table(RevokedAccessTokens::Table)
+                    .col(RevokedAccessTokens::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The access token issued with each refresh token, so revoking a
+        // session can revoke its access tokens too
+        for mut column in [
+            ColumnDef::new(RefreshTokens::AccessTokenJti)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(RefreshTokens::AccessTokenExpiresAt)
+                .big_integer()
+                .null()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RefreshTokens::Table)
+                        .add_column_if_not_exists(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            RefreshTokens::AccessTokenExpiresAt,
+            RefreshTokens::AccessTokenJti,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RefreshTokens::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(RevokedAccessTokens::Table).to_owned())
+            .await
+    }
+}
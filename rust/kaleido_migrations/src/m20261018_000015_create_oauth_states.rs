@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum OauthStates {
+    Table,
+    Id,
+    State,
+    Provider,
+    CodeVerifier,
+    Nonce,
+    RedirectPath,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OauthStates::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OauthStates::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OauthStates::State)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(OauthStates::Provider).string().not_null())
+                    .col(
+                        ColumnDef::new(OauthStates::CodeVerifier)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OauthStates::Nonce).string().not_null())
+                    .col(ColumnDef::new(OauthStates::RedirectPath).string().null())
+                    .col(
+                        ColumnDef::new(OauthStates::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OauthStates::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("CURRENT_TIMESTAMP")),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OauthStates::Table).to_owned())
+            .await
+    }
+}
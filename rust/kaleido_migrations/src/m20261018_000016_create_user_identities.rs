@@ -0,0 +1,181 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum UserIdentities {
+    Table,
+    Id,
+    UserId,
+    Provider,
+    Subject,
+    Email,
+    EmailVerified,
+    LinkedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    OauthSubject,
+    OauthProvider,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserIdentities::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserIdentities::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UserIdentities::UserId).integer().not_null())
+                    .col(ColumnDef::new(UserIdentities::Provider).string().not_null())
+                    .col(ColumnDef::new(UserIdentities::Subject).string().not_null())
+                    .col(ColumnDef::new(UserIdentities::Email).string().null())
+                    .col(
+                        ColumnDef::new(UserIdentities::EmailVerified)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(UserIdentities::LinkedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("CURRENT_TIMESTAMP")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_identities_user")
+                            .from(UserIdentities::Table, UserIdentities::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_user_identities_provider_subject")
+                    .table(UserIdentities::Table)
+                    .col(UserIdentities::Provider)
+                    .col(UserIdentities::Subject)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_user_identities_user_id")
+                    .table(UserIdentities::Table)
+                    .col(UserIdentities::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let table = Users::Table.to_string();
+        if !manager
+            .has_column(&table, &Users::OauthSubject.to_string())
+            .await?
+        {
+            return Ok(());
+        }
+
+        // Each user's single provider link becomes their first identity
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+INSERT INTO user_identities (user_id, provider, subject, email, email_verified, linked_at)
+SELECT id, oauth_provider, oauth_subject, email, email_verified_at IS NOT NULL, created_at
+    FROM users
+    WHERE oauth_provider IS NOT NULL AND oauth_subject IS NOT NULL
+"#,
+            )
+            .await?;
+
+        // SQLite cannot drop the UNIQUE `oauth_subject` column in place; the
+        // columns stay there, unused, until the table is next rebuilt
+        if manager.get_database_backend() == DatabaseBackend::Postgres {
+            for column in [Users::OauthSubject, Users::OauthProvider] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Users::Table)
+                            .drop_column(column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DatabaseBackend::Postgres {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column_if_not_exists(
+                            ColumnDef::new(Users::OauthSubject)
+                                .string()
+                                .null()
+                                .unique_key(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column_if_not_exists(
+                            ColumnDef::new(Users::OauthProvider).string().null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            // Only one provider fits back into `users`; keep the oldest link
+            manager
+                .get_connection()
+                .execute_unprepared(
+                    r#"
+UPDATE users
+    SET oauth_provider = first.provider, oauth_subject = first.subject
+    FROM (
+        SELECT DISTINCT ON (user_id) user_id, provider, subject
+            FROM user_identities
+            ORDER BY user_id, linked_at, id
+    ) AS first
+    WHERE users.id = first.user_id
+"#,
+                )
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(UserIdentities::Table).to_owned())
+            .await
+    }
+}
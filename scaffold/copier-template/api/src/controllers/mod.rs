@@ -10,6 +10,7 @@ use std::sync::Arc;
 pub fn routes() -> Router<Arc<AppStorage>> {
     Router::new()
         .nest("/api", auth::routes())
+        .merge(auth::jwks_routes())
         .nest("/api/oauth", auth::oauth_routes())
         .nest("/api/admin/feature-flags", feature_flags::admin_routes())
         .nest("/api/feature-flags", feature_flags::public_routes())
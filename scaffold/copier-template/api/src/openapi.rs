@@ -11,13 +11,29 @@ use utoipa::OpenApi;
         auth_openapi::paths::current,
         auth_openapi::paths::refresh,
         auth_openapi::paths::logout,
+        auth_openapi::paths::list_sessions,
+        auth_openapi::paths::revoke_all_sessions,
+        auth_openapi::paths::rename_session,
+        auth_openapi::paths::revoke_session,
         auth_openapi::paths::resend_confirmation,
         auth_openapi::paths::verify_email,
         auth_openapi::paths::forgot_password,
         auth_openapi::paths::reset_password,
+        auth_openapi::paths::request_magic_link,
+        auth_openapi::paths::verify_magic_link,
+        auth_openapi::paths::mfa_status,
+        auth_openapi::paths::mfa_verify,
+        auth_openapi::paths::mfa_totp_enroll,
+        auth_openapi::paths::mfa_totp_confirm,
+        auth_openapi::paths::mfa_totp_disable,
+        auth_openapi::paths::mfa_recovery_codes,
+        auth_openapi::paths::jwks,
         auth_openapi::paths::oauth_providers,
         auth_openapi::paths::oauth_authorize,
         auth_openapi::paths::oauth_callback,
+        auth_openapi::paths::oauth_link,
+        auth_openapi::paths::list_identities,
+        auth_openapi::paths::unlink_identity,
         glass_openapi::paths::public_flags,
         glass_openapi::paths::list_flags,
         glass_openapi::paths::update_flag,
@@ -31,9 +47,24 @@ use utoipa::OpenApi;
             auth_openapi::schemas::ResendConfirmationRequest,
             auth_openapi::schemas::ForgotPasswordRequest,
             auth_openapi::schemas::ResetPasswordRequest,
+            auth_openapi::schemas::MagicLinkRequest,
+            auth_openapi::schemas::MagicLinkVerifyRequest,
             auth_openapi::schemas::UserResponse,
+            auth_openapi::schemas::TokenResponse,
+            auth_openapi::schemas::SessionResponse,
+            auth_openapi::schemas::RenameSessionRequest,
+            auth_openapi::schemas::SessionsRevokedResponse,
+            auth_openapi::schemas::MfaChallengeResponse,
+            auth_openapi::schemas::MfaVerifyRequest,
+            auth_openapi::schemas::MfaCodeRequest,
+            auth_openapi::schemas::MfaStatusResponse,
+            auth_openapi::schemas::TotpCodeRequest,
+            auth_openapi::schemas::TotpEnrollmentResponse,
+            auth_openapi::schemas::RecoveryCodesResponse,
             auth_openapi::schemas::OAuthProviderMetadata,
             auth_openapi::schemas::OAuthProvidersResponse,
+            auth_openapi::schemas::OAuthLinkResponse,
+            auth_openapi::schemas::IdentityResponse,
             glass_openapi::schemas::PublicFlagResponse,
             glass_openapi::schemas::FeatureFlagResponse,
             glass_openapi::schemas::UpdateFlagRequest,
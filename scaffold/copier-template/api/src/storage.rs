@@ -1,9 +1,9 @@
 use crate::config::Config;
-use crate::tasks::TaskQueue;
-use crate::tasks::{create_auth_service, AppAuthService};
+use crate::tasks::{create_auth_service, register_payload_versions, AppAuthService, TaskQueue};
 use kaleido::auth::controllers::oauth::OAuthRouteStorage;
-use kaleido::auth::{AuthRouteStorage, AuthStorage};
+use kaleido::auth::{AuthRouteStorage, AuthStorage, RevocationStore};
 use kaleido::background_jobs::admin::BackgroundTasksStorage;
+use kaleido::background_jobs::{DurableStorage, TaskEventBus};
 use kaleido::glass::feature_flags::{FeatureFlagService, FeatureFlagStorage};
 use migration::MigratorTrait;
 use sea_orm::DatabaseConnection;
@@ -14,6 +14,8 @@ pub struct AppStorage {
     pub tasks: TaskQueue,
     pub feature_flags: FeatureFlagService,
     pub auth_service: AppAuthService,
+    pub revocation_store: Option<RevocationStore>,
+    pub task_events: TaskEventBus,
 }
 
 impl AppStorage {
@@ -26,20 +28,35 @@ impl AppStorage {
             .await
             .expect("Migration failed");
 
-        let tasks = TaskQueue::new(db.clone());
+        register_payload_versions();
+        // Worker transitions arrive over Postgres NOTIFY; enqueues made here
+        // are published to the bus directly
+        let task_events = TaskEventBus::default();
+        task_events.spawn_postgres_listener(&db);
+        let tasks = TaskQueue::from_queue(kaleido::background_jobs::TaskQueue::new(
+            DurableStorage::new(db.clone()).with_event_bus(task_events.clone()),
+        ));
 
         let feature_flags = FeatureFlagService::new();
         if let Err(err) = feature_flags.load_cache(&db).await {
             tracing::warn!(error = ?err, "failed to load feature flags cache");
         }
 
-        let auth_service = create_auth_service(db.clone(), tasks.clone());
+        let revocation_store = Config::get()
+            .auth_token_revocation_enabled
+            .then(RevocationStore::new);
+        let mut auth_service = create_auth_service(db.clone(), tasks.clone());
+        if let Some(store) = &revocation_store {
+            auth_service = auth_service.with_revocation_store(store.clone());
+        }
 
         Self {
             db,
             tasks,
             feature_flags,
             auth_service,
+            revocation_store,
+            task_events,
         }
     }
 }
@@ -58,6 +75,14 @@ impl BackgroundTasksStorage for AppStorage {
     fn db(&self) -> &DatabaseConnection {
         &self.db
     }
+
+    fn task_events(&self) -> Option<&TaskEventBus> {
+        Some(&self.task_events)
+    }
+
+    fn task_queue(&self) -> kaleido::background_jobs::TaskQueue<DurableStorage> {
+        self.tasks.inner().clone()
+    }
 }
 
 impl AuthStorage for AppStorage {
@@ -65,8 +90,20 @@ impl AuthStorage for AppStorage {
         &self.db
     }
 
-    fn jwt_secret(&self) -> &str {
-        &Config::get().jwt_secret
+    fn jwt_keys(&self) -> &kaleido::auth::JwtKeys {
+        &Config::get().jwt_keys
+    }
+
+    fn refresh_token_key(&self) -> &str {
+        &Config::get().refresh_token_key
+    }
+
+    fn token_policy(&self) -> &kaleido::auth::TokenPolicy {
+        &Config::get().token_policy
+    }
+
+    fn revocation_store(&self) -> Option<&RevocationStore> {
+        self.revocation_store.as_ref()
     }
 }
 
@@ -96,6 +133,10 @@ impl AuthRouteStorage for AppStorage {
     fn registration_enabled(&self) -> bool {
         Config::get().auth_registration_enabled
     }
+
+    fn magic_link_enabled(&self) -> bool {
+        Config::get().auth_magic_link_enabled
+    }
 }
 
 impl OAuthRouteStorage for AppStorage {
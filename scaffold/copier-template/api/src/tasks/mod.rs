@@ -1,3 +1,15 @@
 pub mod adapter;
 
 pub use adapter::{create_auth_service, AppAuthService, TaskQueue};
+
+/// Payload versions of task types this app enqueues, as `(task_type, version)`
+///
+/// List a task type here when its processor raises `payload_version`, so the
+/// API stamps new tasks with the version the worker expects.
+pub const PAYLOAD_VERSIONS: &[(&str, i32)] = &[];
+
+pub fn register_payload_versions() {
+    for (task_type, version) in PAYLOAD_VERSIONS {
+        kaleido::background_jobs::register_payload_version(*task_type, *version);
+    }
+}
@@ -20,9 +20,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         poll_interval_secs: 10,
     });
 
+    // Processors warn on registration if these disagree with their versions
+    api::tasks::register_payload_versions();
+
     let worker = TaskWorker::new(db)
         .with_batch_size(worker_config.batch_size)
-        .with_poll_interval(Duration::from_secs(worker_config.poll_interval_secs));
+        .with_poll_interval(Duration::from_secs(worker_config.poll_interval_secs))
+        .with_schedule_runner(Duration::from_secs(5));
 
     let worker = register_auth_email_processors(worker, cfg)?;
     let worker = register_default_processors(worker).await?;
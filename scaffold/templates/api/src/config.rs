@@ -1,3 +1,4 @@
+use kaleido::auth::{JwtKeys, TokenPolicy};
 use once_cell::sync::OnceCell;
 use std::env;
 
@@ -6,9 +7,13 @@ pub struct Config {
     pub database_url: String,
     pub frontend_url: String,
     pub api_url: String,
-    pub jwt_secret: String,
+    pub jwt_keys: JwtKeys,
+    pub refresh_token_key: String,
+    pub token_policy: TokenPolicy,
     pub auth_password_enabled: bool,
     pub auth_registration_enabled: bool,
+    pub auth_magic_link_enabled: bool,
+    pub auth_token_revocation_enabled: bool,
     pub app_name: String,
     pub smtp_host: String,
     pub smtp_port: u16,
@@ -31,9 +36,17 @@ impl Config {
             frontend_url: env::var("FRONTEND_URL")
                 .unwrap_or_else(|_| "http://localhost:5173".to_string()),
             api_url: env::var("API_URL").unwrap_or_else(|_| "http://localhost:3000".to_string()),
-            jwt_secret: env::var("JWT_SECRET").unwrap_or_else(|_| "change_me_in_dev".to_string()),
+            // JWT_KEYS for asymmetric keys, otherwise HS256 with JWT_SECRET
+            jwt_keys: JwtKeys::from_env().expect("invalid JWT key configuration"),
+            // Must match what `EnvConfigProvider` reads for the auth service
+            refresh_token_key: env::var("REFRESH_TOKEN_KEY")
+                .or_else(|_| env::var("JWT_SECRET"))
+                .unwrap_or_else(|_| "change_me_in_dev".to_string()),
+            token_policy: TokenPolicy::from_env(),
             auth_password_enabled: env_bool("AUTH_PASSWORD_ENABLED", true),
             auth_registration_enabled: env_bool("AUTH_REGISTRATION_ENABLED", true),
+            auth_magic_link_enabled: env_bool("AUTH_MAGIC_LINK_ENABLED", false),
+            auth_token_revocation_enabled: env_bool("AUTH_TOKEN_REVOCATION_ENABLED", true),
             app_name: env::var("APP_NAME").unwrap_or_else(|_| "App".to_string()),
             smtp_host: env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string()),
             smtp_port: env::var("SMTP_PORT")